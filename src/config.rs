@@ -0,0 +1,210 @@
+use serde::Deserialize;
+use std::fmt;
+use std::net::ToSocketAddrs;
+use std::path::{ Path, PathBuf };
+
+/// Config file read when neither `--config` nor `INVENTORY_CONFIG` is given.
+const DEFAULT_CONFIG_FILE: &str = "inventory.toml";
+
+const MIB: usize = 1024 * 1024;
+const PAGE_SIZE: usize = 4096;
+
+/// Server settings. Values come from the built-in defaults, then the TOML
+/// config file, then `INVENTORY_*` environment variables, each layer
+/// overriding the previous one.
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub db_path: PathBuf,
+    pub bind_address: String,
+    /// LMDB map size in bytes; caps how large the database can grow.
+    pub map_size: usize,
+    /// HTTP worker threads; actix picks one per core when unset.
+    pub workers: Option<usize>,
+    /// Origins allowed to make cross-origin requests. `"*"` allows any
+    /// origin; an empty list disables CORS entirely.
+    pub cors_allowed_origins: Vec<String>,
+    /// Seed the sample data set on startup when the database is empty.
+    pub seed_on_startup: bool,
+    /// Days of movement history reorder points are computed from.
+    pub reorder_window_days: u32,
+    /// Probability of not running out during a supplier lead time that
+    /// safety stock is sized for, e.g. `0.95`.
+    pub reorder_service_level: f64,
+}
+
+/// Parameters for computing reorder points from movement history.
+#[derive(Debug, Clone, Copy)]
+pub struct ReorderPolicy {
+    pub window_days: u32,
+    pub service_level: f64,
+}
+
+impl ReorderPolicy {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.window_days == 0 {
+            return Err("reorder window must be at least 1 day".to_string());
+        }
+        if !(0.5..1.0).contains(&self.service_level) {
+            return Err(
+                format!("service level must be at least 0.5 and below 1, got {}", self.service_level)
+            );
+        }
+        Ok(())
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            db_path: PathBuf::from("./inventory_db"),
+            bind_address: "127.0.0.1:8080".to_string(),
+            map_size: 1024 * MIB, // 1GB
+            workers: None,
+            cors_allowed_origins: Vec::new(),
+            seed_on_startup: false,
+            reorder_window_days: 90,
+            reorder_service_level: 0.95,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct ConfigError(String);
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid configuration: {}", self.0)
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+impl Config {
+    /// Loads and validates the configuration. An explicitly named config file
+    /// must exist; the default `inventory.toml` is optional.
+    pub fn load(path: Option<&Path>) -> Result<Self, ConfigError> {
+        let explicit = path
+            .map(Path::to_path_buf)
+            .or_else(|| std::env::var_os("INVENTORY_CONFIG").map(PathBuf::from));
+
+        let mut config = match explicit {
+            Some(path) => Self::from_file(&path)?,
+            None if Path::new(DEFAULT_CONFIG_FILE).exists() => {
+                Self::from_file(Path::new(DEFAULT_CONFIG_FILE))?
+            }
+            None => Config::default(),
+        };
+        config.apply_env()?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    pub fn reorder_policy(&self) -> ReorderPolicy {
+        ReorderPolicy {
+            window_days: self.reorder_window_days,
+            service_level: self.reorder_service_level,
+        }
+    }
+
+    fn from_file(path: &Path) -> Result<Self, ConfigError> {
+        let contents = std::fs::read_to_string(path)
+            .map_err(|e| ConfigError(format!("cannot read {}: {}", path.display(), e)))?;
+        toml::from_str(&contents).map_err(|e| ConfigError(format!("{}: {}", path.display(), e)))
+    }
+
+    fn apply_env(&mut self) -> Result<(), ConfigError> {
+        if let Some(value) = env_var("INVENTORY_DB_PATH") {
+            self.db_path = PathBuf::from(value);
+        }
+        if let Some(value) = env_var("INVENTORY_BIND_ADDRESS") {
+            self.bind_address = value;
+        }
+        if let Some(value) = env_var("INVENTORY_MAP_SIZE") {
+            self.map_size = parse_env("INVENTORY_MAP_SIZE", &value)?;
+        }
+        if let Some(value) = env_var("INVENTORY_WORKERS") {
+            self.workers = Some(parse_env("INVENTORY_WORKERS", &value)?);
+        }
+        if let Some(value) = env_var("INVENTORY_CORS_ALLOWED_ORIGINS") {
+            self.cors_allowed_origins = value
+                .split(',')
+                .map(str::trim)
+                .filter(|origin| !origin.is_empty())
+                .map(str::to_string)
+                .collect();
+        }
+        if let Some(value) = env_var("INVENTORY_SEED_ON_STARTUP") {
+            self.seed_on_startup = parse_env("INVENTORY_SEED_ON_STARTUP", &value)?;
+        }
+        if let Some(value) = env_var("INVENTORY_REORDER_WINDOW_DAYS") {
+            self.reorder_window_days = parse_env("INVENTORY_REORDER_WINDOW_DAYS", &value)?;
+        }
+        if let Some(value) = env_var("INVENTORY_REORDER_SERVICE_LEVEL") {
+            self.reorder_service_level = parse_env("INVENTORY_REORDER_SERVICE_LEVEL", &value)?;
+        }
+        Ok(())
+    }
+
+    fn validate(&self) -> Result<(), ConfigError> {
+        let resolves = self.bind_address
+            .to_socket_addrs()
+            .is_ok_and(|mut addrs| addrs.next().is_some());
+        if !resolves {
+            return Err(ConfigError(format!("bind_address '{}' does not resolve", self.bind_address)));
+        }
+
+        if self.map_size < 10 * MIB || !self.map_size.is_multiple_of(PAGE_SIZE) {
+            return Err(
+                ConfigError(
+                    format!(
+                        "map_size must be at least {} bytes and a multiple of {}, got {}",
+                        10 * MIB,
+                        PAGE_SIZE,
+                        self.map_size
+                    )
+                )
+            );
+        }
+
+        // LMDB cannot open a data file larger than the map.
+        let data_file = self.db_path.join("data.mdb");
+        if
+            let Ok(metadata) = std::fs::metadata(&data_file) &&
+            metadata.len() > (self.map_size as u64)
+        {
+            return Err(
+                ConfigError(
+                    format!(
+                        "map_size {} is smaller than the existing data file {} ({} bytes)",
+                        self.map_size,
+                        data_file.display(),
+                        metadata.len()
+                    )
+                )
+            );
+        }
+
+        if self.workers == Some(0) {
+            return Err(ConfigError("workers must be at least 1".to_string()));
+        }
+
+        for origin in &self.cors_allowed_origins {
+            if origin != "*" && !origin.starts_with("http://") && !origin.starts_with("https://") {
+                return Err(
+                    ConfigError(format!("CORS origin '{}' must be \"*\" or an http(s) URL", origin))
+                );
+            }
+        }
+
+        self.reorder_policy().validate().map_err(ConfigError)
+    }
+}
+
+fn env_var(name: &str) -> Option<String> {
+    std::env::var(name).ok().filter(|value| !value.is_empty())
+}
+
+fn parse_env<T: std::str::FromStr>(name: &str, value: &str) -> Result<T, ConfigError> {
+    value.trim().parse().map_err(|_| ConfigError(format!("{} has an invalid value '{}'", name, value)))
+}
@@ -0,0 +1,151 @@
+use serde::{ Serialize, Deserialize };
+use std::fmt;
+
+/// A rule one field of a request broke.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FieldError {
+    pub field: String,
+    pub message: String,
+}
+
+impl FieldError {
+    pub fn new(field: &str, message: impl Into<String>) -> Self {
+        FieldError {
+            field: field.to_string(),
+            message: message.into(),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum InventoryError {
+    Storage(heed::Error),
+    /// The request body, query string or path could not be decoded.
+    Malformed(String),
+    NotFound(String),
+    Conflict(String),
+    Validation(String),
+    /// Field-level validation failures, all reported together.
+    InvalidFields(Vec<FieldError>),
+    InvalidTransition {
+        entity: String,
+        from: String,
+        to: String,
+    },
+    UnknownLocation(String),
+    InactiveLocation(String),
+    UnknownItem(String),
+    /// A field refers to a record that does not exist.
+    UnknownReference {
+        field: String,
+        id: String,
+    },
+    /// A record cannot be deleted while others still point at it.
+    Referenced {
+        entity: String,
+        referrers: Vec<String>,
+    },
+    /// The client edited a version of the record that is no longer current.
+    PreconditionFailed {
+        entity: String,
+        /// Versions the client accepts; empty when it named none that can
+        /// match.
+        expected: Vec<u64>,
+        current: u64,
+    },
+    InvalidMovement(String),
+    BomCycle(Vec<String>),
+    InsufficientStock {
+        item: String,
+        location: String,
+        available: u64,
+        requested: u64,
+    },
+}
+
+impl fmt::Display for InventoryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InventoryError::Storage(e) => write!(f, "storage error: {}", e),
+            InventoryError::Malformed(reason) => write!(f, "malformed request: {}", reason),
+            InventoryError::NotFound(what) => write!(f, "{} not found", what),
+            InventoryError::Conflict(reason) => write!(f, "{}", reason),
+            InventoryError::Validation(reason) => write!(f, "{}", reason),
+            InventoryError::InvalidFields(errors) => {
+                let errors: Vec<String> = errors
+                    .iter()
+                    .map(|error| format!("{}: {}", error.field, error.message))
+                    .collect();
+                write!(f, "invalid fields: {}", errors.join("; "))
+            }
+            InventoryError::InvalidTransition { entity, from, to } =>
+                write!(f, "{} cannot move from {} to {}", entity, from, to),
+            InventoryError::UnknownLocation(location) => write!(f, "unknown location '{}'", location),
+            InventoryError::InactiveLocation(location) => write!(f, "location '{}' is inactive", location),
+            InventoryError::UnknownItem(item) => write!(f, "unknown item '{}'", item),
+            InventoryError::UnknownReference { field, id } => write!(f, "{} '{}' does not exist", field, id),
+            InventoryError::Referenced { entity, referrers } =>
+                write!(f, "{} is still referenced by {}", entity, referrers.join(", ")),
+            InventoryError::PreconditionFailed { entity, expected, current } => {
+                let expected: Vec<String> = expected.iter().map(u64::to_string).collect();
+                if expected.is_empty() {
+                    write!(f, "{} is at version {}, which no strong If-Match tag names", entity, current)
+                } else {
+                    write!(f, "{} is at version {}, not {}", entity, current, expected.join(" or "))
+                }
+            }
+            InventoryError::InvalidMovement(reason) => write!(f, "invalid movement: {}", reason),
+            InventoryError::BomCycle(path) => write!(f, "BOM cycle: {}", path.join(" -> ")),
+            InventoryError::InsufficientStock { item, location, available, requested } =>
+                write!(
+                    f,
+                    "insufficient stock of '{}' at '{}': {} available, {} requested",
+                    item,
+                    location,
+                    available,
+                    requested
+                ),
+        }
+    }
+}
+
+impl InventoryError {
+    /// Stable, machine-readable name of the error kind.
+    pub fn code(&self) -> &'static str {
+        match self {
+            InventoryError::Storage(_) => "storage_error",
+            InventoryError::Malformed(_) => "malformed_request",
+            InventoryError::NotFound(_) => "not_found",
+            InventoryError::Conflict(_) => "conflict",
+            InventoryError::Validation(_) | InventoryError::InvalidFields(_) => "validation_failed",
+            InventoryError::InvalidTransition { .. } => "invalid_transition",
+            InventoryError::UnknownLocation(_) => "unknown_location",
+            InventoryError::InactiveLocation(_) => "inactive_location",
+            InventoryError::UnknownItem(_) => "unknown_item",
+            InventoryError::UnknownReference { .. } => "unknown_reference",
+            InventoryError::Referenced { .. } => "still_referenced",
+            InventoryError::PreconditionFailed { .. } => "precondition_failed",
+            InventoryError::InvalidMovement(_) => "invalid_movement",
+            InventoryError::BomCycle(_) => "bom_cycle",
+            InventoryError::InsufficientStock { .. } => "insufficient_stock",
+        }
+    }
+
+    /// The offending fields, for errors that can name them.
+    pub fn details(&self) -> Vec<FieldError> {
+        match self {
+            InventoryError::InvalidFields(errors) => errors.clone(),
+            InventoryError::UnknownReference { field, id } =>
+                vec![FieldError::new(field, format!("'{}' does not exist", id))],
+            _ => Vec::new(),
+        }
+    }
+}
+
+impl std::error::Error for InventoryError {}
+
+impl From<heed::Error> for InventoryError {
+    fn from(e: heed::Error) -> Self {
+        InventoryError::Storage(e)
+    }
+}
@@ -0,0 +1,28 @@
+use chrono::Utc;
+use std::sync::{ Mutex, PoisonError };
+
+/// Hands out record IDs that sort in creation order: a prefix, the creation
+/// time as 12 hex digits of Unix milliseconds, and 4 hex digits counting IDs
+/// within the same millisecond, e.g. `MOVE-018F2A9C41D20000`.
+#[derive(Default)]
+pub struct IdGenerator {
+    /// Millisecond and sequence number of the last ID handed out.
+    last: Mutex<(u64, u16)>,
+}
+
+impl IdGenerator {
+    pub fn next(&self, prefix: &str) -> String {
+        let now = Utc::now().timestamp_millis().max(0) as u64;
+        let mut last = self.last.lock().unwrap_or_else(PoisonError::into_inner);
+        // Never step back, even when the clock does; borrow the next
+        // millisecond once a sequence runs out.
+        *last = if now > last.0 {
+            (now, 0)
+        } else if last.1 == u16::MAX {
+            (last.0 + 1, 0)
+        } else {
+            (last.0, last.1 + 1)
+        };
+        format!("{}-{:012X}{:04X}", prefix, last.0, last.1)
+    }
+}
@@ -0,0 +1,82 @@
+use heed::types::{ Str, Unit };
+use heed::{ Database, RoTxn, RwTxn };
+use std::ops::Bound;
+
+/// Secondary index over one primary database. Entries are keyed
+/// `value\0primary_key` and carry no data, so records sharing a value sit
+/// next to each other and come back in primary key order.
+pub struct Index<T> {
+    db: Database<Str, Unit>,
+    /// Indexed value of a record; `None` leaves the record out of the index.
+    value: fn(&T) -> Option<String>,
+}
+
+impl<T> Index<T> {
+    pub fn new(db: Database<Str, Unit>, value: fn(&T) -> Option<String>) -> Self {
+        Index { db, value }
+    }
+
+    /// Moves the entry for `key` from the value of `old` to that of `new`.
+    /// Pass `None` as `old` for a new record and as `new` for a deleted one.
+    pub fn update(&self, wtxn: &mut RwTxn, key: &str, old: Option<&T>, new: Option<&T>) -> heed::Result<()> {
+        let old_value = old.and_then(self.value);
+        let new_value = new.and_then(self.value);
+        if old_value == new_value {
+            return Ok(());
+        }
+        if let Some(value) = old_value {
+            self.db.delete(wtxn, &entry_key(&value, key))?;
+        }
+        if let Some(value) = new_value {
+            self.db.put(wtxn, &entry_key(&value, key), &())?;
+        }
+        Ok(())
+    }
+
+    /// Primary keys of the records whose value is exactly `value`, sorted.
+    pub fn keys(&self, rtxn: &RoTxn, value: &str) -> heed::Result<Vec<String>> {
+        let prefix = entry_key(value, "");
+        self.db
+            .prefix_iter(rtxn, &prefix)?
+            .map(|res| res.map(|(entry, ())| entry[prefix.len()..].to_string()))
+            .collect()
+    }
+
+    /// Primary keys of the records whose value lies within the inclusive
+    /// bounds, ordered by value.
+    pub fn keys_between(&self, rtxn: &RoTxn, from: Option<&str>, to: Option<&str>) -> heed::Result<Vec<String>> {
+        // Every entry for `to` sorts below `to\x01`.
+        let upper = to.map(|to| format!("{}\u{1}", to));
+        let bounds = (
+            from.map_or(Bound::Unbounded, Bound::Included),
+            upper.as_deref().map_or(Bound::Unbounded, Bound::Excluded),
+        );
+        self.db
+            .range(rtxn, &bounds)?
+            .map(|res| {
+                res.map(|(entry, ())| {
+                    entry
+                        .split_once('\0')
+                        .map_or(entry, |(_, key)| key)
+                        .to_string()
+                })
+            })
+            .collect()
+    }
+
+    pub fn insert(&self, wtxn: &mut RwTxn, key: &str, record: &T) -> heed::Result<()> {
+        self.update(wtxn, key, None, Some(record))
+    }
+
+    pub fn clear(&self, wtxn: &mut RwTxn) -> heed::Result<()> {
+        self.db.clear(wtxn)
+    }
+
+    pub fn len(&self, rtxn: &RoTxn) -> heed::Result<u64> {
+        self.db.len(rtxn)
+    }
+}
+
+fn entry_key(value: &str, key: &str) -> String {
+    format!("{}\0{}", value, key)
+}
@@ -2,28 +2,94 @@
 #[link(name = "advapi32")]
 unsafe extern "system" {}
 use actix_web::{web, App, HttpServer};
-use std::collections::HashMap;
-use std::path::Path;
+use std::collections::{ BTreeMap, HashMap };
+use std::ops::Bound;
+use std::path::{ Path, PathBuf };
 use std::sync::Arc;
 use chrono::NaiveDate;
 use heed::{ Database, Env, EnvOpenOptions, RwTxn, RoTxn };
+mod config;
+mod error;
+mod ids;
+mod index;
+mod migrate;
 mod routes;
-use heed::types::{ SerdeBincode, Str };
+use heed::types::{ DecodeIgnore, SerdeBincode, Str, Unit };
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use serde_json::{ Map, Value };
+use crate::config::{ Config, ReorderPolicy };
+use crate::error::{ FieldError, InventoryError };
+use crate::ids::IdGenerator;
+use crate::index::Index;
+use crate::migrate::{ baseline, SCHEMA_VERSION };
 mod r#struct;
+#[cfg(test)]
+mod tests;
 use crate::r#struct::{
+    BomExplosion,
+    BomRequirement,
+    ComponentFilter,
+    CatalogueItem,
+    Locations,
+    PriceBreak,
+    LocationKind,
     Products,
     Procurement,
+    ProcurementStatus,
     Procurements,
+    ProcurementPlan,
     ProductionRate,
     RecorderPoint,
     Components,
     Movements,
+    MovementFilter,
+    MovementReceipt,
+    MovementStatus,
+    MovementType,
+    Status,
+    StockItem,
+    Validate,
+    Versioned,
     Orders,
+    OrderStatus,
+    OrderFilter,
+    Page,
+    PageRequest,
+    ProductFilter,
+    Suppliers,
     SuppliersOrders,
+    SupplierOrderStatus,
+    SupplierOrderFilter,
+    SortOrder,
+    AssemblyStatus,
     AssemblyTimeLine,
+    AssemblyFilter,
+    AssemblySchedule,
+    LateOrder,
+    UnscheduledOrder,
     Watches,
+    within,
 };
 
+/// Pseudo location assembly movements use for components on the assembly
+/// line; it holds no stock of its own.
+const ASSEMBLY_LINE: &str = "ASSEMBLY-LINE";
+
+/// Locations the sample data and the baseline migration register.
+const STANDARD_LOCATIONS: [(&str, &str, LocationKind); 7] = [
+    ("CN", "CN", LocationKind::Warehouse),
+    ("KLING", "Kling", LocationKind::Warehouse),
+    ("ST-JAKOB", "St Jakob", LocationKind::Warehouse),
+    ("WURENLOS", "Wurenlos", LocationKind::Assembly),
+    ("FLF", "FLF", LocationKind::Warehouse),
+    ("CUSTOMER", "Customer", LocationKind::Customer),
+    ("SCRAP", "Scrap", LocationKind::Scrap),
+];
+
+/// Key of the stored layout version in the meta database.
+const SCHEMA_VERSION_KEY: &str = "schema_version";
+
 // ========== DATABASE IMPLEMENTATION ==========
 
 pub struct InventoryDB {
@@ -38,18 +104,57 @@ pub struct InventoryDB {
     production_rate_db: Database<Str, SerdeBincode<ProductionRate>>,
     recorder_point_db: Database<Str, SerdeBincode<RecorderPoint>>,
     watches_db: Database<Str, SerdeBincode<Watches>>,
+    locations_db: Database<Str, SerdeBincode<Locations>>,
+    product_names_db: Database<Str, Str>,
+    component_names_db: Database<Str, Str>,
+    suppliers_db: Database<Str, SerdeBincode<Suppliers>>,
+    /// Keyed by `supplier_id\0component_id`, see [`catalogue_key`].
+    catalogue_db: Database<Str, SerdeBincode<CatalogueItem>>,
+    movements_by_date: Index<Movements>,
+    movements_by_component: Index<Movements>,
+    orders_by_status: Index<Orders>,
+    orders_by_product: Index<Orders>,
+    supplier_orders_by_supplier: Index<SuppliersOrders>,
+    timelines_by_order: Index<AssemblyTimeLine>,
+    /// Database-wide settings such as the schema version.
+    meta_db: Database<Str, SerdeBincode<u64>>,
+    ids: IdGenerator,
 }
 
 impl InventoryDB {
-    pub fn new(path: &Path) -> Result<Self, heed::Error> {
+    /// Opens the database, refusing one whose records are in a layout this
+    /// build cannot read; `migrate` converts those. An empty database is
+    /// stamped with the current layout.
+    pub fn new(path: &Path, map_size: usize) -> Result<Self, InventoryError> {
+        let db = Self::open(path, map_size)?;
+        db.with_write_txn(|wtxn| {
+            match db.meta_db.get(wtxn, SCHEMA_VERSION_KEY)? {
+                Some(SCHEMA_VERSION) => Ok(()),
+                Some(version) => Err(unsupported_schema(version)),
+                None if db.is_empty(wtxn)? => {
+                    db.meta_db.put(wtxn, SCHEMA_VERSION_KEY, &SCHEMA_VERSION)?;
+                    Ok(())
+                }
+                None =>
+                    Err(
+                        InventoryError::Conflict(
+                            "database holds records in the baseline layout; run `migrate` first".to_string()
+                        )
+                    ),
+            }
+        })?;
+        Ok(db)
+    }
+
+    fn open(path: &Path, map_size: usize) -> Result<Self, heed::Error> {
         if !path.exists() {
             std::fs::create_dir_all(path)?;
         }
 
         let env = unsafe {
             EnvOpenOptions::new()
-                .map_size(1024 * 1024 * 1024) // 1GB
-                .max_dbs(10)
+                .map_size(map_size)
+                .max_dbs(22)
                 .open(path)?
         };
 
@@ -66,8 +171,25 @@ impl InventoryDB {
             env.create_database(&mut wtxn, Some("production_rate"))?,
             env.create_database(&mut wtxn, Some("recorder_point"))?,
             env.create_database(&mut wtxn, Some("watches"))?,
+            env.create_database(&mut wtxn, Some("locations"))?,
+            env.create_database(&mut wtxn, Some("product_names"))?,
+            env.create_database(&mut wtxn, Some("component_names"))?,
+            env.create_database(&mut wtxn, Some("suppliers"))?,
+            env.create_database(&mut wtxn, Some("supplier_catalogue"))?,
         );
 
+        let index_names = [
+            "movements_by_date",
+            "movements_by_component",
+            "orders_by_status",
+            "orders_by_product",
+            "supplier_orders_by_supplier",
+            "timelines_by_order",
+        ];
+        let indexes = index_names.map(|name| env.create_database::<Str, Unit>(&mut wtxn, Some(name)));
+        let [by_date, by_component, by_status, by_product, by_supplier, by_order] = indexes;
+        let meta_db = env.create_database(&mut wtxn, Some("meta"))?;
+
         wtxn.commit()?;
 
         Ok(Self {
@@ -82,12 +204,25 @@ impl InventoryDB {
             production_rate_db: dbs.7,
             recorder_point_db: dbs.8,
             watches_db: dbs.9,
+            locations_db: dbs.10,
+            product_names_db: dbs.11,
+            component_names_db: dbs.12,
+            suppliers_db: dbs.13,
+            catalogue_db: dbs.14,
+            movements_by_date: Index::new(by_date?, |movement| Some(movement.date.to_string())),
+            movements_by_component: Index::new(by_component?, |movement| movement.component_id.clone()),
+            orders_by_status: Index::new(by_status?, |order| Some(order.order_status.to_string())),
+            orders_by_product: Index::new(by_product?, |order| Some(order.product_id.clone())),
+            supplier_orders_by_supplier: Index::new(by_supplier?, |order| Some(order.supplier_id.clone())),
+            timelines_by_order: Index::new(by_order?, |timeline| Some(timeline.order.clone())),
+            meta_db,
+            ids: IdGenerator::default(),
         })
     }
 
     // ========== TRANSACTION HELPERS ==========
-    pub fn with_write_txn<F, T>(&self, f: F) -> Result<T, heed::Error>
-        where F: FnOnce(&mut RwTxn) -> Result<T, heed::Error>
+    pub fn with_write_txn<F, T, E>(&self, f: F) -> Result<T, E>
+        where F: FnOnce(&mut RwTxn) -> Result<T, E>, E: From<heed::Error>
     {
         let mut wtxn = self.env.write_txn()?;
         let result = f(&mut wtxn)?;
@@ -102,9 +237,386 @@ impl InventoryDB {
         f(&rtxn)
     }
 
+    // ========== RECORD IDS ==========
+    /// Settles the key of a record about to be created: generates one when
+    /// the client left it empty, otherwise makes sure the client's is free.
+    fn claim_key<T>(
+        &self,
+        rtxn: &RoTxn,
+        db: &Database<Str, SerdeBincode<T>>,
+        prefix: &str,
+        id: &mut String,
+        entity: &str
+    ) -> Result<(), InventoryError> {
+        let keys = db.remap_data_type::<DecodeIgnore>();
+        if id.is_empty() {
+            let mut candidate = self.ids.next(prefix);
+            while keys.get(rtxn, &candidate)?.is_some() {
+                candidate = self.ids.next(prefix);
+            }
+            *id = candidate;
+            return Ok(());
+        }
+        if keys.get(rtxn, id)?.is_some() {
+            return Err(InventoryError::Conflict(format!("{} '{}' already exists", entity, id)));
+        }
+        Ok(())
+    }
+
+    // ========== SECONDARY INDEXES ==========
+    // Every write to an indexed database goes through these so the indexes
+    // change in the same transaction as the record.
+
+    fn store_movement(&self, wtxn: &mut RwTxn, movement: &mut Movements) -> Result<(), heed::Error> {
+        let stored = self.movements_db.get(wtxn, &movement.movement_id)?;
+        bump_version(movement, stored.as_ref());
+        for index in [&self.movements_by_date, &self.movements_by_component] {
+            index.update(wtxn, &movement.movement_id, stored.as_ref(), Some(movement))?;
+        }
+        self.movements_db.put(wtxn, &movement.movement_id, movement)
+    }
+
+    /// Stores a movement the server books itself, under a freshly claimed ID.
+    fn store_new_movement(&self, wtxn: &mut RwTxn, movement: &mut Movements) -> Result<(), InventoryError> {
+        self.claim_key(wtxn, &self.movements_db, "MOVE", &mut movement.movement_id, "movement")?;
+        Ok(self.store_movement(wtxn, movement)?)
+    }
+
+    fn store_order(&self, wtxn: &mut RwTxn, order: &mut Orders) -> Result<(), heed::Error> {
+        let stored = self.orders_db.get(wtxn, &order.order_id)?;
+        bump_version(order, stored.as_ref());
+        for index in [&self.orders_by_status, &self.orders_by_product] {
+            index.update(wtxn, &order.order_id, stored.as_ref(), Some(order))?;
+        }
+        self.orders_db.put(wtxn, &order.order_id, order)
+    }
+
+    fn remove_order(&self, wtxn: &mut RwTxn, id: &str) -> Result<bool, heed::Error> {
+        let Some(stored) = self.orders_db.get(wtxn, id)? else {
+            return Ok(false);
+        };
+        for index in [&self.orders_by_status, &self.orders_by_product] {
+            index.update(wtxn, id, Some(&stored), None)?;
+        }
+        self.orders_db.delete(wtxn, id)
+    }
+
+    fn store_supplier_order(&self, wtxn: &mut RwTxn, order: &mut SuppliersOrders) -> Result<(), heed::Error> {
+        let stored = self.suppliers_orders_db.get(wtxn, &order.order_id)?;
+        bump_version(order, stored.as_ref());
+        self.supplier_orders_by_supplier.update(wtxn, &order.order_id, stored.as_ref(), Some(order))?;
+        self.suppliers_orders_db.put(wtxn, &order.order_id, order)
+    }
+
+    fn remove_supplier_order(&self, wtxn: &mut RwTxn, id: &str) -> Result<bool, heed::Error> {
+        let Some(stored) = self.suppliers_orders_db.get(wtxn, id)? else {
+            return Ok(false);
+        };
+        self.supplier_orders_by_supplier.update(wtxn, id, Some(&stored), None)?;
+        self.suppliers_orders_db.delete(wtxn, id)
+    }
+
+    fn store_assembly_timeline(
+        &self,
+        wtxn: &mut RwTxn,
+        timeline: &mut AssemblyTimeLine
+    ) -> Result<(), heed::Error> {
+        let stored = self.assembly_timeline_db.get(wtxn, &timeline.assembly_id)?;
+        bump_version(timeline, stored.as_ref());
+        self.timelines_by_order.update(wtxn, &timeline.assembly_id, stored.as_ref(), Some(timeline))?;
+        self.assembly_timeline_db.put(wtxn, &timeline.assembly_id, timeline)
+    }
+
+    fn remove_assembly_timeline(&self, wtxn: &mut RwTxn, id: &str) -> Result<bool, heed::Error> {
+        let Some(stored) = self.assembly_timeline_db.get(wtxn, id)? else {
+            return Ok(false);
+        };
+        self.timelines_by_order.update(wtxn, id, Some(&stored), None)?;
+        self.assembly_timeline_db.delete(wtxn, id)
+    }
+
+    fn clear_indexes(&self, wtxn: &mut RwTxn) -> Result<(), heed::Error> {
+        self.movements_by_date.clear(wtxn)?;
+        self.movements_by_component.clear(wtxn)?;
+        self.orders_by_status.clear(wtxn)?;
+        self.orders_by_product.clear(wtxn)?;
+        self.supplier_orders_by_supplier.clear(wtxn)?;
+        self.timelines_by_order.clear(wtxn)
+    }
+
+    /// Regenerates every secondary index from the primary databases and
+    /// returns the number of index entries written.
+    pub fn rebuild_indexes(&self) -> Result<u64, heed::Error> {
+        self.with_write_txn(|wtxn| {
+            self.clear_indexes(wtxn)?;
+
+            let movements = self.movements_db
+                .iter(wtxn)?
+                .map(|res| res.map(|(key, v)| (key.to_string(), v)))
+                .collect::<heed::Result<Vec<(String, Movements)>>>()?;
+            for (key, movement) in &movements {
+                self.movements_by_date.insert(wtxn, key, movement)?;
+                self.movements_by_component.insert(wtxn, key, movement)?;
+            }
+
+            let orders = self.orders_db
+                .iter(wtxn)?
+                .map(|res| res.map(|(key, v)| (key.to_string(), v)))
+                .collect::<heed::Result<Vec<(String, Orders)>>>()?;
+            for (key, order) in &orders {
+                self.orders_by_status.insert(wtxn, key, order)?;
+                self.orders_by_product.insert(wtxn, key, order)?;
+            }
+
+            let supplier_orders = self.suppliers_orders_db
+                .iter(wtxn)?
+                .map(|res| res.map(|(key, v)| (key.to_string(), v)))
+                .collect::<heed::Result<Vec<(String, SuppliersOrders)>>>()?;
+            for (key, order) in &supplier_orders {
+                self.supplier_orders_by_supplier.insert(wtxn, key, order)?;
+            }
+
+            let timelines = self.assembly_timeline_db
+                .iter(wtxn)?
+                .map(|res| res.map(|(key, v)| (key.to_string(), v)))
+                .collect::<heed::Result<Vec<(String, AssemblyTimeLine)>>>()?;
+            for (key, timeline) in &timelines {
+                self.timelines_by_order.insert(wtxn, key, timeline)?;
+            }
+
+            Ok(
+                self.movements_by_date.len(wtxn)? +
+                    self.movements_by_component.len(wtxn)? +
+                    self.orders_by_status.len(wtxn)? +
+                    self.orders_by_product.len(wtxn)? +
+                    self.supplier_orders_by_supplier.len(wtxn)? +
+                    self.timelines_by_order.len(wtxn)?
+            )
+        })
+    }
+
+    /// Orders currently in one of `statuses`, in order ID order per status.
+    fn orders_with_status(&self, rtxn: &RoTxn, statuses: &[OrderStatus]) -> Result<Vec<Orders>, heed::Error> {
+        let mut orders = Vec::new();
+        for status in statuses {
+            for key in self.orders_by_status.keys(rtxn, &status.to_string())? {
+                if let Some(order) = self.orders_db.get(rtxn, &key)? {
+                    orders.push(order);
+                }
+            }
+        }
+        Ok(orders)
+    }
+
+    // ========== PAGINATION ==========
+    /// Reads one page of `db` in key order, skipping records `keep` rejects.
+    pub fn page<T>(
+        &self,
+        db: &Database<Str, SerdeBincode<T>>,
+        request: &PageRequest,
+        keep: impl FnMut(&T) -> bool
+    ) -> Result<Page<T>, heed::Error>
+        where T: DeserializeOwned
+    {
+        let rtxn = self.env.read_txn()?;
+        read_page(&rtxn, db, request, keep)
+    }
+
+    // ========== SCHEMA MIGRATION ==========
+    /// Converts a database written by an earlier release to the current
+    /// record layout and returns the number of records converted. It all
+    /// happens in one write transaction, so a failed migration leaves the
+    /// database as it was.
+    pub fn migrate(path: &Path, map_size: usize) -> Result<u64, InventoryError> {
+        let db = Self::open(path, map_size)?;
+        db.with_write_txn(|wtxn| {
+            let converted = match db.meta_db.get(wtxn, SCHEMA_VERSION_KEY)? {
+                Some(SCHEMA_VERSION) => 0,
+                Some(version) => {
+                    return Err(unsupported_schema(version));
+                }
+                None if db.is_empty(wtxn)? => 0,
+                None => db.migrate_baseline(wtxn)?,
+            };
+            db.meta_db.put(wtxn, SCHEMA_VERSION_KEY, &SCHEMA_VERSION)?;
+            Ok(converted)
+        })
+    }
+
+    /// Rewrites every record of the baseline layout. Stock held in the fixed
+    /// per-site fields moves to the matching registry locations, and
+    /// references the baseline only kept by name are resolved to IDs.
+    fn migrate_baseline(&self, wtxn: &mut RwTxn) -> Result<u64, InventoryError> {
+        let products = read_baseline::<_, baseline::Products>(wtxn, &self.products_db)?;
+        let components = read_baseline::<_, baseline::Components>(wtxn, &self.components_db)?;
+        let movements = read_baseline::<_, baseline::Movements>(wtxn, &self.movements_db)?;
+        let supplier_orders = read_baseline::<_, baseline::SuppliersOrders>(wtxn, &self.suppliers_orders_db)?;
+        let orders = read_baseline::<_, baseline::Orders>(wtxn, &self.orders_db)?;
+        let procurements = read_baseline::<_, baseline::Procurements>(wtxn, &self.procurements_db)?;
+        let timelines = read_baseline::<_, baseline::AssemblyTimeLine>(wtxn, &self.assembly_timeline_db)?;
+        let rates = read_baseline::<_, baseline::ProductionRate>(wtxn, &self.production_rate_db)?;
+        let points = read_baseline::<_, baseline::RecorderPoint>(wtxn, &self.recorder_point_db)?;
+        let watches = read_baseline::<_, baseline::Watches>(wtxn, &self.watches_db)?;
+        let converted = [
+            products.len(),
+            components.len(),
+            movements.len(),
+            supplier_orders.len(),
+            orders.len(),
+            procurements.len(),
+            timelines.len(),
+            rates.len(),
+            points.len(),
+            watches.len(),
+        ].iter().sum::<usize>() as u64;
+
+        // The write helpers read the stored record to bump its version, which
+        // fails on baseline bytes, so every record is written afresh.
+        self.products_db.clear(wtxn)?;
+        self.components_db.clear(wtxn)?;
+        self.movements_db.clear(wtxn)?;
+        self.suppliers_orders_db.clear(wtxn)?;
+        self.orders_db.clear(wtxn)?;
+        self.procurements_db.clear(wtxn)?;
+        self.assembly_timeline_db.clear(wtxn)?;
+        self.production_rate_db.clear(wtxn)?;
+        self.recorder_point_db.clear(wtxn)?;
+        self.watches_db.clear(wtxn)?;
+        self.product_names_db.clear(wtxn)?;
+        self.component_names_db.clear(wtxn)?;
+        self.clear_indexes(wtxn)?;
+        self.register_standard_locations(wtxn)?;
+
+        let product_ids: HashMap<String, String> = products
+            .iter()
+            .map(|(_, product)| (product.product_name.clone(), product.product_id.clone()))
+            .collect();
+        let component_ids: HashMap<String, String> = components
+            .iter()
+            .map(|(_, component)| (component.component_name.clone(), component.component_id.clone()))
+            .collect();
+        let order_quantities: HashMap<String, u64> = orders
+            .iter()
+            .map(|(_, order)| (order.order_id.clone(), order.quantity_required))
+            .collect();
+
+        for (_, product) in products {
+            self.put_product(wtxn, &mut product.upgrade())?;
+        }
+        // Reorder points go in before components so writing a component
+        // re-evaluates them.
+        for (key, point) in points {
+            let component_id = component_ids.get(&point.component_name).cloned().unwrap_or_default();
+            let mut point = point.upgrade(component_id);
+            point.version = next_version(wtxn, &self.recorder_point_db, &key)?;
+            self.recorder_point_db.put(wtxn, &key, &point)?;
+        }
+        for (_, component) in components {
+            self.put_component(wtxn, &mut component.upgrade())?;
+        }
+        for (key, watch) in watches {
+            let mut watch = watch.upgrade();
+            watch.version = next_version(wtxn, &self.watches_db, &key)?;
+            self.watches_db.put(wtxn, &key, &watch)?;
+        }
+        for (key, rate) in rates {
+            let mut rate = rate.upgrade();
+            rate.version = next_version(wtxn, &self.production_rate_db, &key)?;
+            self.production_rate_db.put(wtxn, &key, &rate)?;
+        }
+        for (key, order) in orders {
+            let mut order = order.upgrade().map_err(|e| unconvertible("order", &key, e))?;
+            self.store_order(wtxn, &mut order)?;
+        }
+        for (key, group) in procurements {
+            let mut group = group.upgrade().map_err(|e| unconvertible("procurement", &key, e))?;
+            group.version = next_version(wtxn, &self.procurements_db, &key)?;
+            self.procurements_db.put(wtxn, &key, &group)?;
+        }
+        for (key, order) in supplier_orders {
+            let component_id = component_ids.get(&order.component_name).cloned().unwrap_or_default();
+            let mut order = order
+                .upgrade(component_id)
+                .map_err(|e| unconvertible("supplier order", &key, e))?;
+            self.store_supplier_order(wtxn, &mut order)?;
+        }
+        for (key, movement) in movements {
+            let component_id = movement.component_name
+                .as_ref()
+                .and_then(|name| component_ids.get(name))
+                .cloned();
+            let product_id = movement.product_name
+                .as_ref()
+                .and_then(|name| product_ids.get(name))
+                .cloned();
+            let mut movement = movement
+                .upgrade(component_id, product_id)
+                .map_err(|e| unconvertible("movement", &key, e))?;
+            self.store_movement(wtxn, &mut movement)?;
+        }
+        for (key, timeline) in timelines {
+            let quantity = order_quantities.get(&timeline.order).copied().unwrap_or(0);
+            let mut timeline = timeline
+                .upgrade(quantity)
+                .map_err(|e| unconvertible("assembly timeline", &key, e))?;
+            self.store_assembly_timeline(wtxn, &mut timeline)?;
+        }
+        Ok(converted)
+    }
+
+    /// Registers the standard locations that are not registered yet; the
+    /// baseline stock fields map onto the first five.
+    fn register_standard_locations(&self, wtxn: &mut RwTxn) -> Result<(), heed::Error> {
+        for (location_id, location_name, kind) in STANDARD_LOCATIONS {
+            if self.locations_db.get(wtxn, location_id)?.is_some() {
+                continue;
+            }
+            let mut location = Locations {
+                location_id: location_id.to_string(),
+                location_name: location_name.to_string(),
+                kind,
+                active: true,
+                address: None,
+                version: 0,
+            };
+            location.version = next_version(wtxn, &self.locations_db, location_id)?;
+            self.locations_db.put(wtxn, location_id, &location)?;
+        }
+        Ok(())
+    }
+
     // ========== SAMPLE DATA INITIALIZATION ==========
-    pub fn initialize_sample_data(&self) -> Result<(), heed::Error> {
+    /// True when none of the primary databases hold any records.
+    fn is_empty(&self, rtxn: &RoTxn) -> Result<bool, heed::Error> {
+        Ok(
+            self.products_db.is_empty(rtxn)? &&
+                self.components_db.is_empty(rtxn)? &&
+                self.movements_db.is_empty(rtxn)? &&
+                self.suppliers_orders_db.is_empty(rtxn)? &&
+                self.orders_db.is_empty(rtxn)? &&
+                self.procurements_db.is_empty(rtxn)? &&
+                self.assembly_timeline_db.is_empty(rtxn)? &&
+                self.production_rate_db.is_empty(rtxn)? &&
+                self.recorder_point_db.is_empty(rtxn)? &&
+                self.watches_db.is_empty(rtxn)? &&
+                self.locations_db.is_empty(rtxn)? &&
+                self.suppliers_db.is_empty(rtxn)? &&
+                self.catalogue_db.is_empty(rtxn)?
+        )
+    }
+
+    /// Replaces the database contents with the sample data set. Refuses to
+    /// touch a database that already holds records unless `force` is set.
+    pub fn initialize_sample_data(&self, force: bool) -> Result<(), InventoryError> {
         self.with_write_txn(|wtxn| {
+            if !force && !self.is_empty(wtxn)? {
+                return Err(
+                    InventoryError::Conflict(
+                        "database is not empty; rerun `seed --force` to replace its contents".to_string()
+                    )
+                );
+            }
+
             // Clear all databases first
             self.products_db.clear(wtxn)?;
             self.components_db.clear(wtxn)?;
@@ -116,25 +628,40 @@ impl InventoryDB {
             self.production_rate_db.clear(wtxn)?;
             self.recorder_point_db.clear(wtxn)?;
             self.watches_db.clear(wtxn)?;
+            self.locations_db.clear(wtxn)?;
+            self.product_names_db.clear(wtxn)?;
+            self.component_names_db.clear(wtxn)?;
+            self.suppliers_db.clear(wtxn)?;
+            self.catalogue_db.clear(wtxn)?;
+            self.clear_indexes(wtxn)?;
+
+            // Create sample locations
+            self.register_standard_locations(wtxn)?;
 
             // Create sample products
-            let product_bp = Products {
+            let mut product_bp = Products {
                 product_name: "BP Watch".to_string(),
                 product_id: "PROD-001".to_string(),
+                watch_model_id: Some("BP-2023-001".to_string()),
                 components: Some(vec!["COMP-001".to_string(), "COMP-002".to_string()]),
-                cn: 100,
-                kling: 50,
-                st_jacob: 75,
-                wurenlos: 200,
+                stock: BTreeMap::from([
+                    ("CN".to_string(), 100),
+                    ("KLING".to_string(), 50),
+                    ("ST-JAKOB".to_string(), 75),
+                    ("WURENLOS".to_string(), 200),
+                    ("FLF".to_string(), 150),
+                ]),
                 wurenlos_sold: 25,
-                flf: 150,
-                in_transit: 30,
-                total_available: 600,
-                reserver_for_orders: 150,
+                in_transit: 0,
+                on_hand: 0,
+                total_available: 0,
+                free_to_promise: 0,
+                reserver_for_orders: 0,
                 waste: 5,
                 customer: 0,
+                version: 0,
             };
-            self.products_db.put(wtxn, "PROD-001", &product_bp)?;
+            self.put_product(wtxn, &mut product_bp)?;
 
             // Create sample components
             let components = vec![
@@ -143,78 +670,139 @@ impl InventoryDB {
                     product_name: "BP Watch".to_string(),
                     component_name: "Premium Dial".to_string(),
                     component_id: "COMP-001".to_string(),
-                    cn: 40,
-                    kling: 20,
-                    st_jacob: 30,
-                    wurenlos: 80,
+                    stock: BTreeMap::from([
+                        ("CN".to_string(), 40),
+                        ("KLING".to_string(), 20),
+                        ("ST-JAKOB".to_string(), 30),
+                        ("WURENLOS".to_string(), 80),
+                        ("FLF".to_string(), 60),
+                    ]),
                     wurenlos_sold: 10,
-                    flf: 60,
-                    in_transit: 15,
-                    total_available: 245,
+                    in_transit: 0,
+                    on_hand: 0,
+                    total_available: 0,
+                    free_to_promise: 0,
                     ordered_surplus: 25.5,
-                    reserver_for_orders: 60,
+                    reserver_for_orders: 0,
                     waste: 2,
                     customer: 0,
                     assembly_line: 0,
+                    version: 0,
                 },
                 Components {
                     product_id: "PROD-001".to_string(),
                     product_name: "BP Watch".to_string(),
                     component_name: "Luminous Hands".to_string(),
                     component_id: "COMP-002".to_string(),
-                    cn: 35,
-                    kling: 15,
-                    st_jacob: 25,
-                    wurenlos: 70,
+                    stock: BTreeMap::from([
+                        ("CN".to_string(), 35),
+                        ("KLING".to_string(), 15),
+                        ("ST-JAKOB".to_string(), 25),
+                        ("WURENLOS".to_string(), 70),
+                        ("FLF".to_string(), 50),
+                    ]),
                     wurenlos_sold: 8,
-                    flf: 50,
-                    in_transit: 12,
-                    total_available: 215,
+                    in_transit: 0,
+                    on_hand: 0,
+                    total_available: 0,
+                    free_to_promise: 0,
                     ordered_surplus: 18.0,
-                    reserver_for_orders: 45,
+                    reserver_for_orders: 0,
                     waste: 1,
                     customer: 0,
                     assembly_line: 0,
+                    version: 0,
                 }
             ];
 
-            for component in components {
-                self.components_db.put(wtxn, &component.component_id, &component)?;
+            for mut component in components {
+                self.put_component(wtxn, &mut component)?;
             }
 
             // Create sample movements
-            let movement_1 = Movements {
+            let mut movement_1 = Movements {
                 movement_id: "MOVE-001".to_string(),
                 transaction_id: "TRANS-001".to_string(),
                 date: NaiveDate::from_ymd_opt(2023, 5, 15).expect("Invalid date"),
-                movement_type: "Component".to_string(),
+                movement_type: MovementType::Component,
+                component_id: Some("COMP-001".to_string()),
                 component_name: Some("Premium Dial".to_string()),
+                product_id: None,
                 product_name: None,
                 source_location: "CN".to_string(),
                 destination_location: "Wurenlos".to_string(),
                 quantity: 10,
                 notes: Some("Regular stock transfer".to_string()),
-                status: "Completed".to_string(),
+                status: MovementStatus::Completed,
                 supplier_order_id: None,
+                received_quantity: 0,
+                returned_quantity: 0,
+                receipts: Vec::new(),
+                version: 0,
+            };
+            self.store_movement(wtxn, &mut movement_1)?;
+
+            // Create sample supplier and its catalogue
+            let mut supplier = Suppliers {
+                supplier_id: "SUPP-001".to_string(),
+                supplier_name: "Swiss Parts AG".to_string(),
+                contact_name: Some("Anna Meier".to_string()),
+                email: Some("orders@swissparts.example".to_string()),
+                phone: None,
+                address: Some("Biel/Bienne".to_string()),
+                currency: "CHF".to_string(),
+                default_lead_time: 14,
+                version: 0,
             };
-            self.movements_db.put(wtxn, "MOVE-001", &movement_1)?;
+            supplier.version = next_version(wtxn, &self.suppliers_db, "SUPP-001")?;
+            self.suppliers_db.put(wtxn, "SUPP-001", &supplier)?;
+
+            let catalogue = [
+                ("COMP-001", "SP-DIAL-PREM", 50, 10, vec![(1, 12.5), (100, 11.0), (500, 9.75)]),
+                ("COMP-002", "SP-HANDS-LUM", 25, 25, vec![(1, 4.2), (250, 3.8)]),
+            ];
+            for (component_id, supplier_sku, moq, pack_size, price_breaks) in catalogue {
+                let mut item = CatalogueItem {
+                    supplier_id: "SUPP-001".to_string(),
+                    component_id: component_id.to_string(),
+                    supplier_sku: supplier_sku.to_string(),
+                    price_breaks: price_breaks
+                        .into_iter()
+                        .map(|(min_quantity, unit_price)| PriceBreak { min_quantity, unit_price })
+                        .collect(),
+                    moq,
+                    pack_size,
+                    version: 0,
+                };
+                let key = catalogue_key("SUPP-001", component_id);
+                item.version = next_version(wtxn, &self.catalogue_db, &key)?;
+                self.catalogue_db.put(wtxn, &key, &item)?;
+            }
 
             // Create sample supplier order
-            let supplier_order = SuppliersOrders {
+            let mut supplier_order = SuppliersOrders {
                 supplier_id: "SUPP-001".to_string(),
-                component_name: "Sapphire Crystal".to_string(),
+                component_id: "COMP-001".to_string(),
+                component_name: "Premium Dial".to_string(),
                 procurement_id: "PROC-001".to_string(),
                 order_id: "SUPP-ORD-001".to_string(),
                 total_components_required: 50,
                 components_roundof: 50,
-                status: "Pending".to_string(),
+                status: SupplierOrderStatus::Pending,
                 order_date: NaiveDate::from_ymd_opt(2023, 5, 15).expect("Invalid date"),
                 expected_delivery_date: NaiveDate::from_ymd_opt(2023, 5, 15).expect("Invalid date"),
+                supplier_sku: String::new(),
+                unit_price: 0.0,
+                total_price: 0.0,
+                currency: String::new(),
+                received_quantity: 0,
+                movements: Vec::new(),
+                version: 0,
             };
-            self.suppliers_orders_db.put(wtxn, "SUPP-ORD-001", &supplier_order)?;
+            self.put_supplier_order(wtxn, &mut supplier_order)?;
 
             // Create sample order
-            let customer_order = Orders {
+            let mut customer_order = Orders {
                 order_id: "ORD-001".to_string(),
                 procurements: Some(vec!["PROC-001".to_string()]),
                 supplier_orders: Some(vec!["SUPP-ORD-001".to_string()]),
@@ -226,18 +814,19 @@ impl InventoryDB {
                 production_start_date: NaiveDate::from_ymd_opt(2023, 5, 15).expect("Invalid date"),
                 expected_ship_date: NaiveDate::from_ymd_opt(2023, 5, 15).expect("Invalid date"),
                 recid: "REC-001".to_string(),
-                order_status: "Processing".to_string(),
-                total_components_booked: 100,
+                order_status: OrderStatus::Processing,
+                total_components_booked: 0,
                 components_notes: Some("Need expedited shipping".to_string()),
-                components_required: 100,
-                total_gap_components: Some(vec![20, 30]),
-                components: Some(vec!["COMP-001".to_string(), "COMP-002".to_string()]),
+                components_required: 0,
+                total_gap_components: None,
+                components: None,
+                components_booked: None,
+                version: 0,
             };
-            self.orders_db.put(wtxn, "ORD-001", &customer_order)?;
+            self.store_order(wtxn, &mut customer_order)?;
 
             // Create sample procurement
-            // When creating sample data:
-            let procurements = Procurements {
+            let mut procurements = Procurements {
                 procurement_id: "PROC-GROUP-001".to_string(),
                 order_id: "ORD-001".to_string(),
                 procurements: vec![Procurement {
@@ -245,14 +834,16 @@ impl InventoryDB {
                     order_id: "ORD-001".to_string(),
                     components: Some(vec!["COMP-001".to_string()]),
                     quantity: 20,
-                    status: "Pending".to_string(),
+                    status: ProcurementStatus::Pending,
                     product: "BP Watch".to_string(),
                 }],
+                version: 0,
             };
+            procurements.version = next_version(wtxn, &self.procurements_db, "PROC-GROUP-001")?;
             self.procurements_db.put(wtxn, "PROC-GROUP-001", &procurements)?;
 
             // Create sample assembly timeline
-            let assembly = AssemblyTimeLine {
+            let mut assembly = AssemblyTimeLine {
                 assembly_id: "ASSEM-001".to_string(),
                 order: "ORD-001".to_string(),
                 product: "BP Watch".to_string(),
@@ -267,43 +858,65 @@ impl InventoryDB {
                 ),
                 assembly_start_date: NaiveDate::from_ymd_opt(2023, 5, 15).expect("Invalid date"),
                 assembly_end_date: NaiveDate::from_ymd_opt(2023, 5, 15).expect("Invalid date"),
-                assembly_status: "Scheduled".to_string(),
+                assembly_status: AssemblyStatus::Scheduled,
                 total_duration: 5,
                 assembly_notes: Some("Priority order".to_string()),
+                quantity: 50,
+                produced_quantity: 0,
+                scrapped: BTreeMap::new(),
+                version: 0,
             };
-            self.assembly_timeline_db.put(wtxn, "ASSEM-001", &assembly)?;
+            self.store_assembly_timeline(wtxn, &mut assembly)?;
 
             // Create sample production rate
-            let production_rate = ProductionRate {
+            let mut production_rate = ProductionRate {
                 prodction_rate_id: "RATE-001".to_string(),
-                watch_model_id: "BP-2023".to_string(),
+                watch_model_id: "BP-2023-001".to_string(),
                 assembly_time_per_watch: 30,
                 daily_production_capacity: 40,
+                version: 0,
             };
+            production_rate.version = next_version(wtxn, &self.production_rate_db, "RATE-001")?;
             self.production_rate_db.put(wtxn, "RATE-001", &production_rate)?;
 
             // Create sample recorder point
-            let recorder_point = RecorderPoint {
+            let mut recorder_point = RecorderPoint {
                 recorder_point_id: "REORD-001".to_string(),
+                component_id: "COMP-001".to_string(),
                 component_name: "Premium Dial".to_string(),
                 supplier_lead_time: 14,
                 assumed_daily_usage: 5.2,
+                daily_usage_std_dev: 0.0,
                 lead_time_demand: 72.8,
                 safety_stock: 36.4,
                 reorder_point: 110,
                 need_to_order: true,
+                last_computed: None,
+                version: 0,
             };
+            recorder_point.version = next_version(wtxn, &self.recorder_point_db, "REORD-001")?;
             self.recorder_point_db.put(wtxn, "REORD-001", &recorder_point)?;
 
-            // Create sample watch
-            let watch = Watches {
-                watch_id: "WATCH-001".to_string(),
-                watch_model_id: "BP-2023-001".to_string(),
-                brand: "BrandX".to_string(),
-                component_id: "COMP-001".to_string(),
-                required_quantity: 1,
-            };
-            self.watches_db.put(wtxn, "WATCH-001", &watch)?;
+            // Create sample watch BOM lines
+            let watches = [
+                ("WATCH-001", "COMP-001", 1),
+                ("WATCH-002", "COMP-002", 2),
+            ];
+            for (watch_id, component_id, required_quantity) in watches {
+                let mut watch = Watches {
+                    watch_id: watch_id.to_string(),
+                    watch_model_id: "BP-2023-001".to_string(),
+                    brand: "BrandX".to_string(),
+                    component_id: component_id.to_string(),
+                    required_quantity,
+                    version: 0,
+                };
+                watch.version = next_version(wtxn, &self.watches_db, watch_id)?;
+                self.watches_db.put(wtxn, watch_id, &watch)?;
+            }
+
+            // Book component stock for the sample order
+            self.reserve_order_in(wtxn, "ORD-001")?;
 
             Ok(())
         })
@@ -318,12 +931,9 @@ impl InventoryDB {
                 println!("{} (ID: {})", product.product_name, product.product_id);
                 println!("  Total Available: {}", product.total_available);
                 println!("  Reserved: {}", product.reserver_for_orders);
-                println!(
-                    "  Locations - CN: {}, Kling: {}, Wurenlos: {}",
-                    product.cn,
-                    product.kling,
-                    product.wurenlos
-                );
+                for (location_id, quantity) in &product.stock {
+                    println!("  {}: {}", location_id, quantity);
+                }
             }
 
             println!("\n=== COMPONENTS INVENTORY ===");
@@ -337,7 +947,7 @@ impl InventoryDB {
             println!("\n=== PENDING ORDERS ===");
             for item in self.orders_db.iter(rtxn)? {
                 let (_, order) = item?;
-                if order.order_status != "Completed" {
+                if order.order_status != OrderStatus::Completed {
                     println!("Order {} - Status: {}", order.order_id, order.order_status);
                     println!("  Product: {}, Quantity: {}", order.product, order.quanity_ordered);
                 }
@@ -348,65 +958,258 @@ impl InventoryDB {
     }
 
     // ========== PRODUCTS CRUD ==========
-    pub fn create_product(&self, product: &Products) -> Result<(), heed::Error> {
-        let mut wtxn = self.env.write_txn()?;
-        self.products_db.put(&mut wtxn, &product.product_id, product)?;
-        wtxn.commit()
+    /// Writes a product with fresh derived totals and keeps the name index
+    /// pointing at it.
+    fn put_product(&self, wtxn: &mut RwTxn, product: &mut Products) -> Result<(), heed::Error> {
+        product.refresh_totals();
+        let previous = self.products_db.get(wtxn, &product.product_id)?;
+        bump_version(product, previous.as_ref());
+        if let Some(previous) = previous {
+            unlink_name(wtxn, &self.product_names_db, &previous.product_name, &product.product_id)?;
+        }
+        self.product_names_db.put(wtxn, &product.product_name, &product.product_id)?;
+        self.products_db.put(wtxn, &product.product_id, product)
+    }
+
+    pub fn create_product(&self, mut product: Products) -> Result<Products, InventoryError> {
+        product.validate()?;
+        self.with_write_txn(|wtxn| {
+            self.claim_key(wtxn, &self.products_db, "PROD", &mut product.product_id, "product")?;
+            claim_name(wtxn, &self.product_names_db, "product", &product.product_name, &product.product_id)?;
+            self.check_stock_locations(wtxn, &product.stock)?;
+            product.keep_bookkeeping(None);
+            self.put_product(wtxn, &mut product)?;
+            Ok(product)
+        })
     }
 
     pub fn get_product(&self, id: &str) -> Result<Option<Products>, heed::Error> {
         let rtxn = self.env.read_txn()?;
-        self.products_db.get(&rtxn, id)
+        Ok(self.products_db.get(&rtxn, id)?.map(with_totals))
     }
 
-    pub fn update_product(&self, product: &Products) -> Result<(), heed::Error> {
-        self.create_product(product)
+    pub fn update_product(
+        &self,
+        mut product: Products,
+        expected: Option<&[u64]>
+    ) -> Result<Products, InventoryError> {
+        product.validate()?;
+        self.with_write_txn(|wtxn| {
+            check_version(wtxn, &self.products_db, "product", &product.product_id, expected)?;
+            self.update_product_in(wtxn, &mut product)?;
+            Ok(product)
+        })
     }
 
-    pub fn delete_product(&self, id: &str) -> Result<bool, heed::Error> {
-        let mut wtxn = self.env.write_txn()?;
-        let deleted = self.products_db.delete(&mut wtxn, id)?;
-        wtxn.commit()?;
-        Ok(deleted)
+    pub fn patch_product(
+        &self,
+        id: &str,
+        patch: &Value,
+        expected: Option<&[u64]>
+    ) -> Result<Products, InventoryError> {
+        self.with_write_txn(|wtxn| {
+            let mut product = patched(wtxn, &self.products_db, "product", "product_id", id, patch, expected)?;
+            self.update_product_in(wtxn, &mut product)?;
+            Ok(product)
+        })
+    }
+
+    fn update_product_in(&self, wtxn: &mut RwTxn, product: &mut Products) -> Result<(), InventoryError> {
+        claim_name(wtxn, &self.product_names_db, "product", &product.product_name, &product.product_id)?;
+        self.check_stock_locations(wtxn, &product.stock)?;
+        let stored = self.products_db.get(wtxn, &product.product_id)?;
+        product.keep_bookkeeping(stored.as_ref());
+        Ok(self.put_product(wtxn, product)?)
+    }
+
+    /// Deletes a product once no order, component or movement refers to it.
+    pub fn delete_product(&self, id: &str, expected: Option<&[u64]>) -> Result<bool, InventoryError> {
+        self.with_write_txn(|wtxn| {
+            check_version(wtxn, &self.products_db, "product", id, expected)?;
+            let Some(product) = self.products_db.get(wtxn, id)? else {
+                return Ok(false);
+            };
+            let mut blocking = described("order", self.orders_by_product.keys(wtxn, id)?);
+            blocking.extend(
+                described("component", matching_keys(wtxn, &self.components_db, |c| c.product_id == id)?)
+            );
+            blocking.extend(
+                described(
+                    "movement",
+                    matching_keys(wtxn, &self.movements_db, |m| m.product_id.as_deref() == Some(id))?
+                )
+            );
+            restrict(format!("product '{}'", id), blocking)?;
+
+            unlink_name(wtxn, &self.product_names_db, &product.product_name, id)?;
+            Ok(self.products_db.delete(wtxn, id)?)
+        })
     }
 
-    pub fn get_all_products(&self) -> Result<Vec<Products>, heed::Error> {
+    pub fn list_products(
+        &self,
+        filter: &ProductFilter,
+        request: &PageRequest
+    ) -> Result<Page<Products>, InventoryError> {
         let rtxn = self.env.read_txn()?;
-        self.products_db
-            .iter(&rtxn)?
-            .map(|res| res.map(|(_, v)| v))
-            .collect()
+        let location = self.filter_location(&rtxn, filter.location.as_deref())?;
+        let mut page = read_page(&rtxn, &self.products_db, request, |product| {
+            location.as_ref().is_none_or(|location| product.quantity_at(location) > 0)
+        })?;
+        page.items = page.items.into_iter().map(with_totals).collect();
+        Ok(page)
     }
 
     // ========== COMPONENTS CRUD ==========
-    pub fn create_component(&self, component: &Components) -> Result<(), heed::Error> {
-        let mut wtxn = self.env.write_txn()?;
-        self.components_db.put(&mut wtxn, &component.component_id, component)?;
-        wtxn.commit()
+    /// Writes a component with fresh derived totals, keeps the name index
+    /// pointing at it and re-evaluates its reorder points.
+    fn put_component(&self, wtxn: &mut RwTxn, component: &mut Components) -> Result<(), heed::Error> {
+        component.refresh_totals();
+        let previous = self.components_db.get(wtxn, &component.component_id)?;
+        bump_version(component, previous.as_ref());
+        if let Some(previous) = previous {
+            unlink_name(wtxn, &self.component_names_db, &previous.component_name, &component.component_id)?;
+        }
+        self.component_names_db.put(wtxn, &component.component_name, &component.component_id)?;
+        self.components_db.put(wtxn, &component.component_id, component)?;
+        self.refresh_need_to_order(wtxn, component)
+    }
+
+    pub fn create_component(&self, mut component: Components) -> Result<Components, InventoryError> {
+        component.validate()?;
+        self.with_write_txn(|wtxn| {
+            self.claim_key(wtxn, &self.components_db, "COMP", &mut component.component_id, "component")?;
+            self.check_component_refs(wtxn, &component)?;
+            claim_name(
+                wtxn,
+                &self.component_names_db,
+                "component",
+                &component.component_name,
+                &component.component_id
+            )?;
+            component.keep_bookkeeping(None);
+            self.put_component(wtxn, &mut component)?;
+            Ok(component)
+        })
     }
 
     pub fn get_component(&self, id: &str) -> Result<Option<Components>, heed::Error> {
         let rtxn = self.env.read_txn()?;
-        self.components_db.get(&rtxn, id)
+        Ok(self.components_db.get(&rtxn, id)?.map(with_totals))
     }
 
-    pub fn update_component(&self, component: &Components) -> Result<(), heed::Error> {
-        self.create_component(component)
+    pub fn update_component(
+        &self,
+        mut component: Components,
+        expected: Option<&[u64]>
+    ) -> Result<Components, InventoryError> {
+        component.validate()?;
+        self.with_write_txn(|wtxn| {
+            check_version(wtxn, &self.components_db, "component", &component.component_id, expected)?;
+            self.update_component_in(wtxn, &mut component)?;
+            Ok(component)
+        })
     }
 
-    pub fn delete_component(&self, id: &str) -> Result<bool, heed::Error> {
-        let mut wtxn = self.env.write_txn()?;
-        let deleted = self.components_db.delete(&mut wtxn, id)?;
-        wtxn.commit()?;
-        Ok(deleted)
+    pub fn patch_component(
+        &self,
+        id: &str,
+        patch: &Value,
+        expected: Option<&[u64]>
+    ) -> Result<Components, InventoryError> {
+        self.with_write_txn(|wtxn| {
+            let mut component = patched(
+                wtxn,
+                &self.components_db,
+                "component",
+                "component_id",
+                id,
+                patch,
+                expected
+            )?;
+            self.update_component_in(wtxn, &mut component)?;
+            Ok(component)
+        })
+    }
+
+    fn update_component_in(&self, wtxn: &mut RwTxn, component: &mut Components) -> Result<(), InventoryError> {
+        self.check_component_refs(wtxn, component)?;
+        claim_name(
+            wtxn,
+            &self.component_names_db,
+            "component",
+            &component.component_name,
+            &component.component_id
+        )?;
+        let stored = self.components_db.get(wtxn, &component.component_id)?;
+        component.keep_bookkeeping(stored.as_ref());
+        Ok(self.put_component(wtxn, component)?)
+    }
+
+    fn check_component_refs(&self, rtxn: &RoTxn, component: &Components) -> Result<(), InventoryError> {
+        self.check_stock_locations(rtxn, &component.stock)?;
+        if component.product_id.is_empty() {
+            return Ok(());
+        }
+        require(rtxn, &self.products_db, "product_id", &component.product_id)
     }
 
-    pub fn get_all_components(&self) -> Result<Vec<Components>, heed::Error> {
+    /// Deletes a component once no BOM line, order, supplier order or
+    /// movement refers to it. Its reorder points and catalogue entries go
+    /// with it, and products stop listing it.
+    pub fn delete_component(&self, id: &str, expected: Option<&[u64]>) -> Result<bool, InventoryError> {
+        self.with_write_txn(|wtxn| {
+            check_version(wtxn, &self.components_db, "component", id, expected)?;
+            let Some(component) = self.components_db.get(wtxn, id)? else {
+                return Ok(false);
+            };
+            let lists = |components: &Option<Vec<String>>| {
+                components.as_ref().is_some_and(|components| components.iter().any(|c| c == id))
+            };
+            let mut blocking = described("watch", matching_keys(wtxn, &self.watches_db, |w| w.component_id == id)?);
+            blocking.extend(described("order", matching_keys(wtxn, &self.orders_db, |o| lists(&o.components))?));
+            blocking.extend(
+                described(
+                    "supplier order",
+                    matching_keys(wtxn, &self.suppliers_orders_db, |o| o.component_id == id)?
+                )
+            );
+            blocking.extend(described("movement", self.movements_by_component.keys(wtxn, id)?));
+            restrict(format!("component '{}'", id), blocking)?;
+
+            for key in matching_keys(wtxn, &self.recorder_point_db, |point| point.applies_to(&component))? {
+                self.recorder_point_db.delete(wtxn, &key)?;
+            }
+            for key in matching_keys(wtxn, &self.catalogue_db, |item| item.component_id == id)? {
+                self.catalogue_db.delete(wtxn, &key)?;
+            }
+            for key in matching_keys(wtxn, &self.products_db, |p| lists(&p.components))? {
+                if let Some(mut product) = self.products_db.get(wtxn, &key)? {
+                    if let Some(components) = &mut product.components {
+                        components.retain(|c| c != id);
+                    }
+                    self.put_product(wtxn, &mut product)?;
+                }
+            }
+
+            unlink_name(wtxn, &self.component_names_db, &component.component_name, id)?;
+            Ok(self.components_db.delete(wtxn, id)?)
+        })
+    }
+
+    pub fn list_components(
+        &self,
+        filter: &ComponentFilter,
+        request: &PageRequest
+    ) -> Result<Page<Components>, InventoryError> {
         let rtxn = self.env.read_txn()?;
-        self.components_db
-            .iter(&rtxn)?
-            .map(|res| res.map(|(_, v)| v))
-            .collect()
+        let location = self.filter_location(&rtxn, filter.location.as_deref())?;
+        let mut page = read_page(&rtxn, &self.components_db, request, |component| {
+            filter.product.as_ref().is_none_or(|product| component.product_id == *product) &&
+                location.as_ref().is_none_or(|location| component.quantity_at(location) > 0)
+        })?;
+        page.items = page.items.into_iter().map(with_totals).collect();
+        Ok(page)
     }
 
     // ========== RELATIONSHIP MANAGEMENT ==========
@@ -414,24 +1217,19 @@ impl InventoryDB {
         &self,
         product_id: &str,
         component_id: &str
-    ) -> Result<(), heed::Error> {
-        let mut wtxn = self.env.write_txn()?;
+    ) -> Result<(), InventoryError> {
+        self.with_write_txn(|wtxn| {
+            let Some(mut product) = self.products_db.get(wtxn, product_id)? else {
+                return Err(InventoryError::NotFound(format!("product '{}'", product_id)));
+            };
+            require(wtxn, &self.components_db, "component_id", component_id)?;
 
-        if let Some(mut product) = self.products_db.get(&wtxn, product_id)? {
-            match product.components {
-                Some(ref mut components) if !components.contains(&component_id.to_string()) => {
-                    components.push(component_id.to_string());
-                }
-                None => {
-                    product.components = Some(vec![component_id.to_string()]);
-                }
-                _ => {}
+            let components = product.components.get_or_insert_with(Vec::new);
+            if !components.iter().any(|c| c == component_id) {
+                components.push(component_id.to_string());
             }
-
-            self.products_db.put(&mut wtxn, product_id, &product)?;
-        }
-
-        wtxn.commit()
+            Ok(self.put_product(wtxn, &mut product)?)
+        })
     }
 
     pub fn get_product_components(&self, product_id: &str) -> Result<Vec<Components>, heed::Error> {
@@ -446,186 +1244,3284 @@ impl InventoryDB {
         let mut result = Vec::new();
         for id in components {
             if let Some(component) = self.components_db.get(&rtxn, &id)? {
-                result.push(component);
+                result.push(with_totals(component));
             }
         }
         Ok(result)
     }
-    // ========== INVENTORY MOVEMENTS ==========
-    pub fn record_movement(&self, movement: &Movements) -> Result<(), heed::Error> {
-        let mut wtxn = self.env.write_txn()?;
-
-        // Update source inventory
-        if let Some(component_name) = &movement.component_name {
-            if let Some(mut component) = self.components_db.get(&wtxn, component_name)? {
-                match movement.source_location.as_str() {
-                    "CN" => {
-                        component.cn -= movement.quantity;
-                    }
-                    "Kling" => {
-                        component.kling -= movement.quantity;
-                    }
-                    "St Jakob" => {
-                        component.st_jacob -= movement.quantity;
-                    }
-                    "Wurenlos" => {
-                        component.wurenlos -= movement.quantity;
-                    }
-                    "FLF" => {
-                        component.flf -= movement.quantity;
-                    }
-                    _ => {}
-                }
-                self.components_db.put(&mut wtxn, component_name, &component)?;
-            }
-        }
-
-        // Update destination inventory
-        if let Some(component_name) = &movement.component_name {
-            if let Some(mut component) = self.components_db.get(&wtxn, component_name)? {
-                match movement.destination_location.as_str() {
-                    "CN" => {
-                        component.cn += movement.quantity;
-                    }
-                    "Kling" => {
-                        component.kling += movement.quantity;
-                    }
-                    "St Jakob" => {
-                        component.st_jacob += movement.quantity;
-                    }
-                    "Wurenlos" => {
-                        component.wurenlos += movement.quantity;
-                    }
-                    "FLF" => {
-                        component.flf += movement.quantity;
-                    }
-                    _ => {}
-                }
-                self.components_db.put(&mut wtxn, component_name, &component)?;
-            }
-        }
-
-        // Record the movement
-        self.movements_db.put(&mut wtxn, &movement.movement_id, movement)?;
-
-        wtxn.commit()
+    // ========== LOCATIONS CRUD ==========
+    pub fn create_location(&self, mut location: Locations) -> Result<Locations, InventoryError> {
+        location.validate()?;
+        self.with_write_txn(|wtxn| {
+            self.claim_key(wtxn, &self.locations_db, "LOC", &mut location.location_id, "location")?;
+            location.version = next_version(wtxn, &self.locations_db, &location.location_id)?;
+            self.locations_db.put(wtxn, &location.location_id, &location)?;
+            Ok(location)
+        })
     }
 
-    pub fn get_movement(&self, id: &str) -> Result<Option<Movements>, heed::Error> {
+    pub fn get_location(&self, id: &str) -> Result<Option<Locations>, heed::Error> {
         let rtxn = self.env.read_txn()?;
-        self.movements_db.get(&rtxn, id)
+        self.locations_db.get(&rtxn, id)
     }
 
-    pub fn get_all_movements(&self) -> Result<Vec<Movements>, heed::Error> {
-        let rtxn = self.env.read_txn()?;
-        self.movements_db
-            .iter(&rtxn)?
-            .map(|res| res.map(|(_, v)| v))
-            .collect()
+    pub fn update_location(
+        &self,
+        mut location: Locations,
+        expected: Option<&[u64]>
+    ) -> Result<Locations, InventoryError> {
+        location.validate()?;
+        self.with_write_txn(|wtxn| {
+            check_version(wtxn, &self.locations_db, "location", &location.location_id, expected)?;
+            self.put_location(wtxn, &mut location)?;
+            Ok(location)
+        })
     }
 
-    // ========== ORDERS MANAGEMENT ==========
-    pub fn create_order(&self, order: &Orders) -> Result<(), heed::Error> {
-        let mut wtxn = self.env.write_txn()?;
-        self.orders_db.put(&mut wtxn, &order.order_id, order)?;
-        wtxn.commit()
+    pub fn patch_location(
+        &self,
+        id: &str,
+        patch: &Value,
+        expected: Option<&[u64]>
+    ) -> Result<Locations, InventoryError> {
+        self.with_write_txn(|wtxn| {
+            let mut location = patched(
+                wtxn,
+                &self.locations_db,
+                "location",
+                "location_id",
+                id,
+                patch,
+                expected
+            )?;
+            self.put_location(wtxn, &mut location)?;
+            Ok(location)
+        })
     }
 
-    pub fn get_order(&self, id: &str) -> Result<Option<Orders>, heed::Error> {
-        let rtxn = self.env.read_txn()?;
-        self.orders_db.get(&rtxn, id)
+    /// Deletes a location once no item holds stock there and no movement or
+    /// assembly timeline refers to it.
+    pub fn delete_location(&self, id: &str, expected: Option<&[u64]>) -> Result<bool, InventoryError> {
+        self.with_write_txn(|wtxn| {
+            check_version(wtxn, &self.locations_db, "location", id, expected)?;
+            let Some(location) = self.locations_db.get(wtxn, id)? else {
+                return Ok(false);
+            };
+            restrict(format!("location '{}'", id), self.location_referrers(wtxn, &location)?)?;
+            Ok(self.locations_db.delete(wtxn, id)?)
+        })
     }
 
-    pub fn update_order(&self, order: &Orders) -> Result<(), heed::Error> {
-        self.create_order(order)
+    /// Writes a location. Its kind decides how stock held there is counted,
+    /// so it only changes while nothing refers to the location.
+    fn put_location(&self, wtxn: &mut RwTxn, location: &mut Locations) -> Result<(), InventoryError> {
+        if
+            let Some(stored) = self.locations_db.get(wtxn, &location.location_id)? &&
+            stored.kind != location.kind
+        {
+            let referrers = self.location_referrers(wtxn, &stored)?;
+            restrict(format!("location '{}'", location.location_id), referrers)?;
+        }
+        location.version = next_version(wtxn, &self.locations_db, &location.location_id)?;
+        Ok(self.locations_db.put(wtxn, &location.location_id, location)?)
     }
 
-    pub fn delete_order(&self, id: &str) -> Result<bool, heed::Error> {
-        let mut wtxn = self.env.write_txn()?;
-        let deleted = self.orders_db.delete(&mut wtxn, id)?;
-        wtxn.commit()?;
-        Ok(deleted)
+    /// Items holding stock at `location`, and movements and assembly
+    /// timelines that name it.
+    fn location_referrers(&self, rtxn: &RoTxn, location: &Locations) -> Result<Vec<String>, heed::Error> {
+        let id = location.location_id.as_str();
+        let names = |key: &str| {
+            key.eq_ignore_ascii_case(&location.location_id) || key.eq_ignore_ascii_case(&location.location_name)
+        };
+        let mut referrers = described(
+            "product",
+            matching_keys(rtxn, &self.products_db, |p| p.stock.get(id).is_some_and(|&held| held > 0))?
+        );
+        referrers.extend(
+            described(
+                "component",
+                matching_keys(rtxn, &self.components_db, |c| c.stock.get(id).is_some_and(|&held| held > 0))?
+            )
+        );
+        referrers.extend(
+            described(
+                "movement",
+                matching_keys(rtxn, &self.movements_db, |m| {
+                    names(&m.source_location) || names(&m.destination_location)
+                })?
+            )
+        );
+        referrers.extend(
+            described(
+                "assembly timeline",
+                matching_keys(rtxn, &self.assembly_timeline_db, |t| names(&t.assembly_location))?
+            )
+        );
+        Ok(referrers)
     }
 
-    pub fn get_all_orders(&self) -> Result<Vec<Orders>, heed::Error> {
-        let rtxn = self.env.read_txn()?;
-        self.orders_db
-            .iter(&rtxn)?
-            .map(|res| res.map(|(_, v)| v))
-            .collect()
+    pub fn list_locations(&self, request: &PageRequest) -> Result<Page<Locations>, heed::Error> {
+        self.page(&self.locations_db, request, |_| true)
     }
 
-    // ========== INVENTORY QUERIES ==========
-    pub fn get_inventory_levels(
-        &self,
-        location: &str
-    ) -> Result<HashMap<String, u64>, heed::Error> {
-        let rtxn = self.env.read_txn()?;
-        let mut levels = HashMap::new();
-
-        for result in self.components_db.iter(&rtxn)? {
-            let (_, component) = result?;
-            let quantity = match location {
-                "CN" => component.cn,
-                "Kling" => component.kling,
-                "St Jakob" => component.st_jacob,
-                "Wurenlos" => component.wurenlos,
-                "FLF" => component.flf,
-                _ => 0,
-            };
-            levels.insert(component.component_name, quantity);
+    /// Resolves a location filter; an unknown location is an error rather
+    /// than an empty result.
+    fn filter_location(&self, rtxn: &RoTxn, key: Option<&str>) -> Result<Option<Locations>, InventoryError> {
+        let Some(key) = key else {
+            return Ok(None);
+        };
+        match self.resolve_location(rtxn, key)? {
+            Some(location) => Ok(Some(location)),
+            None => Err(InventoryError::UnknownLocation(key.to_string())),
         }
+    }
 
-        Ok(levels)
+    /// Names a location filter may appear under in records that store the
+    /// location as given by the client: its ID and display name when it is a
+    /// known location, otherwise the key itself (e.g. `ASSEMBLY-LINE`).
+    fn location_aliases(&self, rtxn: &RoTxn, key: Option<&str>) -> Result<Option<Vec<String>>, heed::Error> {
+        let Some(key) = key else {
+            return Ok(None);
+        };
+        Ok(
+            Some(match self.resolve_location(rtxn, key)? {
+                Some(location) => vec![location.location_id, location.location_name],
+                None => vec![key.to_string()],
+            })
+        )
     }
-}
 
+    /// Fails unless every key of a client-sent stock map is a registered
+    /// location that holds stock; customer and scrap quantities have fields
+    /// of their own.
+    fn check_stock_locations(&self, rtxn: &RoTxn, stock: &BTreeMap<String, u64>) -> Result<(), InventoryError> {
+        let mut errors = Vec::new();
+        for key in stock.keys() {
+            let field = format!("stock.{}", key);
+            match self.locations_db.get(rtxn, key)? {
+                None => errors.push(FieldError::new(&field, "is not a registered location")),
+                Some(location) if matches!(location.kind, LocationKind::Customer | LocationKind::Scrap) => {
+                    errors.push(FieldError::new(&field, "is not a stock-holding location"));
+                }
+                Some(_) => {}
+            }
+        }
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(InventoryError::InvalidFields(errors))
+        }
+    }
+
+    /// Looks a location up by ID, falling back to a case-insensitive match on
+    /// its ID or display name so payloads can keep sending "St Jakob".
+    fn resolve_location(&self, rtxn: &RoTxn, key: &str) -> Result<Option<Locations>, heed::Error> {
+        if let Some(location) = self.locations_db.get(rtxn, key)? {
+            return Ok(Some(location));
+        }
+
+        for item in self.locations_db.iter(rtxn)? {
+            let (_, location) = item?;
+            if
+                location.location_id.eq_ignore_ascii_case(key) ||
+                location.location_name.eq_ignore_ascii_case(key)
+            {
+                return Ok(Some(location));
+            }
+        }
+        Ok(None)
+    }
+
+    // ========== SUPPLIERS ==========
+    pub fn create_supplier(&self, mut supplier: Suppliers) -> Result<Suppliers, InventoryError> {
+        supplier.validate()?;
+        self.with_write_txn(|wtxn| {
+            self.claim_key(wtxn, &self.suppliers_db, "SUPP", &mut supplier.supplier_id, "supplier")?;
+            supplier.version = next_version(wtxn, &self.suppliers_db, &supplier.supplier_id)?;
+            self.suppliers_db.put(wtxn, &supplier.supplier_id, &supplier)?;
+            Ok(supplier)
+        })
+    }
+
+    pub fn get_supplier(&self, id: &str) -> Result<Option<Suppliers>, heed::Error> {
+        let rtxn = self.env.read_txn()?;
+        self.suppliers_db.get(&rtxn, id)
+    }
+
+    pub fn update_supplier(
+        &self,
+        mut supplier: Suppliers,
+        expected: Option<&[u64]>
+    ) -> Result<Suppliers, InventoryError> {
+        supplier.validate()?;
+        self.with_write_txn(|wtxn| {
+            check_version(wtxn, &self.suppliers_db, "supplier", &supplier.supplier_id, expected)?;
+            supplier.version = next_version(wtxn, &self.suppliers_db, &supplier.supplier_id)?;
+            self.suppliers_db.put(wtxn, &supplier.supplier_id, &supplier)?;
+            Ok(supplier)
+        })
+    }
+
+    pub fn patch_supplier(
+        &self,
+        id: &str,
+        patch: &Value,
+        expected: Option<&[u64]>
+    ) -> Result<Suppliers, InventoryError> {
+        self.with_write_txn(|wtxn| {
+            let mut supplier = patched(
+                wtxn,
+                &self.suppliers_db,
+                "supplier",
+                "supplier_id",
+                id,
+                patch,
+                expected
+            )?;
+            supplier.version = next_version(wtxn, &self.suppliers_db, id)?;
+            self.suppliers_db.put(wtxn, id, &supplier)?;
+            Ok(supplier)
+        })
+    }
+
+    /// Deletes a supplier together with its catalogue, once none of its
+    /// orders are left.
+    pub fn delete_supplier(&self, id: &str, expected: Option<&[u64]>) -> Result<bool, InventoryError> {
+        self.with_write_txn(|wtxn| {
+            check_version(wtxn, &self.suppliers_db, "supplier", id, expected)?;
+            restrict(
+                format!("supplier '{}'", id),
+                described("supplier order", self.supplier_orders_by_supplier.keys(wtxn, id)?)
+            )?;
+            let keys = self.catalogue_db
+                .prefix_iter(wtxn, &catalogue_key(id, ""))?
+                .map(|res| res.map(|(key, _)| key.to_string()))
+                .collect::<heed::Result<Vec<String>>>()?;
+            for key in keys {
+                self.catalogue_db.delete(wtxn, &key)?;
+            }
+            Ok(self.suppliers_db.delete(wtxn, id)?)
+        })
+    }
+
+    pub fn list_suppliers(&self, request: &PageRequest) -> Result<Page<Suppliers>, heed::Error> {
+        self.page(&self.suppliers_db, request, |_| true)
+    }
+
+    // ========== SUPPLIER CATALOGUE ==========
+    /// Adds or replaces a catalogue item. The supplier and component must
+    /// exist; price breaks are stored in ascending quantity order.
+    pub fn put_catalogue_item(
+        &self,
+        mut item: CatalogueItem,
+        expected: Option<&[u64]>
+    ) -> Result<CatalogueItem, InventoryError> {
+        item.validate()?;
+        item.price_breaks.sort_by_key(|price_break| price_break.min_quantity);
+
+        self.with_write_txn(|wtxn| {
+            if self.suppliers_db.get(wtxn, &item.supplier_id)?.is_none() {
+                return Err(InventoryError::NotFound(format!("supplier '{}'", item.supplier_id)));
+            }
+            if self.components_db.get(wtxn, &item.component_id)?.is_none() {
+                return Err(InventoryError::UnknownItem(item.component_id.clone()));
+            }
+            let key = catalogue_key(&item.supplier_id, &item.component_id);
+            check_version(wtxn, &self.catalogue_db, "catalogue item", &key, expected)?;
+            item.version = next_version(wtxn, &self.catalogue_db, &key)?;
+            self.catalogue_db.put(wtxn, &key, &item)?;
+            Ok(item)
+        })
+    }
+
+    pub fn patch_catalogue_item(
+        &self,
+        supplier_id: &str,
+        component_id: &str,
+        patch: &Value,
+        expected: Option<&[u64]>
+    ) -> Result<CatalogueItem, InventoryError> {
+        let key = catalogue_key(supplier_id, component_id);
+        self.with_write_txn(|wtxn| {
+            let mut item: CatalogueItem = patched_by(
+                wtxn,
+                &self.catalogue_db,
+                &key,
+                &format!("catalogue item '{}' of supplier '{}'", component_id, supplier_id),
+                &[("supplier_id", supplier_id), ("component_id", component_id)],
+                patch,
+                expected
+            )?;
+            item.price_breaks.sort_by_key(|price_break| price_break.min_quantity);
+            item.version = next_version(wtxn, &self.catalogue_db, &key)?;
+            self.catalogue_db.put(wtxn, &key, &item)?;
+            Ok(item)
+        })
+    }
+
+    pub fn get_catalogue_item(
+        &self,
+        supplier_id: &str,
+        component_id: &str
+    ) -> Result<Option<CatalogueItem>, heed::Error> {
+        let rtxn = self.env.read_txn()?;
+        self.catalogue_db.get(&rtxn, &catalogue_key(supplier_id, component_id))
+    }
+
+    pub fn delete_catalogue_item(
+        &self,
+        supplier_id: &str,
+        component_id: &str,
+        expected: Option<&[u64]>
+    ) -> Result<bool, InventoryError> {
+        let key = catalogue_key(supplier_id, component_id);
+        self.with_write_txn(|wtxn| {
+            check_version(wtxn, &self.catalogue_db, "catalogue item", &key, expected)?;
+            Ok(self.catalogue_db.delete(wtxn, &key)?)
+        })
+    }
+
+    pub fn get_supplier_catalogue(&self, supplier_id: &str) -> Result<Vec<CatalogueItem>, heed::Error> {
+        let rtxn = self.env.read_txn()?;
+        self.catalogue_db
+            .prefix_iter(&rtxn, &catalogue_key(supplier_id, ""))?
+            .map(|res| res.map(|(_, v)| v))
+            .collect()
+    }
+
+    /// The catalogue item that fills `required` units of a component at the
+    /// lowest total price across all suppliers.
+    fn cheapest_catalogue_item(
+        &self,
+        rtxn: &RoTxn,
+        component_id: &str,
+        required: u64
+    ) -> Result<Option<CatalogueItem>, heed::Error> {
+        let mut cheapest: Option<(f64, CatalogueItem)> = None;
+        for entry in self.catalogue_db.iter(rtxn)? {
+            let (_, item) = entry?;
+            if item.component_id != component_id {
+                continue;
+            }
+            let quantity = item.order_quantity(required);
+            let total = item.unit_price(quantity).unwrap_or_default() * (quantity as f64);
+            if cheapest.as_ref().is_none_or(|(best, _)| total < *best) {
+                cheapest = Some((total, item));
+            }
+        }
+        Ok(cheapest.map(|(_, item)| item))
+    }
+
+    // ========== SUPPLIER ORDERS ==========
+    /// Checks a supplier order against the supplier's catalogue and prices it
+    /// before writing it. An order quantity of 0 is filled in from the
+    /// required quantity; any other must meet the MOQ and be whole packs.
+    fn put_supplier_order(
+        &self,
+        wtxn: &mut RwTxn,
+        order: &mut SuppliersOrders
+    ) -> Result<(), InventoryError> {
+        let Some(supplier) = self.suppliers_db.get(wtxn, &order.supplier_id)? else {
+            return Err(InventoryError::Validation(format!("unknown supplier '{}'", order.supplier_id)));
+        };
+        let component_id = if order.component_id.is_empty() {
+            self.component_names_db
+                .get(wtxn, &order.component_name)?
+                .ok_or_else(|| InventoryError::UnknownItem(order.component_name.clone()))?
+                .to_string()
+        } else {
+            order.component_id.clone()
+        };
+        let Some(component) = self.components_db.get(wtxn, &component_id)? else {
+            return Err(InventoryError::UnknownItem(component_id));
+        };
+        let key = catalogue_key(&supplier.supplier_id, &component.component_id);
+        let Some(item) = self.catalogue_db.get(wtxn, &key)? else {
+            return Err(
+                InventoryError::Validation(
+                    format!(
+                        "supplier '{}' does not list component '{}'",
+                        supplier.supplier_id,
+                        component.component_id
+                    )
+                )
+            );
+        };
+
+        if order.components_roundof == 0 {
+            order.components_roundof = item.order_quantity(order.total_components_required);
+        }
+        if order.components_roundof < item.moq {
+            return Err(
+                InventoryError::Validation(
+                    format!(
+                        "{} units of '{}' is below the minimum order quantity of {}",
+                        order.components_roundof,
+                        component.component_id,
+                        item.moq
+                    )
+                )
+            );
+        }
+        if order.components_roundof < order.received_quantity {
+            return Err(
+                InventoryError::Validation(
+                    format!(
+                        "cannot order {} units of '{}' when {} have been received",
+                        order.components_roundof,
+                        component.component_id,
+                        order.received_quantity
+                    )
+                )
+            );
+        }
+        if !order.components_roundof.is_multiple_of(item.pack_size.max(1)) {
+            return Err(
+                InventoryError::Validation(
+                    format!(
+                        "{} units of '{}' is not a whole number of packs of {}",
+                        order.components_roundof,
+                        component.component_id,
+                        item.pack_size
+                    )
+                )
+            );
+        }
+
+        order.component_id = component.component_id;
+        order.component_name = component.component_name;
+        order.supplier_sku = item.supplier_sku.clone();
+        order.unit_price = item.unit_price(order.components_roundof).unwrap_or_default();
+        order.total_price = order.unit_price * (order.components_roundof as f64);
+        order.currency = supplier.currency;
+        self.store_supplier_order(wtxn, order)?;
+        Ok(())
+    }
+
+    pub fn create_supplier_order(
+        &self,
+        mut order: SuppliersOrders
+    ) -> Result<SuppliersOrders, InventoryError> {
+        order.validate()?;
+        self.with_write_txn(|wtxn| {
+            self.claim_key(wtxn, &self.suppliers_orders_db, "SUPP-ORD", &mut order.order_id, "supplier order")?;
+            self.check_supplier_order_refs(wtxn, &order)?;
+            order.keep_receipts(None);
+            order.status = SupplierOrderStatus::Draft;
+            self.put_supplier_order(wtxn, &mut order)?;
+            Ok(order)
+        })
+    }
+
+    pub fn update_supplier_order(
+        &self,
+        mut order: SuppliersOrders,
+        expected: Option<&[u64]>
+    ) -> Result<SuppliersOrders, InventoryError> {
+        order.validate()?;
+        self.with_write_txn(|wtxn| {
+            check_version(wtxn, &self.suppliers_orders_db, "supplier order", &order.order_id, expected)?;
+            self.update_supplier_order_in(wtxn, &mut order, None)?;
+            Ok(order)
+        })
+    }
+
+    /// Patches a supplier order; a changed status is applied as a transition.
+    pub fn patch_supplier_order(
+        &self,
+        id: &str,
+        patch: &Value,
+        expected: Option<&[u64]>
+    ) -> Result<SuppliersOrders, InventoryError> {
+        self.with_write_txn(|wtxn| {
+            let mut order = patched(
+                wtxn,
+                &self.suppliers_orders_db,
+                "supplier order",
+                "order_id",
+                id,
+                patch,
+                expected
+            )?;
+            let status = order.status;
+            self.update_supplier_order_in(wtxn, &mut order, Some(status))?;
+            Ok(order)
+        })
+    }
+
+    /// Stores a supplier order over the stored one; `status`, when it differs
+    /// from the stored status, is applied as a transition before the store.
+    fn update_supplier_order_in(
+        &self,
+        wtxn: &mut RwTxn,
+        order: &mut SuppliersOrders,
+        status: Option<SupplierOrderStatus>
+    ) -> Result<(), InventoryError> {
+        self.check_supplier_order_refs(wtxn, order)?;
+        let stored = self.suppliers_orders_db.get(wtxn, &order.order_id)?;
+        order.keep_receipts(stored.as_ref());
+        // Statuses only move through transitions; a new order starts as a draft.
+        order.status = stored.map_or(SupplierOrderStatus::Draft, |stored| stored.status);
+        if let Some(status) = status
+            && status != order.status
+        {
+            self.transition_supplier_order_in(order, status)?;
+        }
+        self.put_supplier_order(wtxn, order)
+    }
+
+    /// The procurement line is optional, but when set it must exist.
+    fn check_supplier_order_refs(&self, rtxn: &RoTxn, order: &SuppliersOrders) -> Result<(), InventoryError> {
+        if order.procurement_id.is_empty() {
+            return Ok(());
+        }
+        for item in self.procurements_db.iter(rtxn)? {
+            let (_, group) = item?;
+            if group.procurements.iter().any(|line| line.procurement_id == order.procurement_id) {
+                return Ok(());
+            }
+        }
+        Err(InventoryError::UnknownReference {
+            field: "procurement_id".to_string(),
+            id: order.procurement_id.clone(),
+        })
+    }
+
+    /// Deletes a supplier order nothing has been received against yet, and
+    /// drops it from the customer orders listing it.
+    pub fn delete_supplier_order(&self, id: &str, expected: Option<&[u64]>) -> Result<bool, InventoryError> {
+        self.with_write_txn(|wtxn| {
+            check_version(wtxn, &self.suppliers_orders_db, "supplier order", id, expected)?;
+            let Some(order) = self.suppliers_orders_db.get(wtxn, id)? else {
+                return Ok(false);
+            };
+            restrict(format!("supplier order '{}'", id), described("movement", order.movements))?;
+
+            let listing = matching_keys(wtxn, &self.orders_db, |customer_order| {
+                customer_order.supplier_orders.as_ref().is_some_and(|orders| orders.iter().any(|o| o == id))
+            })?;
+            for key in listing {
+                if let Some(mut customer_order) = self.orders_db.get(wtxn, &key)? {
+                    if let Some(orders) = &mut customer_order.supplier_orders {
+                        orders.retain(|o| o != id);
+                    }
+                    self.store_order(wtxn, &mut customer_order)?;
+                }
+            }
+            Ok(self.remove_supplier_order(wtxn, id)?)
+        })
+    }
+
+    pub fn list_supplier_orders(
+        &self,
+        filter: &SupplierOrderFilter,
+        request: &PageRequest
+    ) -> Result<Page<SuppliersOrders>, heed::Error> {
+        let rtxn = self.env.read_txn()?;
+        let keys = match &filter.supplier {
+            Some(supplier) => Some(self.supplier_orders_by_supplier.keys(&rtxn, supplier)?),
+            None => None,
+        };
+        read_page_in(&rtxn, &self.suppliers_orders_db, keys, request, |order| {
+            filter.status.is_none_or(|status| order.status == status) &&
+                filter.supplier.as_ref().is_none_or(|supplier| order.supplier_id == *supplier) &&
+                filter.component.as_ref().is_none_or(|component| order.component_id == *component) &&
+                within(order.order_date, filter.from, filter.to)
+        })
+    }
+
+    /// Books a full or partial delivery of a supplier order into a
+    /// stock-holding location. The receipt is recorded as a completed
+    /// movement from the supplier, and the order moves to
+    /// `PartiallyReceived` or `Received` by what is still outstanding.
+    /// `quantity` defaults to everything outstanding.
+    pub fn receive_supplier_order(
+        &self,
+        id: &str,
+        location: &str,
+        quantity: Option<u64>,
+        date: NaiveDate,
+        notes: Option<String>
+    ) -> Result<SuppliersOrders, InventoryError> {
+        self.with_write_txn(|wtxn| {
+            let Some(mut order) = self.suppliers_orders_db.get(wtxn, id)? else {
+                return Err(InventoryError::NotFound(format!("supplier order '{}'", id)));
+            };
+            check_transition(
+                format!("supplier order '{}'", id),
+                order.status,
+                SupplierOrderStatus::Received
+            )?;
+
+            let outstanding = order.outstanding();
+            let quantity = quantity.unwrap_or(outstanding);
+            if quantity == 0 || quantity > outstanding {
+                return Err(
+                    InventoryError::Validation(
+                        format!("cannot receive {} of {} outstanding", quantity, outstanding)
+                    )
+                );
+            }
+
+            let destination = self.movement_location(wtxn, location)?;
+            if matches!(destination.kind, LocationKind::Customer | LocationKind::Scrap) {
+                return Err(
+                    InventoryError::InvalidMovement(
+                        format!("supplier deliveries cannot be booked into '{}'", destination.location_id)
+                    )
+                );
+            }
+
+            let mut movement = Movements {
+                movement_id: String::new(),
+                transaction_id: order.order_id.clone(),
+                date,
+                movement_type: MovementType::Component,
+                component_id: Some(order.component_id.clone()),
+                component_name: Some(order.component_name.clone()),
+                product_id: None,
+                product_name: None,
+                source_location: order.supplier_id.clone(),
+                destination_location: destination.location_id.clone(),
+                quantity,
+                notes,
+                status: MovementStatus::Completed,
+                supplier_order_id: Some(order.order_id.clone()),
+                received_quantity: 0,
+                returned_quantity: 0,
+                receipts: Vec::new(),
+                version: 0,
+            };
+            // The supplier is outside the network, so nothing leaves a location.
+            self.update_movement_item(wtxn, &movement, |item| {
+                *item.quantity_at_mut(&destination) += quantity;
+                Ok(())
+            })?;
+            self.store_new_movement(wtxn, &mut movement)?;
+
+            order.received_quantity += quantity;
+            order.movements.push(movement.movement_id);
+            order.status = if order.outstanding() == 0 {
+                SupplierOrderStatus::Received
+            } else {
+                SupplierOrderStatus::PartiallyReceived
+            };
+            self.store_supplier_order(wtxn, &mut order)?;
+            Ok(order)
+        })
+    }
+
+    // ========== INVENTORY MOVEMENTS ==========
+    /// Resolves a movement endpoint to an active location, or fails the movement.
+    fn movement_location(&self, rtxn: &RoTxn, key: &str) -> Result<Locations, InventoryError> {
+        match self.resolve_location(rtxn, key)? {
+            Some(location) if location.active => Ok(location),
+            Some(location) => Err(InventoryError::InactiveLocation(location.location_id)),
+            None => Err(InventoryError::UnknownLocation(key.to_string())),
+        }
+    }
+
+    /// Finds the component a movement refers to. `component_id` must exist when
+    /// given; payloads that only carry a name go through the name index.
+    fn movement_component(
+        &self,
+        rtxn: &RoTxn,
+        movement: &Movements
+    ) -> Result<Components, InventoryError> {
+        let component_id = match (&movement.component_id, &movement.component_name) {
+            (Some(component_id), _) => component_id.clone(),
+            (None, Some(component_name)) =>
+                self.component_names_db
+                    .get(rtxn, component_name)?
+                    .ok_or_else(|| InventoryError::UnknownItem(component_name.clone()))?
+                    .to_string(),
+            (None, None) => {
+                return Err(InventoryError::InvalidMovement("no component given".to_string()));
+            }
+        };
+        self.components_db
+            .get(rtxn, &component_id)?
+            .ok_or(InventoryError::UnknownItem(component_id))
+    }
+
+    /// Finds the product a movement refers to, by ID first and then through the
+    /// name index.
+    fn movement_product(
+        &self,
+        rtxn: &RoTxn,
+        movement: &Movements
+    ) -> Result<Products, InventoryError> {
+        let product_id = match (&movement.product_id, &movement.product_name) {
+            (Some(product_id), _) => product_id.clone(),
+            (None, Some(product_name)) =>
+                self.product_names_db
+                    .get(rtxn, product_name)?
+                    .ok_or_else(|| InventoryError::UnknownItem(product_name.clone()))?
+                    .to_string(),
+            (None, None) => {
+                return Err(InventoryError::InvalidMovement("no product given".to_string()));
+            }
+        };
+        self.products_db
+            .get(rtxn, &product_id)?
+            .ok_or(InventoryError::UnknownItem(product_id))
+    }
+
+    /// Fills in the resolved item ID and name so stored movements always
+    /// reference the item by ID.
+    fn resolve_movement_item(
+        &self,
+        rtxn: &RoTxn,
+        movement: &mut Movements
+    ) -> Result<(), InventoryError> {
+        match movement.movement_type {
+            MovementType::Component => {
+                let component = self.movement_component(rtxn, movement)?;
+                movement.component_id = Some(component.component_id);
+                movement.component_name = Some(component.component_name);
+            }
+            MovementType::Product => {
+                let product = self.movement_product(rtxn, movement)?;
+                movement.product_id = Some(product.product_id);
+                movement.product_name = Some(product.product_name);
+            }
+        }
+        Ok(())
+    }
+
+    /// Loads the item a resolved movement refers to, lets `f` adjust its stock
+    /// and writes it back.
+    fn update_movement_item<F>(
+        &self,
+        wtxn: &mut RwTxn,
+        movement: &Movements,
+        f: F
+    ) -> Result<(), InventoryError>
+        where F: FnOnce(&mut dyn StockItem) -> Result<(), InventoryError>
+    {
+        match movement.movement_type {
+            MovementType::Component => {
+                let id = movement.component_id.as_deref().unwrap_or_default();
+                let Some(mut component) = self.components_db.get(wtxn, id)? else {
+                    return Err(InventoryError::UnknownItem(id.to_string()));
+                };
+                f(&mut component)?;
+                self.put_component(wtxn, &mut component)?;
+            }
+            MovementType::Product => {
+                let id = movement.product_id.as_deref().unwrap_or_default();
+                let Some(mut product) = self.products_db.get(wtxn, id)? else {
+                    return Err(InventoryError::UnknownItem(id.to_string()));
+                };
+                f(&mut product)?;
+                self.put_product(wtxn, &mut product)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Applies a movement and records it in one write transaction. Every check
+    /// runs before the commit, so a rejected movement leaves stock untouched.
+    /// The stored movement always carries the resolved item ID and name.
+    ///
+    /// A `Completed` movement lands at the destination straight away; a
+    /// `Dispatched` one only leaves the source and is held in `in_transit`
+    /// until [`InventoryDB::receive_movement`] or
+    /// [`InventoryDB::cancel_movement`]. Supplier deliveries go through
+    /// [`InventoryDB::receive_supplier_order`] instead.
+    pub fn record_movement(&self, mut movement: Movements) -> Result<Movements, InventoryError> {
+        movement.validate()?;
+        if movement.supplier_order_id.is_some() {
+            return Err(
+                InventoryError::InvalidMovement(
+                    "supplier deliveries are booked as supplier order receipts".to_string()
+                )
+            );
+        }
+        self.with_write_txn(|wtxn| {
+            self.claim_key(wtxn, &self.movements_db, "MOVE", &mut movement.movement_id, "movement")?;
+            let source = self.movement_location(wtxn, &movement.source_location)?;
+            let destination = self.movement_location(wtxn, &movement.destination_location)?;
+            self.resolve_movement_item(wtxn, &mut movement)?;
+
+            movement.received_quantity = 0;
+            movement.returned_quantity = 0;
+            movement.receipts.clear();
+
+            let quantity = movement.quantity;
+            match movement.status {
+                MovementStatus::Completed => {
+                    self.update_movement_item(wtxn, &movement, |item| {
+                        take_stock(item, &source, quantity)?;
+                        *item.quantity_at_mut(&destination) += quantity;
+                        Ok(())
+                    })?;
+                }
+                MovementStatus::Dispatched => {
+                    self.update_movement_item(wtxn, &movement, |item| {
+                        take_stock(item, &source, quantity)?;
+                        *item.in_transit_mut() += quantity;
+                        Ok(())
+                    })?;
+                }
+                status => {
+                    return Err(
+                        InventoryError::InvalidMovement(
+                            format!("new movements must be Completed or Dispatched, not {:?}", status)
+                        )
+                    );
+                }
+            }
+
+            // Record the movement
+            self.store_movement(wtxn, &mut movement)?;
+            Ok(movement)
+        })
+    }
+
+    /// Loads a movement that still has stock in transit, or explains why the
+    /// requested transition is not possible.
+    fn in_transit_movement(
+        &self,
+        rtxn: &RoTxn,
+        id: &str,
+        to: MovementStatus
+    ) -> Result<Movements, InventoryError> {
+        let Some(movement) = self.movements_db.get(rtxn, id)? else {
+            return Err(InventoryError::NotFound(format!("movement '{}'", id)));
+        };
+        check_transition(format!("movement '{}'", id), movement.status, to)?;
+        Ok(movement)
+    }
+
+    /// Books (part of) a dispatched movement into its destination. Without a
+    /// quantity the whole outstanding amount is received.
+    pub fn receive_movement(
+        &self,
+        id: &str,
+        quantity: Option<u64>,
+        date: NaiveDate,
+        notes: Option<String>
+    ) -> Result<Movements, InventoryError> {
+        self.with_write_txn(|wtxn| {
+            let mut movement = self.in_transit_movement(wtxn, id, MovementStatus::Received)?;
+            let outstanding = movement.outstanding();
+            let quantity = quantity.unwrap_or(outstanding);
+            if quantity == 0 || quantity > outstanding {
+                return Err(
+                    InventoryError::InvalidMovement(
+                        format!("cannot receive {} of {} outstanding", quantity, outstanding)
+                    )
+                );
+            }
+
+            let destination = self.movement_location(wtxn, &movement.destination_location)?;
+            self.update_movement_item(wtxn, &movement, |item| {
+                take_in_transit(item, quantity)?;
+                *item.quantity_at_mut(&destination) += quantity;
+                Ok(())
+            })?;
+
+            movement.received_quantity += quantity;
+            movement.receipts.push(MovementReceipt { date, quantity, notes });
+            movement.status = if movement.outstanding() == 0 {
+                MovementStatus::Received
+            } else {
+                MovementStatus::PartiallyReceived
+            };
+            self.store_movement(wtxn, &mut movement)?;
+            Ok(movement)
+        })
+    }
+
+    /// Returns whatever is still in transit to the movement's source and
+    /// closes it. Quantities already received stay where they are.
+    pub fn cancel_movement(
+        &self,
+        id: &str,
+        notes: Option<String>
+    ) -> Result<Movements, InventoryError> {
+        self.with_write_txn(|wtxn| {
+            let mut movement = self.in_transit_movement(wtxn, id, MovementStatus::Cancelled)?;
+            let outstanding = movement.outstanding();
+
+            // Stock goes home even when its source has been deactivated since.
+            let source = self.resolve_location(wtxn, &movement.source_location)?
+                .ok_or_else(|| InventoryError::UnknownLocation(movement.source_location.clone()))?;
+            self.update_movement_item(wtxn, &movement, |item| {
+                take_in_transit(item, outstanding)?;
+                *item.quantity_at_mut(&source) += outstanding;
+                Ok(())
+            })?;
+
+            movement.returned_quantity += outstanding;
+            movement.status = MovementStatus::Cancelled;
+            if notes.is_some() {
+                movement.notes = notes;
+            }
+            self.store_movement(wtxn, &mut movement)?;
+            Ok(movement)
+        })
+    }
+
+    pub fn get_movement(&self, id: &str) -> Result<Option<Movements>, heed::Error> {
+        let rtxn = self.env.read_txn()?;
+        self.movements_db.get(&rtxn, id)
+    }
+
+    pub fn list_movements(
+        &self,
+        filter: &MovementFilter,
+        request: &PageRequest
+    ) -> Result<Page<Movements>, heed::Error> {
+        let rtxn = self.env.read_txn()?;
+        let locations = self.location_aliases(&rtxn, filter.location.as_deref())?;
+        let keys = if let Some(component) = &filter.component {
+            Some(self.movements_by_component.keys(&rtxn, component)?)
+        } else if filter.from.is_some() || filter.to.is_some() {
+            let from = filter.from.map(|date| date.to_string());
+            let to = filter.to.map(|date| date.to_string());
+            Some(self.movements_by_date.keys_between(&rtxn, from.as_deref(), to.as_deref())?)
+        } else {
+            None
+        };
+        read_page_in(&rtxn, &self.movements_db, keys, request, |movement| {
+            filter.status.is_none_or(|status| movement.status == status) &&
+                filter.movement_type.is_none_or(|kind| movement.movement_type == kind) &&
+                within(movement.date, filter.from, filter.to) &&
+                filter.product.as_ref().is_none_or(|product| movement.product_id.as_ref() == Some(product)) &&
+                filter.component
+                    .as_ref()
+                    .is_none_or(|component| movement.component_id.as_ref() == Some(component)) &&
+                locations.as_ref().is_none_or(|aliases| {
+                    aliases.iter().any(|alias| {
+                        movement.source_location.eq_ignore_ascii_case(alias) ||
+                            movement.destination_location.eq_ignore_ascii_case(alias)
+                    })
+                })
+        })
+    }
+
+    // ========== ORDERS MANAGEMENT ==========
+    pub fn create_order(&self, mut order: Orders) -> Result<Orders, InventoryError> {
+        order.validate()?;
+        self.with_write_txn(|wtxn| {
+            self.claim_key(wtxn, &self.orders_db, "ORD", &mut order.order_id, "order")?;
+            require(wtxn, &self.products_db, "product_id", &order.product_id)?;
+            order.keep_reservation(None);
+            order.order_status = OrderStatus::Pending;
+            self.store_order(wtxn, &mut order)?;
+            Ok(order)
+        })
+    }
+
+    pub fn update_order(&self, mut order: Orders, expected: Option<&[u64]>) -> Result<Orders, InventoryError> {
+        order.validate()?;
+        self.with_write_txn(|wtxn| {
+            check_version(wtxn, &self.orders_db, "order", &order.order_id, expected)?;
+            self.update_order_in(wtxn, &mut order, None)?;
+            Ok(order)
+        })
+    }
+
+    /// Patches an order; a changed status is applied as a transition.
+    pub fn patch_order(
+        &self,
+        id: &str,
+        patch: &Value,
+        expected: Option<&[u64]>
+    ) -> Result<Orders, InventoryError> {
+        self.with_write_txn(|wtxn| {
+            let mut order = patched(wtxn, &self.orders_db, "order", "order_id", id, patch, expected)?;
+            let status = order.order_status;
+            self.update_order_in(wtxn, &mut order, Some(status))?;
+            Ok(order)
+        })
+    }
+
+    /// Stores an order over the stored one; `status`, when it differs from
+    /// the stored status, is applied as a transition before the store.
+    fn update_order_in(
+        &self,
+        wtxn: &mut RwTxn,
+        order: &mut Orders,
+        status: Option<OrderStatus>
+    ) -> Result<(), InventoryError> {
+        require(wtxn, &self.products_db, "product_id", &order.product_id)?;
+        let stored = self.orders_db.get(wtxn, &order.order_id)?;
+        order.keep_reservation(stored.as_ref());
+        // Statuses only move through transitions; a new order starts pending.
+        order.order_status = stored.map_or(OrderStatus::Pending, |stored| stored.order_status);
+        if let Some(status) = status
+            && status != order.order_status
+        {
+            self.transition_order_in(wtxn, order, status)?;
+        }
+        Ok(self.store_order(wtxn, order)?)
+    }
+
+    pub fn get_order(&self, id: &str) -> Result<Option<Orders>, heed::Error> {
+        let rtxn = self.env.read_txn()?;
+        self.orders_db.get(&rtxn, id)
+    }
+
+    /// Deletes an order and hands its reserved component stock back. Its
+    /// assembly timelines and procurements must be deleted first.
+    pub fn delete_order(&self, id: &str, expected: Option<&[u64]>) -> Result<bool, InventoryError> {
+        self.with_write_txn(|wtxn| {
+            check_version(wtxn, &self.orders_db, "order", id, expected)?;
+            let Some(mut order) = self.orders_db.get(wtxn, id)? else {
+                return Ok(false);
+            };
+            let mut blocking = described("assembly timeline", self.timelines_by_order.keys(wtxn, id)?);
+            blocking.extend(
+                described("procurement", matching_keys(wtxn, &self.procurements_db, |g| g.order_id == id)?)
+            );
+            restrict(format!("order '{}'", id), blocking)?;
+            self.release_reservation(wtxn, &mut order)?;
+            Ok(self.remove_order(wtxn, id)?)
+        })
+    }
+
+    pub fn list_orders(
+        &self,
+        filter: &OrderFilter,
+        request: &PageRequest
+    ) -> Result<Page<Orders>, heed::Error> {
+        let rtxn = self.env.read_txn()?;
+        let keys = match (filter.status, &filter.product) {
+            (Some(status), _) => Some(self.orders_by_status.keys(&rtxn, &status.to_string())?),
+            (None, Some(product)) => Some(self.orders_by_product.keys(&rtxn, product)?),
+            (None, None) => None,
+        };
+        read_page_in(&rtxn, &self.orders_db, keys, request, |order| {
+            filter.status.is_none_or(|status| order.order_status == status) &&
+                filter.product.as_ref().is_none_or(|product| order.product_id == *product) &&
+                within(order.expected_ship_date, filter.from, filter.to)
+        })
+    }
+
+    // ========== BILL OF MATERIALS ==========
+    pub fn create_watch(&self, mut watch: Watches) -> Result<Watches, InventoryError> {
+        watch.validate()?;
+        self.with_write_txn(|wtxn| {
+            self.claim_key(wtxn, &self.watches_db, "WATCH", &mut watch.watch_id, "watch")?;
+            self.check_watch_refs(wtxn, &watch)?;
+            watch.version = next_version(wtxn, &self.watches_db, &watch.watch_id)?;
+            self.watches_db.put(wtxn, &watch.watch_id, &watch)?;
+            Ok(watch)
+        })
+    }
+
+    pub fn update_watch(
+        &self,
+        mut watch: Watches,
+        expected: Option<&[u64]>
+    ) -> Result<Watches, InventoryError> {
+        watch.validate()?;
+        self.with_write_txn(|wtxn| {
+            check_version(wtxn, &self.watches_db, "watch", &watch.watch_id, expected)?;
+            self.check_watch_refs(wtxn, &watch)?;
+            watch.version = next_version(wtxn, &self.watches_db, &watch.watch_id)?;
+            self.watches_db.put(wtxn, &watch.watch_id, &watch)?;
+            Ok(watch)
+        })
+    }
+
+    pub fn patch_watch(
+        &self,
+        id: &str,
+        patch: &Value,
+        expected: Option<&[u64]>
+    ) -> Result<Watches, InventoryError> {
+        self.with_write_txn(|wtxn| {
+            let mut watch = patched(wtxn, &self.watches_db, "watch", "watch_id", id, patch, expected)?;
+            self.check_watch_refs(wtxn, &watch)?;
+            watch.version = next_version(wtxn, &self.watches_db, id)?;
+            self.watches_db.put(wtxn, id, &watch)?;
+            Ok(watch)
+        })
+    }
+
+    pub fn delete_watch(&self, id: &str, expected: Option<&[u64]>) -> Result<bool, InventoryError> {
+        self.with_write_txn(|wtxn| {
+            check_version(wtxn, &self.watches_db, "watch", id, expected)?;
+            Ok(self.watches_db.delete(wtxn, id)?)
+        })
+    }
+
+    /// A BOM line holds a component record, also when the component is a
+    /// sub-assembly with BOM lines of its own: reservation and assembly take
+    /// finished sub-assemblies from its stock.
+    fn check_watch_refs(&self, rtxn: &RoTxn, watch: &Watches) -> Result<(), InventoryError> {
+        require(rtxn, &self.components_db, "component_id", &watch.component_id)
+    }
+
+    /// BOM lines of a watch model or sub-assembly.
+    fn bom_lines(&self, rtxn: &RoTxn, model_id: &str) -> Result<Vec<Watches>, heed::Error> {
+        let mut lines = Vec::new();
+        for item in self.watches_db.iter(rtxn)? {
+            let (_, watch) = item?;
+            if watch.watch_model_id == model_id {
+                lines.push(watch);
+            }
+        }
+        Ok(lines)
+    }
+
+    /// Walks the BOM below `model_id`, adding gross requirements per
+    /// component. A component with BOM lines of its own is a sub-assembly and
+    /// is exploded further; `path` holds the chain being walked so that a
+    /// component reappearing below itself is reported as a cycle.
+    fn explode_into(
+        &self,
+        rtxn: &RoTxn,
+        model_id: &str,
+        quantity: u64,
+        level: u32,
+        path: &mut Vec<String>,
+        requirements: &mut BTreeMap<String, BomRequirement>
+    ) -> Result<(), InventoryError> {
+        for line in self.bom_lines(rtxn, model_id)? {
+            if path.contains(&line.component_id) {
+                let mut cycle = path.clone();
+                cycle.push(line.component_id);
+                return Err(InventoryError::BomCycle(cycle));
+            }
+
+            let required = quantity.saturating_mul(line.required_quantity);
+            let sub_assembly = !self.bom_lines(rtxn, &line.component_id)?.is_empty();
+            let requirement = requirements
+                .entry(line.component_id.clone())
+                .or_insert_with(|| BomRequirement {
+                    component_id: line.component_id.clone(),
+                    component_name: None,
+                    level,
+                    sub_assembly,
+                    required_quantity: 0,
+                    available: BTreeMap::new(),
+                    total_available: 0,
+                    shortfall: 0,
+                });
+            requirement.level = requirement.level.min(level);
+            requirement.required_quantity = requirement.required_quantity.saturating_add(required);
+
+            if sub_assembly {
+                path.push(line.component_id.clone());
+                self.explode_into(rtxn, &line.component_id, required, level + 1, path, requirements)?;
+                path.pop();
+            }
+        }
+        Ok(())
+    }
+
+    /// Explodes the BOM of a watch model for `quantity` units and sets every
+    /// requirement against current per-location stock. Returns `None` when the
+    /// model has no BOM lines.
+    pub fn explode_bom(
+        &self,
+        model_id: &str,
+        quantity: u64
+    ) -> Result<Option<BomExplosion>, InventoryError> {
+        let rtxn = self.env.read_txn()?;
+        if self.bom_lines(&rtxn, model_id)?.is_empty() {
+            return Ok(None);
+        }
+
+        let mut requirements = BTreeMap::new();
+        let mut path = vec![model_id.to_string()];
+        self.explode_into(&rtxn, model_id, quantity, 1, &mut path, &mut requirements)?;
+
+        let mut components: Vec<BomRequirement> = requirements.into_values().collect();
+        for requirement in &mut components {
+            if let Some(component) = self.components_db.get(&rtxn, &requirement.component_id)? {
+                let component = with_totals(component);
+                requirement.component_name = Some(component.component_name);
+                requirement.available = component.stock;
+                requirement.total_available = component.total_available;
+            }
+            requirement.shortfall = requirement.required_quantity.saturating_sub(
+                requirement.total_available
+            );
+        }
+        components.sort_by(|a, b| a.level.cmp(&b.level).then(a.component_id.cmp(&b.component_id)));
+
+        Ok(
+            Some(BomExplosion {
+                watch_model_id: model_id.to_string(),
+                quantity,
+                components,
+            })
+        )
+    }
+
+    // ========== ORDER RESERVATIONS ==========
+    /// Reserves stock for `quantity` units of `model_id`. Available stock of
+    /// each BOM line is booked first; whatever is still missing is exploded
+    /// further for sub-assemblies, or recorded as a gap for plain components.
+    fn reserve_bom(
+        &self,
+        wtxn: &mut RwTxn,
+        model_id: &str,
+        quantity: u64,
+        path: &mut Vec<String>,
+        booked: &mut BTreeMap<String, u64>,
+        gaps: &mut BTreeMap<String, u64>
+    ) -> Result<(), InventoryError> {
+        for line in self.bom_lines(wtxn, model_id)? {
+            if path.contains(&line.component_id) {
+                let mut cycle = path.clone();
+                cycle.push(line.component_id);
+                return Err(InventoryError::BomCycle(cycle));
+            }
+            let Some(mut component) = self.components_db.get(wtxn, &line.component_id)? else {
+                return Err(InventoryError::UnknownItem(line.component_id));
+            };
+
+            let needed = quantity.saturating_mul(line.required_quantity);
+            component.refresh_totals();
+            let take = component.total_available.min(needed);
+            component.reserver_for_orders += take;
+            self.put_component(wtxn, &mut component)?;
+            *booked.entry(line.component_id.clone()).or_default() += take;
+            gaps.entry(line.component_id.clone()).or_default();
+
+            let missing = needed - take;
+            if missing == 0 {
+                continue;
+            }
+            if self.bom_lines(wtxn, &line.component_id)?.is_empty() {
+                *gaps.entry(line.component_id).or_default() += missing;
+            } else {
+                path.push(line.component_id.clone());
+                self.reserve_bom(wtxn, &line.component_id, missing, path, booked, gaps)?;
+                path.pop();
+            }
+        }
+        Ok(())
+    }
+
+    /// Hands the stock booked for an order back and clears its reservation.
+    fn release_reservation(&self, wtxn: &mut RwTxn, order: &mut Orders) -> Result<(), heed::Error> {
+        let components = order.components.clone().unwrap_or_default();
+        let booked = order.components_booked.take().unwrap_or_default();
+        for (component_id, quantity) in components.iter().zip(booked) {
+            if let Some(mut component) = self.components_db.get(wtxn, component_id)? {
+                component.reserver_for_orders = component.reserver_for_orders.saturating_sub(
+                    quantity
+                );
+                self.put_component(wtxn, &mut component)?;
+            }
+        }
+        order.total_gap_components = None;
+        order.total_components_booked = 0;
+        order.components_required = 0;
+        Ok(())
+    }
+
+    fn reserve_order_in(&self, wtxn: &mut RwTxn, order_id: &str) -> Result<Orders, InventoryError> {
+        let Some(mut order) = self.orders_db.get(wtxn, order_id)? else {
+            return Err(InventoryError::NotFound(format!("order '{}'", order_id)));
+        };
+        if !order.order_status.is_open() {
+            return Err(
+                InventoryError::Conflict(
+                    format!("order '{}' is {}; only open orders can be reserved", order_id, order.order_status)
+                )
+            );
+        }
+        let Some(product) = self.products_db.get(wtxn, &order.product_id)? else {
+            return Err(InventoryError::UnknownItem(order.product_id));
+        };
+        let model_id = product.watch_model_id.unwrap_or(product.product_id);
+        if self.bom_lines(wtxn, &model_id)?.is_empty() {
+            return Err(
+                InventoryError::Validation(format!("watch model '{}' has no BOM lines", model_id))
+            );
+        }
+
+        self.release_reservation(wtxn, &mut order)?;
+
+        let mut booked = BTreeMap::new();
+        let mut gaps = BTreeMap::new();
+        let mut path = vec![model_id.clone()];
+        self.reserve_bom(wtxn, &model_id, order.quantity_required, &mut path, &mut booked, &mut gaps)?;
+
+        order.total_components_booked = booked.values().sum();
+        order.components_required = order.total_components_booked + gaps.values().sum::<u64>();
+        order.components = Some(booked.keys().cloned().collect());
+        order.components_booked = Some(booked.into_values().collect());
+        order.total_gap_components = Some(gaps.into_values().collect());
+        self.store_order(wtxn, &mut order)?;
+        Ok(order)
+    }
+
+    /// Explodes the order's product BOM and reserves component stock against
+    /// it, replacing any earlier reservation. Shortfalls are recorded per
+    /// component in `total_gap_components`. Only pending and processing
+    /// orders can be reserved.
+    pub fn reserve_order(&self, order_id: &str) -> Result<Orders, InventoryError> {
+        self.with_write_txn(|wtxn| self.reserve_order_in(wtxn, order_id))
+    }
+
+    /// Releases everything reserved for an order, e.g. when it is cancelled.
+    pub fn release_order(&self, order_id: &str) -> Result<Orders, InventoryError> {
+        self.with_write_txn(|wtxn| {
+            let Some(mut order) = self.orders_db.get(wtxn, order_id)? else {
+                return Err(InventoryError::NotFound(format!("order '{}'", order_id)));
+            };
+            self.release_reservation(wtxn, &mut order)?;
+            self.store_order(wtxn, &mut order)?;
+            Ok(order)
+        })
+    }
+
+    // ========== PROCUREMENT PLANNING ==========
+    /// Creates (or refreshes) the procurement group of an order with one line
+    /// per component gap, and a draft supplier order per line rounded up to
+    /// the supplier's MOQ and pack size. Without a `supplier_id` each
+    /// component goes to the catalogue supplier that fills the gap cheapest.
+    /// IDs are derived from the order and component, so running it again
+    /// updates the existing records instead of adding new ones, and drops
+    /// the lines and draft supplier orders of components that are no longer
+    /// short. Lines and supplier orders that have moved past their initial
+    /// status are left alone.
+    pub fn generate_procurements(
+        &self,
+        order_id: &str,
+        supplier_id: Option<&str>,
+        order_date: NaiveDate,
+        expected_delivery_date: Option<NaiveDate>
+    ) -> Result<ProcurementPlan, InventoryError> {
+        self.with_write_txn(|wtxn| {
+            let Some(mut order) = self.orders_db.get(wtxn, order_id)? else {
+                return Err(InventoryError::NotFound(format!("order '{}'", order_id)));
+            };
+            let Some(gaps) = order.total_gap_components.clone() else {
+                return Err(
+                    InventoryError::Validation(
+                        format!("order '{}' has no reservation; reserve it first", order_id)
+                    )
+                );
+            };
+            let components = order.components.clone().unwrap_or_default();
+            let (short, covered): (Vec<_>, Vec<_>) = components
+                .into_iter()
+                .zip(gaps)
+                .partition(|(_, gap)| *gap > 0);
+
+            let group_id = format!("PROC-GROUP-{}", order_id);
+            let existing_group = self.procurements_db.get(wtxn, &group_id)?;
+            if short.is_empty() && existing_group.is_none() {
+                return Err(
+                    InventoryError::Validation(
+                        format!("order '{}' has no component shortfalls", order_id)
+                    )
+                );
+            }
+            let mut group = existing_group.unwrap_or(Procurements {
+                procurement_id: group_id.clone(),
+                order_id: order_id.to_string(),
+                procurements: Vec::new(),
+                version: 0,
+            });
+            let mut supplier_orders = Vec::new();
+
+            // Components no longer short would be over-ordered by the lines
+            // of an earlier run; drop those still open for changes.
+            for (component_id, _) in covered {
+                let line_id = format!("PROC-{}-{}", order_id, component_id);
+                let before = group.procurements.len();
+                group.procurements.retain(|line| {
+                    line.procurement_id != line_id || line.status != ProcurementStatus::Pending
+                });
+                if group.procurements.len() < before && let Some(procurements) = &mut order.procurements {
+                    procurements.retain(|id| *id != line_id);
+                }
+
+                let supplier_order_id = format!("SUPP-ORD-{}-{}", order_id, component_id);
+                if
+                    self.suppliers_orders_db
+                        .get(wtxn, &supplier_order_id)?
+                        .is_some_and(|existing| existing.status == SupplierOrderStatus::Draft)
+                {
+                    self.remove_supplier_order(wtxn, &supplier_order_id)?;
+                    if let Some(order_supplier_orders) = &mut order.supplier_orders {
+                        order_supplier_orders.retain(|id| *id != supplier_order_id);
+                    }
+                }
+            }
+
+            for (component_id, gap) in short {
+                let line_id = format!("PROC-{}-{}", order_id, component_id);
+                match group.procurements.iter_mut().find(|line| line.procurement_id == line_id) {
+                    Some(line) if line.status == ProcurementStatus::Pending => {
+                        line.quantity = gap;
+                    }
+                    Some(_) => {}
+                    None =>
+                        group.procurements.push(Procurement {
+                            procurement_id: line_id.clone(),
+                            order_id: order_id.to_string(),
+                            components: Some(vec![component_id.clone()]),
+                            quantity: gap,
+                            status: ProcurementStatus::Pending,
+                            product: order.product.clone(),
+                        }),
+                }
+
+                let supplier_order_id = format!("SUPP-ORD-{}-{}", order_id, component_id);
+                let supplier_order = match self.suppliers_orders_db.get(wtxn, &supplier_order_id)? {
+                    Some(existing) if existing.status != SupplierOrderStatus::Draft => existing,
+                    existing => {
+                        let item = match supplier_id {
+                            Some(supplier_id) =>
+                                self.catalogue_db.get(wtxn, &catalogue_key(supplier_id, &component_id))?,
+                            None => self.cheapest_catalogue_item(wtxn, &component_id, gap)?,
+                        };
+                        let Some(item) = item else {
+                            return Err(
+                                InventoryError::Validation(
+                                    format!(
+                                        "no catalogue entry for component '{}'{}",
+                                        component_id,
+                                        supplier_id.map_or(String::new(), |id| format!(" from supplier '{}'", id))
+                                    )
+                                )
+                            );
+                        };
+                        let Some(supplier) = self.suppliers_db.get(wtxn, &item.supplier_id)? else {
+                            return Err(InventoryError::NotFound(format!("supplier '{}'", item.supplier_id)));
+                        };
+                        let order_date = existing.map_or(order_date, |existing| existing.order_date);
+                        let mut supplier_order = SuppliersOrders {
+                            supplier_id: supplier.supplier_id,
+                            component_id: component_id.clone(),
+                            component_name: String::new(),
+                            procurement_id: line_id.clone(),
+                            order_id: supplier_order_id.clone(),
+                            total_components_required: gap,
+                            components_roundof: item.order_quantity(gap),
+                            status: SupplierOrderStatus::Draft,
+                            order_date,
+                            expected_delivery_date: expected_delivery_date.unwrap_or(
+                                order_date + chrono::Days::new(supplier.default_lead_time)
+                            ),
+                            supplier_sku: String::new(),
+                            unit_price: 0.0,
+                            total_price: 0.0,
+                            currency: String::new(),
+                            received_quantity: 0,
+                            movements: Vec::new(),
+                            version: 0,
+                        };
+                        self.put_supplier_order(wtxn, &mut supplier_order)?;
+                        supplier_order
+                    }
+                };
+                supplier_orders.push(supplier_order);
+
+                let procurements = order.procurements.get_or_insert_with(Vec::new);
+                if !procurements.contains(&line_id) {
+                    procurements.push(line_id);
+                }
+                let order_supplier_orders = order.supplier_orders.get_or_insert_with(Vec::new);
+                if !order_supplier_orders.contains(&supplier_order_id) {
+                    order_supplier_orders.push(supplier_order_id);
+                }
+            }
+
+            group.version = next_version(wtxn, &self.procurements_db, &group_id)?;
+            self.procurements_db.put(wtxn, &group_id, &group)?;
+            self.store_order(wtxn, &mut order)?;
+            Ok(ProcurementPlan {
+                procurements: group,
+                supplier_orders,
+            })
+        })
+    }
+
+    pub fn create_procurements(&self, mut group: Procurements) -> Result<Procurements, InventoryError> {
+        group.validate()?;
+        self.with_write_txn(|wtxn| {
+            self.claim_key(wtxn, &self.procurements_db, "PROC-GROUP", &mut group.procurement_id, "procurement")?;
+            self.put_procurements_in(wtxn, &mut group)?;
+            Ok(group)
+        })
+    }
+
+    /// Stores a procurement group. Lines that already exist keep their
+    /// status and new ones start pending; it only changes through
+    /// [`InventoryDB::transition_procurement`].
+    pub fn put_procurements(
+        &self,
+        mut group: Procurements,
+        expected: Option<&[u64]>
+    ) -> Result<Procurements, InventoryError> {
+        group.validate()?;
+        self.with_write_txn(|wtxn| {
+            check_version(wtxn, &self.procurements_db, "procurement", &group.procurement_id, expected)?;
+            self.put_procurements_in(wtxn, &mut group)?;
+            Ok(group)
+        })
+    }
+
+    pub fn patch_procurements(
+        &self,
+        id: &str,
+        patch: &Value,
+        expected: Option<&[u64]>
+    ) -> Result<Procurements, InventoryError> {
+        self.with_write_txn(|wtxn| {
+            let mut group = patched(
+                wtxn,
+                &self.procurements_db,
+                "procurement",
+                "procurement_id",
+                id,
+                patch,
+                expected
+            )?;
+            self.put_procurements_in(wtxn, &mut group)?;
+            Ok(group)
+        })
+    }
+
+    fn put_procurements_in(&self, wtxn: &mut RwTxn, group: &mut Procurements) -> Result<(), InventoryError> {
+        require(wtxn, &self.orders_db, "order_id", &group.order_id)?;
+        let stored = self.procurements_db.get(wtxn, &group.procurement_id)?;
+        let stored_lines = stored.as_ref().map_or(&[][..], |stored| &stored.procurements);
+        for line in &mut group.procurements {
+            // New lines start pending; statuses only move through transitions.
+            line.status = stored_lines
+                .iter()
+                .find(|stored_line| stored_line.procurement_id == line.procurement_id)
+                .map_or(ProcurementStatus::Pending, |stored_line| stored_line.status);
+        }
+        group.version = next_version(wtxn, &self.procurements_db, &group.procurement_id)?;
+        Ok(self.procurements_db.put(wtxn, &group.procurement_id, group)?)
+    }
+
+    /// Deletes a procurement group once no supplier order is placed against
+    /// any of its lines.
+    pub fn delete_procurements(&self, id: &str, expected: Option<&[u64]>) -> Result<bool, InventoryError> {
+        self.with_write_txn(|wtxn| {
+            check_version(wtxn, &self.procurements_db, "procurement", id, expected)?;
+            let Some(group) = self.procurements_db.get(wtxn, id)? else {
+                return Ok(false);
+            };
+            let placed = matching_keys(wtxn, &self.suppliers_orders_db, |order| {
+                group.procurements.iter().any(|line| line.procurement_id == order.procurement_id)
+            })?;
+            restrict(format!("procurement '{}'", id), described("supplier order", placed))?;
+            Ok(self.procurements_db.delete(wtxn, id)?)
+        })
+    }
+
+    pub fn list_assembly_timelines(
+        &self,
+        filter: &AssemblyFilter,
+        request: &PageRequest
+    ) -> Result<Page<AssemblyTimeLine>, heed::Error> {
+        let rtxn = self.env.read_txn()?;
+        let locations = self.location_aliases(&rtxn, filter.location.as_deref())?;
+        let keys = match &filter.order {
+            Some(order) => Some(self.timelines_by_order.keys(&rtxn, order)?),
+            None => None,
+        };
+        read_page_in(&rtxn, &self.assembly_timeline_db, keys, request, |timeline| {
+            filter.status.is_none_or(|status| timeline.assembly_status == status) &&
+                filter.order.as_ref().is_none_or(|order| timeline.order == *order) &&
+                within(timeline.assembly_start_date, filter.from, filter.to) &&
+                locations.as_ref().is_none_or(|aliases| {
+                    aliases.iter().any(|alias| timeline.assembly_location.eq_ignore_ascii_case(alias))
+                })
+        })
+    }
+
+    // ========== ASSEMBLY SCHEDULING ==========
+    pub fn create_production_rate(&self, mut rate: ProductionRate) -> Result<ProductionRate, InventoryError> {
+        rate.validate()?;
+        self.with_write_txn(|wtxn| {
+            self.claim_key(wtxn, &self.production_rate_db, "RATE", &mut rate.prodction_rate_id, "production rate")?;
+            rate.version = next_version(wtxn, &self.production_rate_db, &rate.prodction_rate_id)?;
+            self.production_rate_db.put(wtxn, &rate.prodction_rate_id, &rate)?;
+            Ok(rate)
+        })
+    }
+
+    pub fn update_production_rate(
+        &self,
+        mut rate: ProductionRate,
+        expected: Option<&[u64]>
+    ) -> Result<ProductionRate, InventoryError> {
+        rate.validate()?;
+        self.with_write_txn(|wtxn| {
+            check_version(
+                wtxn,
+                &self.production_rate_db,
+                "production rate",
+                &rate.prodction_rate_id,
+                expected
+            )?;
+            rate.version = next_version(wtxn, &self.production_rate_db, &rate.prodction_rate_id)?;
+            self.production_rate_db.put(wtxn, &rate.prodction_rate_id, &rate)?;
+            Ok(rate)
+        })
+    }
+
+    pub fn patch_production_rate(
+        &self,
+        id: &str,
+        patch: &Value,
+        expected: Option<&[u64]>
+    ) -> Result<ProductionRate, InventoryError> {
+        self.with_write_txn(|wtxn| {
+            let mut rate = patched(
+                wtxn,
+                &self.production_rate_db,
+                "production rate",
+                "prodction_rate_id",
+                id,
+                patch,
+                expected
+            )?;
+            rate.version = next_version(wtxn, &self.production_rate_db, id)?;
+            self.production_rate_db.put(wtxn, id, &rate)?;
+            Ok(rate)
+        })
+    }
+
+    pub fn delete_production_rate(&self, id: &str, expected: Option<&[u64]>) -> Result<bool, InventoryError> {
+        self.with_write_txn(|wtxn| {
+            check_version(wtxn, &self.production_rate_db, "production rate", id, expected)?;
+            Ok(self.production_rate_db.delete(wtxn, id)?)
+        })
+    }
+
+    /// Production rate of the watch model an order builds.
+    fn order_production_rate(&self, rtxn: &RoTxn, order: &Orders) -> Result<ProductionRate, InventoryError> {
+        let Some(product) = self.products_db.get(rtxn, &order.product_id)? else {
+            return Err(InventoryError::UnknownItem(order.product_id.clone()));
+        };
+        let model_id = product.watch_model_id.unwrap_or(product.product_id);
+        for item in self.production_rate_db.iter(rtxn)? {
+            let (_, rate) = item?;
+            if rate.watch_model_id == model_id && rate.daily_production_capacity > 0 {
+                return Ok(rate);
+            }
+        }
+        Err(InventoryError::Validation(format!("no production rate for watch model '{}'", model_id)))
+    }
+
+    /// Day by which a reserved order has all its components: `today` when
+    /// nothing is short, otherwise the latest expected delivery of the
+    /// supplier orders covering its shortfalls.
+    fn components_ready_date(
+        &self,
+        rtxn: &RoTxn,
+        order: &Orders,
+        today: NaiveDate
+    ) -> Result<NaiveDate, InventoryError> {
+        let Some(gaps) = &order.total_gap_components else {
+            return Err(InventoryError::Validation("components are not reserved".to_string()));
+        };
+        let components = order.components.clone().unwrap_or_default();
+        let mut ready = today;
+        for (component_id, &gap) in components.iter().zip(gaps) {
+            if gap == 0 {
+                continue;
+            }
+            let mut covered = 0;
+            for supplier_order_id in order.supplier_orders.iter().flatten() {
+                let Some(supplier_order) = self.suppliers_orders_db.get(rtxn, supplier_order_id)? else {
+                    continue;
+                };
+                if supplier_order.component_id != *component_id || supplier_order.status == SupplierOrderStatus::Cancelled {
+                    continue;
+                }
+                covered += supplier_order.components_roundof;
+                if supplier_order.outstanding() > 0 {
+                    ready = ready.max(supplier_order.expected_delivery_date);
+                }
+            }
+            if covered < gap {
+                return Err(
+                    InventoryError::Validation(
+                        format!("{} of component '{}' is short and not on order", gap - covered, component_id)
+                    )
+                );
+            }
+        }
+        Ok(ready)
+    }
+
+    /// Proposes an assembly timeline per open order, earliest ship date first.
+    /// Each order starts once its components are in and goes to the active
+    /// assembly location that finishes it soonest, taking only the share of
+    /// each day's capacity that earlier orders left free. Orders that already
+    /// have a timeline other than a cancelled one are not replanned, and
+    /// timelines that are neither completed nor cancelled keep their slots.
+    /// With `persist` the proposals are stored as `Planned` timelines; a
+    /// proposal whose ID is taken fails the run instead of overwriting that
+    /// timeline.
+    pub fn schedule_assembly(
+        &self,
+        today: NaiveDate,
+        persist: bool
+    ) -> Result<AssemblySchedule, InventoryError> {
+        if !persist {
+            let rtxn = self.env.read_txn()?;
+            return self.plan_assembly(&rtxn, today);
+        }
+        self.with_write_txn(|wtxn| {
+            let mut schedule = self.plan_assembly(wtxn, today)?;
+            for timeline in &mut schedule.timelines {
+                self.claim_key(
+                    wtxn,
+                    &self.assembly_timeline_db,
+                    "ASSEM",
+                    &mut timeline.assembly_id,
+                    "assembly timeline"
+                )?;
+                self.store_assembly_timeline(wtxn, timeline)?;
+            }
+            Ok(schedule)
+        })
+    }
+
+    fn plan_assembly(&self, rtxn: &RoTxn, today: NaiveDate) -> Result<AssemblySchedule, InventoryError> {
+        // Orders that cannot finish within this many days are reported unscheduled.
+        const HORIZON_DAYS: i64 = 365;
+
+        let mut sites = Vec::new();
+        for item in self.locations_db.iter(rtxn)? {
+            let (_, location) = item?;
+            if location.active && location.kind == LocationKind::Assembly {
+                sites.push(location.location_id);
+            }
+        }
+        if sites.is_empty() {
+            return Err(InventoryError::Validation("no active assembly locations".to_string()));
+        }
+
+        // Share of a site's day already taken, as a fraction of the day.
+        let mut load: HashMap<(String, NaiveDate), f64> = HashMap::new();
+        let mut committed = Vec::new();
+        let mut taken = Vec::new();
+        let timelines = self.assembly_timeline_db
+            .iter(rtxn)?
+            .map(|res| res.map(|(_, v)| v))
+            .collect::<heed::Result<Vec<AssemblyTimeLine>>>()?;
+        for timeline in timelines {
+            taken.push(timeline.assembly_id.clone());
+            // An order whose timeline was cancelled is up for planning again.
+            if timeline.assembly_status == AssemblyStatus::Cancelled {
+                continue;
+            }
+            committed.push(timeline.order.clone());
+            if timeline.assembly_status == AssemblyStatus::Completed {
+                continue;
+            }
+            let Some(site) = self.resolve_location(rtxn, &timeline.assembly_location)? else {
+                continue;
+            };
+            let Some(order) = self.orders_db.get(rtxn, &timeline.order)? else {
+                continue;
+            };
+            let capacity = match self.order_production_rate(rtxn, &order) {
+                Ok(rate) => rate.daily_production_capacity as f64,
+                Err(InventoryError::Storage(e)) => {
+                    return Err(InventoryError::Storage(e));
+                }
+                Err(_) => {
+                    continue;
+                }
+            };
+            let days = (timeline.assembly_end_date - timeline.assembly_start_date).num_days() + 1;
+            // Timelines without a quantity hold their days completely.
+            let share = if timeline.quantity == 0 || days < 1 {
+                1.0
+            } else {
+                ((timeline.quantity as f64) / (capacity * (days as f64))).min(1.0)
+            };
+            for day in timeline.assembly_start_date.iter_days().take(days.max(0) as usize) {
+                *load.entry((site.location_id.clone(), day)).or_insert(0.0) += share;
+            }
+        }
+
+        let mut orders = self.orders_with_status(rtxn, &[OrderStatus::Pending, OrderStatus::Processing])?;
+        orders.retain(|order| !committed.contains(&order.order_id));
+        orders.sort_by(|a, b| {
+            a.expected_ship_date.cmp(&b.expected_ship_date).then_with(|| a.order_id.cmp(&b.order_id))
+        });
+
+        let mut schedule = AssemblySchedule {
+            timelines: Vec::new(),
+            late_orders: Vec::new(),
+            unscheduled: Vec::new(),
+        };
+        for order in orders {
+            let prepared = self
+                .order_production_rate(rtxn, &order)
+                .and_then(|rate| Ok((rate, self.components_ready_date(rtxn, &order, today)?)));
+            let (rate, ready) = match prepared {
+                Ok(prepared) => prepared,
+                Err(InventoryError::Storage(e)) => {
+                    return Err(InventoryError::Storage(e));
+                }
+                Err(e) => {
+                    schedule.unscheduled.push(UnscheduledOrder {
+                        order_id: order.order_id,
+                        reason: e.to_string(),
+                    });
+                    continue;
+                }
+            };
+            if order.quantity_required == 0 {
+                schedule.unscheduled.push(UnscheduledOrder {
+                    order_id: order.order_id,
+                    reason: "nothing to assemble".to_string(),
+                });
+                continue;
+            }
+
+            let capacity = rate.daily_production_capacity as f64;
+            let start = ready.max(today);
+            let mut best: Option<(&String, Vec<(NaiveDate, u64)>)> = None;
+            for site in &sites {
+                let mut remaining = order.quantity_required;
+                let mut slots = Vec::new();
+                for day in start.iter_days().take(HORIZON_DAYS as usize) {
+                    let used = load.get(&(site.clone(), day)).copied().unwrap_or(0.0);
+                    let units = (((1.0 - used) * capacity + 1e-9).floor().max(0.0) as u64).min(remaining);
+                    if units > 0 {
+                        slots.push((day, units));
+                        remaining -= units;
+                    }
+                    if remaining == 0 {
+                        break;
+                    }
+                }
+                let finishes_sooner = best
+                    .as_ref()
+                    .is_none_or(|(_, best_slots)| slots.last() < best_slots.last());
+                if remaining == 0 && finishes_sooner {
+                    best = Some((site, slots));
+                }
+            }
+            let Some((site, slots)) = best else {
+                schedule.unscheduled.push(UnscheduledOrder {
+                    order_id: order.order_id,
+                    reason: format!("does not fit assembly capacity within {} days", HORIZON_DAYS),
+                });
+                continue;
+            };
+
+            for &(day, units) in &slots {
+                *load.entry((site.clone(), day)).or_insert(0.0) += (units as f64) / capacity;
+            }
+            let start_date = slots[0].0;
+            let end_date = slots[slots.len() - 1].0;
+            if end_date > order.expected_ship_date {
+                schedule.late_orders.push(LateOrder {
+                    order_id: order.order_id.clone(),
+                    expected_ship_date: order.expected_ship_date,
+                    assembly_end_date: end_date,
+                    days_late: (end_date - order.expected_ship_date).num_days(),
+                });
+            }
+            // A cancelled timeline of the order may hold the usual ID.
+            let mut assembly_id = format!("ASSEM-{}", order.order_id);
+            let mut attempt = 1;
+            while taken.contains(&assembly_id) {
+                attempt += 1;
+                assembly_id = format!("ASSEM-{}-{}", order.order_id, attempt);
+            }
+            let timeline = AssemblyTimeLine {
+                assembly_id,
+                order: order.order_id,
+                product: order.product,
+                movements: Vec::new(),
+                components_required: order.components_required,
+                total_components_booked: order.total_components_booked,
+                components: order.components.unwrap_or_default(),
+                total_gap_components: order.total_gap_components,
+                assembly_location: site.clone(),
+                components_received_date: ready,
+                assembly_start_date: start_date,
+                assembly_end_date: end_date,
+                assembly_status: AssemblyStatus::Planned,
+                total_duration: ((end_date - start_date).num_days() + 1) as u64,
+                assembly_notes: None,
+                quantity: order.quantity_required,
+                produced_quantity: 0,
+                scrapped: BTreeMap::new(),
+                version: 0,
+            };
+            schedule.timelines.push(timeline);
+        }
+        Ok(schedule)
+    }
+
+    // ========== ASSEMBLY EXECUTION ==========
+    /// Stores an assembly timeline. An existing timeline keeps its status and
+    /// the bookkeeping of its execution, and once started also its quantity
+    /// and location.
+    pub fn put_assembly_timeline(
+        &self,
+        mut timeline: AssemblyTimeLine,
+        expected: Option<&[u64]>
+    ) -> Result<AssemblyTimeLine, InventoryError> {
+        timeline.validate()?;
+        self.with_write_txn(|wtxn| {
+            check_version(
+                wtxn,
+                &self.assembly_timeline_db,
+                "assembly timeline",
+                &timeline.assembly_id,
+                expected
+            )?;
+            self.put_assembly_timeline_in(wtxn, &mut timeline, None)?;
+            Ok(timeline)
+        })
+    }
+
+    /// Patches an assembly timeline; a changed status is applied as a
+    /// transition.
+    pub fn patch_assembly_timeline(
+        &self,
+        id: &str,
+        patch: &Value,
+        expected: Option<&[u64]>
+    ) -> Result<AssemblyTimeLine, InventoryError> {
+        self.with_write_txn(|wtxn| {
+            let mut timeline = patched(
+                wtxn,
+                &self.assembly_timeline_db,
+                "assembly timeline",
+                "assembly_id",
+                id,
+                patch,
+                expected
+            )?;
+            let status = timeline.assembly_status;
+            self.put_assembly_timeline_in(wtxn, &mut timeline, Some(status))?;
+            Ok(timeline)
+        })
+    }
+
+    /// Stores a timeline over the stored one; `status`, when it differs from
+    /// the stored status, is applied as a transition before the store.
+    fn put_assembly_timeline_in(
+        &self,
+        wtxn: &mut RwTxn,
+        timeline: &mut AssemblyTimeLine,
+        status: Option<AssemblyStatus>
+    ) -> Result<(), InventoryError> {
+        if let Some(stored) = self.assembly_timeline_db.get(wtxn, &timeline.assembly_id)? {
+            // Completion splits what went onto the line by the quantity
+            // started, and returns it to where it was taken from.
+            if matches!(stored.assembly_status, AssemblyStatus::InProgress | AssemblyStatus::Completed) {
+                timeline.quantity = stored.quantity;
+                timeline.assembly_location = stored.assembly_location;
+            }
+            timeline.assembly_status = stored.assembly_status;
+            timeline.movements = stored.movements;
+            timeline.produced_quantity = stored.produced_quantity;
+            timeline.scrapped = stored.scrapped;
+        } else {
+            timeline.reset_execution();
+        }
+        if let Some(status) = status
+            && status != timeline.assembly_status
+        {
+            self.transition_assembly_in(timeline, status)?;
+        }
+        self.check_timeline_refs(wtxn, timeline)?;
+        Ok(self.store_assembly_timeline(wtxn, timeline)?)
+    }
+
+    /// Creates a `Planned` timeline; its execution bookkeeping is only filled
+    /// in by starting and completing it.
+    pub fn create_assembly_timeline(
+        &self,
+        mut timeline: AssemblyTimeLine
+    ) -> Result<AssemblyTimeLine, InventoryError> {
+        timeline.validate()?;
+        self.with_write_txn(|wtxn| {
+            self.claim_key(wtxn, &self.assembly_timeline_db, "ASSEM", &mut timeline.assembly_id, "assembly timeline")?;
+            timeline.reset_execution();
+            self.check_timeline_refs(wtxn, &timeline)?;
+            self.store_assembly_timeline(wtxn, &mut timeline)?;
+            Ok(timeline)
+        })
+    }
+
+    fn check_timeline_refs(&self, rtxn: &RoTxn, timeline: &AssemblyTimeLine) -> Result<(), InventoryError> {
+        require(rtxn, &self.orders_db, "order", &timeline.order)?;
+        for movement_id in &timeline.movements {
+            require(rtxn, &self.movements_db, "movements", movement_id)?;
+        }
+        Ok(())
+    }
+
+    pub fn delete_assembly_timeline(&self, id: &str, expected: Option<&[u64]>) -> Result<bool, InventoryError> {
+        self.with_write_txn(|wtxn| {
+            check_version(wtxn, &self.assembly_timeline_db, "assembly timeline", id, expected)?;
+            Ok(self.remove_assembly_timeline(wtxn, id)?)
+        })
+    }
+
+    fn assembly_timeline(&self, rtxn: &RoTxn, id: &str) -> Result<AssemblyTimeLine, InventoryError> {
+        self.assembly_timeline_db
+            .get(rtxn, id)?
+            .ok_or_else(|| InventoryError::NotFound(format!("assembly timeline '{}'", id)))
+    }
+
+    /// Assembly location of a timeline; only active assembly sites qualify.
+    fn assembly_site(&self, rtxn: &RoTxn, timeline: &AssemblyTimeLine) -> Result<Locations, InventoryError> {
+        let site = self.movement_location(rtxn, &timeline.assembly_location)?;
+        if site.kind != LocationKind::Assembly {
+            return Err(
+                InventoryError::InvalidMovement(
+                    format!("'{}' is not an assembly location", site.location_id)
+                )
+            );
+        }
+        Ok(site)
+    }
+
+    /// Starts an assembly: moves the BOM quantities for the timeline's watches
+    /// out of the assembly location's stock into `assembly_line`. Whatever the
+    /// order had reserved of those components is released as it is moved.
+    pub fn start_assembly(
+        &self,
+        id: &str,
+        date: NaiveDate,
+        notes: Option<String>
+    ) -> Result<AssemblyTimeLine, InventoryError> {
+        self.with_write_txn(|wtxn| {
+            let mut timeline = self.assembly_timeline(wtxn, id)?;
+            check_transition(
+                format!("assembly timeline '{}'", id),
+                timeline.assembly_status,
+                AssemblyStatus::InProgress
+            )?;
+            let site = self.assembly_site(wtxn, &timeline)?;
+            let Some(mut order) = self.orders_db.get(wtxn, &timeline.order)? else {
+                return Err(InventoryError::NotFound(format!("order '{}'", timeline.order)));
+            };
+            if !order.order_status.is_open() {
+                return Err(
+                    InventoryError::Conflict(
+                        format!(
+                            "order '{}' is {}; only open orders can be assembled",
+                            order.order_id,
+                            order.order_status
+                        )
+                    )
+                );
+            }
+            let Some(product) = self.products_db.get(wtxn, &order.product_id)? else {
+                return Err(InventoryError::UnknownItem(order.product_id));
+            };
+            let model_id = product.watch_model_id.unwrap_or(product.product_id);
+            let lines = self.bom_lines(wtxn, &model_id)?;
+            let line_ids: Vec<String> = lines.iter().map(|line| line.component_id.clone()).collect();
+            if lines.is_empty() {
+                return Err(
+                    InventoryError::Validation(format!("watch model '{}' has no BOM lines", model_id))
+                );
+            }
+            if timeline.quantity == 0 {
+                timeline.quantity = order.quantity_required;
+            }
+
+            let order_components = order.components.clone().unwrap_or_default();
+            let mut booked = order.components_booked.clone().unwrap_or_default();
+            for line in lines {
+                let Some(quantity) = line.required_quantity.checked_mul(timeline.quantity) else {
+                    return Err(
+                        InventoryError::Validation(
+                            format!(
+                                "{} watches need more of component '{}' than can be counted",
+                                timeline.quantity,
+                                line.component_id
+                            )
+                        )
+                    );
+                };
+                let Some(mut component) = self.components_db.get(wtxn, &line.component_id)? else {
+                    return Err(InventoryError::UnknownItem(line.component_id));
+                };
+                take_stock(&mut component, &site, quantity)?;
+                component.assembly_line += quantity;
+
+                // A released order has nothing booked to hand back.
+                if
+                    let Some(index) = order_components.iter().position(|c| *c == line.component_id) &&
+                    let Some(slot) = booked.get_mut(index)
+                {
+                    let released = (*slot).min(quantity);
+                    *slot -= released;
+                    order.total_components_booked = order.total_components_booked.saturating_sub(released);
+                    component.reserver_for_orders = component.reserver_for_orders.saturating_sub(released);
+                }
+                self.put_component(wtxn, &mut component)?;
+
+                let mut movement = assembly_movement(
+                    &timeline,
+                    date,
+                    &site.location_id,
+                    ASSEMBLY_LINE,
+                    quantity
+                );
+                movement.component_id = Some(component.component_id.clone());
+                movement.component_name = Some(component.component_name.clone());
+                self.store_new_movement(wtxn, &mut movement)?;
+                timeline.movements.push(movement.movement_id);
+            }
+            // Parts booked to build short sub-assemblies are not drawn on
+            // here; hand back the share of the watches started.
+            for (component_id, slot) in order_components.iter().zip(booked.iter_mut()) {
+                if line_ids.contains(component_id) || *slot == 0 {
+                    continue;
+                }
+                let share = u128::from(*slot) * u128::from(timeline.quantity) /
+                    u128::from(order.quantity_required.max(1));
+                let released = u64::try_from(share).unwrap_or(u64::MAX).min(*slot);
+                if let Some(mut component) = self.components_db.get(wtxn, component_id)? {
+                    component.reserver_for_orders = component.reserver_for_orders.saturating_sub(released);
+                    self.put_component(wtxn, &mut component)?;
+                }
+                *slot -= released;
+                order.total_components_booked = order.total_components_booked.saturating_sub(released);
+            }
+            if order.components_booked.is_some() {
+                order.components_booked = Some(booked);
+                self.store_order(wtxn, &mut order)?;
+            }
+
+            timeline.assembly_status = AssemblyStatus::InProgress;
+            timeline.assembly_start_date = date;
+            if notes.is_some() {
+                timeline.assembly_notes = notes;
+            }
+            self.store_assembly_timeline(wtxn, &mut timeline)?;
+            Ok(timeline)
+        })
+    }
+
+    /// Completes an assembly. Components for the `produced` watches are
+    /// consumed from `assembly_line`, reported scrap goes to `waste` and is
+    /// moved to the active scrap location, and anything left over returns to
+    /// the assembly location's stock. The
+    /// finished watches are added to the product's stock there.
+    pub fn complete_assembly(
+        &self,
+        id: &str,
+        produced: Option<u64>,
+        scrapped: BTreeMap<String, u64>,
+        date: NaiveDate
+    ) -> Result<AssemblyTimeLine, InventoryError> {
+        self.with_write_txn(|wtxn| {
+            let mut timeline = self.assembly_timeline(wtxn, id)?;
+            check_transition(
+                format!("assembly timeline '{}'", id),
+                timeline.assembly_status,
+                AssemblyStatus::Completed
+            )?;
+            let site = self.assembly_site(wtxn, &timeline)?;
+            let produced = produced.unwrap_or(timeline.quantity);
+            if produced > timeline.quantity {
+                return Err(
+                    InventoryError::Validation(
+                        format!("cannot produce {} of {} watches started", produced, timeline.quantity)
+                    )
+                );
+            }
+            let Some(order) = self.orders_db.get(wtxn, &timeline.order)? else {
+                return Err(InventoryError::NotFound(format!("order '{}'", timeline.order)));
+            };
+            let Some(mut product) = self.products_db.get(wtxn, &order.product_id)? else {
+                return Err(InventoryError::UnknownItem(order.product_id));
+            };
+
+            // What went onto the line, per component, from the start movements.
+            let mut started: BTreeMap<String, u64> = BTreeMap::new();
+            for movement_id in &timeline.movements {
+                if let Some(movement) = self.movements_db.get(wtxn, movement_id)? &&
+                    movement.destination_location == ASSEMBLY_LINE &&
+                    let Some(component_id) = movement.component_id
+                {
+                    *started.entry(component_id).or_insert(0) += movement.quantity;
+                }
+            }
+            if started.is_empty() {
+                return Err(
+                    InventoryError::InvalidMovement(
+                        format!("assembly timeline '{}' has no components on the assembly line", id)
+                    )
+                );
+            }
+            if let Some(component_id) = scrapped.keys().find(|c| !started.contains_key(*c)) {
+                return Err(
+                    InventoryError::InvalidMovement(
+                        format!("component '{}' is not on the assembly line", component_id)
+                    )
+                );
+            }
+            let mut scrap_location = None;
+            for item in self.locations_db.iter(wtxn)? {
+                let (_, location) = item?;
+                if location.active && location.kind == LocationKind::Scrap {
+                    scrap_location = Some(location.location_id);
+                    break;
+                }
+            }
+            if scrap_location.is_none() && scrapped.values().any(|&quantity| quantity > 0) {
+                return Err(
+                    InventoryError::Validation(
+                        "scrap was reported but no active scrap location is registered".to_string()
+                    )
+                );
+            }
+            let scrap_location = scrap_location.unwrap_or_default();
+
+            let mut movements = Vec::new();
+            for (component_id, on_line) in started {
+                let Some(mut component) = self.components_db.get(wtxn, &component_id)? else {
+                    return Err(InventoryError::UnknownItem(component_id));
+                };
+                let per_watch = on_line.checked_div(timeline.quantity).unwrap_or(0);
+                let consumed = per_watch * produced;
+                let scrap = scrapped.get(&component_id).copied().unwrap_or(0);
+                let Some(leftover) = on_line.checked_sub(consumed + scrap) else {
+                    return Err(
+                        InventoryError::InvalidMovement(
+                            format!(
+                                "{} of '{}' consumed and {} scrapped exceeds the {} on the line",
+                                consumed,
+                                component_id,
+                                scrap,
+                                on_line
+                            )
+                        )
+                    );
+                };
+                let Some(assembly_line) = component.assembly_line.checked_sub(on_line) else {
+                    return Err(InventoryError::InsufficientStock {
+                        item: component_id,
+                        location: ASSEMBLY_LINE.to_string(),
+                        available: component.assembly_line,
+                        requested: on_line,
+                    });
+                };
+                component.assembly_line = assembly_line;
+                component.waste += scrap;
+                *component.quantity_at_mut(&site) += leftover;
+                self.put_component(wtxn, &mut component)?;
+
+                let legs = [(scrap_location.as_str(), scrap), (site.location_id.as_str(), leftover)];
+                for (destination, quantity) in legs {
+                    if quantity > 0 {
+                        let mut movement = assembly_movement(
+                            &timeline,
+                            date,
+                            ASSEMBLY_LINE,
+                            destination,
+                            quantity
+                        );
+                        movement.component_id = Some(component.component_id.clone());
+                        movement.component_name = Some(component.component_name.clone());
+                        movements.push(movement);
+                    }
+                }
+            }
+
+            *product.quantity_at_mut(&site) += produced;
+            self.put_product(wtxn, &mut product)?;
+            if produced > 0 {
+                let mut output = assembly_movement(
+                    &timeline,
+                    date,
+                    ASSEMBLY_LINE,
+                    &site.location_id,
+                    produced
+                );
+                output.movement_type = MovementType::Product;
+                output.product_id = Some(product.product_id.clone());
+                output.product_name = Some(product.product_name.clone());
+                movements.push(output);
+            }
+            for mut movement in movements {
+                self.store_new_movement(wtxn, &mut movement)?;
+                timeline.movements.push(movement.movement_id);
+            }
+
+            timeline.produced_quantity = produced;
+            timeline.scrapped = scrapped;
+            timeline.assembly_status = AssemblyStatus::Completed;
+            timeline.assembly_end_date = date;
+            timeline.total_duration = ((date - timeline.assembly_start_date).num_days() + 1).max(1) as u64;
+            self.store_assembly_timeline(wtxn, &mut timeline)?;
+            Ok(timeline)
+        })
+    }
+
+    // ========== STATUS TRANSITIONS ==========
+    /// Moves an order along its lifecycle. Cancelling an order releases its
+    /// component reservation.
+    pub fn transition_order(&self, id: &str, status: OrderStatus) -> Result<Orders, InventoryError> {
+        self.with_write_txn(|wtxn| {
+            let Some(mut order) = self.orders_db.get(wtxn, id)? else {
+                return Err(InventoryError::NotFound(format!("order '{}'", id)));
+            };
+            self.transition_order_in(wtxn, &mut order, status)?;
+            self.store_order(wtxn, &mut order)?;
+            Ok(order)
+        })
+    }
+
+    /// Checks and applies an order transition; the caller stores the order.
+    fn transition_order_in(
+        &self,
+        wtxn: &mut RwTxn,
+        order: &mut Orders,
+        status: OrderStatus
+    ) -> Result<(), InventoryError> {
+        check_transition(format!("order '{}'", order.order_id), order.order_status, status)?;
+        if status == OrderStatus::Cancelled {
+            self.release_reservation(wtxn, order)?;
+        }
+        order.order_status = status;
+        Ok(())
+    }
+
+    pub fn transition_supplier_order(
+        &self,
+        id: &str,
+        status: SupplierOrderStatus
+    ) -> Result<SuppliersOrders, InventoryError> {
+        self.with_write_txn(|wtxn| {
+            let Some(mut order) = self.suppliers_orders_db.get(wtxn, id)? else {
+                return Err(InventoryError::NotFound(format!("supplier order '{}'", id)));
+            };
+            self.transition_supplier_order_in(&mut order, status)?;
+            self.store_supplier_order(wtxn, &mut order)?;
+            Ok(order)
+        })
+    }
+
+    /// Checks and applies a supplier order transition; the caller stores the
+    /// order.
+    fn transition_supplier_order_in(
+        &self,
+        order: &mut SuppliersOrders,
+        status: SupplierOrderStatus
+    ) -> Result<(), InventoryError> {
+        if matches!(status, SupplierOrderStatus::PartiallyReceived | SupplierOrderStatus::Received) {
+            return Err(
+                InventoryError::Validation(
+                    "supplier orders are received by posting receipts".to_string()
+                )
+            );
+        }
+        check_transition(format!("supplier order '{}'", order.order_id), order.status, status)?;
+        order.status = status;
+        Ok(())
+    }
+
+    /// Moves one line of a procurement group along its lifecycle.
+    pub fn transition_procurement(
+        &self,
+        group_id: &str,
+        line_id: &str,
+        status: ProcurementStatus
+    ) -> Result<Procurements, InventoryError> {
+        self.with_write_txn(|wtxn| {
+            let Some(mut group) = self.procurements_db.get(wtxn, group_id)? else {
+                return Err(InventoryError::NotFound(format!("procurement '{}'", group_id)));
+            };
+            let Some(line) = group.procurements
+                .iter_mut()
+                .find(|line| line.procurement_id == line_id) else {
+                return Err(
+                    InventoryError::NotFound(format!("procurement line '{}' in '{}'", line_id, group_id))
+                );
+            };
+            check_transition(format!("procurement line '{}'", line_id), line.status, status)?;
+            line.status = status;
+            group.version = next_version(wtxn, &self.procurements_db, group_id)?;
+            self.procurements_db.put(wtxn, group_id, &group)?;
+            Ok(group)
+        })
+    }
+
+    pub fn transition_assembly(
+        &self,
+        id: &str,
+        status: AssemblyStatus
+    ) -> Result<AssemblyTimeLine, InventoryError> {
+        self.with_write_txn(|wtxn| {
+            let mut timeline = self.assembly_timeline(wtxn, id)?;
+            self.transition_assembly_in(&mut timeline, status)?;
+            self.store_assembly_timeline(wtxn, &mut timeline)?;
+            Ok(timeline)
+        })
+    }
+
+    /// Checks and applies an assembly transition; the caller stores the
+    /// timeline.
+    fn transition_assembly_in(
+        &self,
+        timeline: &mut AssemblyTimeLine,
+        status: AssemblyStatus
+    ) -> Result<(), InventoryError> {
+        if matches!(status, AssemblyStatus::InProgress | AssemblyStatus::Completed) {
+            return Err(
+                InventoryError::Validation(
+                    "assemblies are started and completed through their start and complete endpoints".to_string()
+                )
+            );
+        }
+        check_transition(
+            format!("assembly timeline '{}'", timeline.assembly_id),
+            timeline.assembly_status,
+            status
+        )?;
+        timeline.assembly_status = status;
+        Ok(())
+    }
+
+    // ========== REORDER POINTS ==========
+    /// Finds the component a reorder point tracks, by ID first and then
+    /// through the name index.
+    fn reorder_component(
+        &self,
+        rtxn: &RoTxn,
+        point: &RecorderPoint
+    ) -> Result<Components, InventoryError> {
+        let component_id = if point.component_id.is_empty() {
+            self.component_names_db
+                .get(rtxn, &point.component_name)?
+                .ok_or_else(|| InventoryError::UnknownItem(point.component_name.clone()))?
+                .to_string()
+        } else {
+            point.component_id.clone()
+        };
+        self.components_db
+            .get(rtxn, &component_id)?
+            .map(with_totals)
+            .ok_or(InventoryError::UnknownItem(component_id))
+    }
+
+    /// Flags `need_to_order` on every reorder point of `component` whose
+    /// reorder point its free-to-promise stock has fallen to.
+    fn refresh_need_to_order(&self, wtxn: &mut RwTxn, component: &Components) -> Result<(), heed::Error> {
+        let mut changed = Vec::new();
+        for item in self.recorder_point_db.iter(wtxn)? {
+            let (_, mut point) = item?;
+            let need_to_order = component.free_to_promise <= point.reorder_point;
+            if point.applies_to(component) && point.need_to_order != need_to_order {
+                point.need_to_order = need_to_order;
+                point.version += 1;
+                changed.push(point);
+            }
+        }
+        for point in changed {
+            self.recorder_point_db.put(wtxn, &point.recorder_point_id, &point)?;
+        }
+        Ok(())
+    }
+
+    /// Daily consumption of a component over the `days` days ending on `today`,
+    /// oldest first. Consumption is stock leaving a stock-holding location for
+    /// anywhere that does not hold stock: customers, scrap, or an assembly
+    /// line. Transfers between sites and receipts from outside are not usage.
+    fn daily_usage(
+        &self,
+        rtxn: &RoTxn,
+        component_id: &str,
+        today: NaiveDate,
+        days: u32
+    ) -> Result<Vec<u64>, heed::Error> {
+        let mut kinds = HashMap::new();
+        for item in self.locations_db.iter(rtxn)? {
+            let (_, location) = item?;
+            kinds.insert(location.location_id.to_lowercase(), location.kind);
+            kinds.insert(location.location_name.to_lowercase(), location.kind);
+        }
+        let holds_stock = |key: &str| {
+            matches!(
+                kinds.get(&key.to_lowercase()),
+                Some(LocationKind::Warehouse | LocationKind::Assembly)
+            )
+        };
+
+        let day = |date: NaiveDate| {
+            let age = (today - date).num_days();
+            (0..i64::from(days)).contains(&age).then(|| (i64::from(days) - 1 - age) as usize)
+        };
+
+        // Components going onto the assembly line count as used on the day
+        // the assembly starts; whatever comes back off the line when it is
+        // completed is netted out of that same day.
+        let mut usage = vec![0; days as usize];
+        let mut started = HashMap::new();
+        let mut returned = Vec::new();
+        for key in self.movements_by_component.keys(rtxn, component_id)? {
+            let Some(movement) = self.movements_db.get(rtxn, &key)? else {
+                continue;
+            };
+            if
+                movement.movement_type != MovementType::Component ||
+                movement.component_id.as_deref() != Some(component_id) ||
+                movement.status == MovementStatus::Cancelled
+            {
+                continue;
+            }
+            if movement.source_location == ASSEMBLY_LINE && holds_stock(&movement.destination_location) {
+                returned.push((movement.transaction_id, movement.quantity));
+                continue;
+            }
+            if !holds_stock(&movement.source_location) || holds_stock(&movement.destination_location) {
+                continue;
+            }
+            if let Some(day) = day(movement.date) {
+                usage[day] += movement.quantity - movement.returned_quantity;
+                if movement.destination_location == ASSEMBLY_LINE {
+                    started.insert(movement.transaction_id, day);
+                }
+            }
+        }
+        for (transaction_id, quantity) in returned {
+            if let Some(&day) = started.get(&transaction_id) {
+                usage[day] = usage[day].saturating_sub(quantity);
+            }
+        }
+        Ok(usage)
+    }
+
+    /// Recomputes a reorder point from the component's usage over the policy
+    /// window: lead-time demand is mean daily usage over the supplier lead
+    /// time, and safety stock covers its variability at the policy service
+    /// level (`z * sigma * sqrt(lead time)`).
+    fn recompute_reorder_point_in(
+        &self,
+        wtxn: &mut RwTxn,
+        point: &mut RecorderPoint,
+        policy: ReorderPolicy,
+        today: NaiveDate
+    ) -> Result<(), InventoryError> {
+        let component = self.reorder_component(wtxn, point)?;
+        let usage = self.daily_usage(wtxn, &component.component_id, today, policy.window_days)?;
+
+        let days = usage.len() as f64;
+        let mean = usage.iter().sum::<u64>() as f64 / days;
+        let variance = if usage.len() > 1 {
+            usage
+                .iter()
+                .map(|&day| (day as f64 - mean).powi(2))
+                .sum::<f64>() / (days - 1.0)
+        } else {
+            0.0
+        };
+        let lead_time = point.supplier_lead_time as f64;
+
+        point.component_id = component.component_id.clone();
+        point.component_name = component.component_name.clone();
+        point.assumed_daily_usage = mean;
+        point.daily_usage_std_dev = variance.sqrt();
+        point.lead_time_demand = mean * lead_time;
+        point.safety_stock =
+            normal_quantile(policy.service_level) * point.daily_usage_std_dev * lead_time.sqrt();
+        point.reorder_point = (point.lead_time_demand + point.safety_stock).ceil() as u64;
+        point.need_to_order = component.free_to_promise <= point.reorder_point;
+        point.last_computed = Some(today);
+        point.version = next_version(wtxn, &self.recorder_point_db, &point.recorder_point_id)?;
+        self.recorder_point_db.put(wtxn, &point.recorder_point_id, point)?;
+        Ok(())
+    }
+
+    /// Stores a reorder point against an existing component, evaluating
+    /// `need_to_order` from current stock rather than trusting the payload.
+    pub fn create_reorder_point(&self, mut point: RecorderPoint) -> Result<RecorderPoint, InventoryError> {
+        point.validate()?;
+        self.with_write_txn(|wtxn| {
+            self.claim_key(wtxn, &self.recorder_point_db, "REORD", &mut point.recorder_point_id, "reorder point")?;
+            self.put_reorder_point_in(wtxn, &mut point)?;
+            Ok(point)
+        })
+    }
+
+    pub fn put_reorder_point(
+        &self,
+        mut point: RecorderPoint,
+        expected: Option<&[u64]>
+    ) -> Result<RecorderPoint, InventoryError> {
+        point.validate()?;
+        self.with_write_txn(|wtxn| {
+            check_version(
+                wtxn,
+                &self.recorder_point_db,
+                "reorder point",
+                &point.recorder_point_id,
+                expected
+            )?;
+            self.put_reorder_point_in(wtxn, &mut point)?;
+            Ok(point)
+        })
+    }
+
+    pub fn patch_reorder_point(
+        &self,
+        id: &str,
+        patch: &Value,
+        expected: Option<&[u64]>
+    ) -> Result<RecorderPoint, InventoryError> {
+        self.with_write_txn(|wtxn| {
+            let mut point = patched(
+                wtxn,
+                &self.recorder_point_db,
+                "reorder point",
+                "recorder_point_id",
+                id,
+                patch,
+                expected
+            )?;
+            self.put_reorder_point_in(wtxn, &mut point)?;
+            Ok(point)
+        })
+    }
+
+    pub fn delete_reorder_point(&self, id: &str, expected: Option<&[u64]>) -> Result<bool, InventoryError> {
+        self.with_write_txn(|wtxn| {
+            check_version(wtxn, &self.recorder_point_db, "reorder point", id, expected)?;
+            Ok(self.recorder_point_db.delete(wtxn, id)?)
+        })
+    }
+
+    fn put_reorder_point_in(&self, wtxn: &mut RwTxn, point: &mut RecorderPoint) -> Result<(), InventoryError> {
+        let component = self.reorder_component(wtxn, point)?;
+        point.component_id = component.component_id;
+        point.component_name = component.component_name;
+        point.need_to_order = component.free_to_promise <= point.reorder_point;
+        point.version = next_version(wtxn, &self.recorder_point_db, &point.recorder_point_id)?;
+        self.recorder_point_db.put(wtxn, &point.recorder_point_id, point)?;
+        Ok(())
+    }
+
+    pub fn recompute_reorder_point(
+        &self,
+        id: &str,
+        policy: ReorderPolicy,
+        today: NaiveDate
+    ) -> Result<RecorderPoint, InventoryError> {
+        policy.validate().map_err(InventoryError::Validation)?;
+        self.with_write_txn(|wtxn| {
+            let Some(mut point) = self.recorder_point_db.get(wtxn, id)? else {
+                return Err(InventoryError::NotFound(format!("reorder point '{}'", id)));
+            };
+            self.recompute_reorder_point_in(wtxn, &mut point, policy, today)?;
+            Ok(point)
+        })
+    }
+
+    /// Recomputes every reorder point in one transaction; a point whose
+    /// component no longer exists fails the whole run.
+    pub fn recompute_reorder_points(
+        &self,
+        policy: ReorderPolicy,
+        today: NaiveDate
+    ) -> Result<Vec<RecorderPoint>, InventoryError> {
+        policy.validate().map_err(InventoryError::Validation)?;
+        self.with_write_txn(|wtxn| {
+            let mut points = self.recorder_point_db
+                .iter(wtxn)?
+                .map(|res| res.map(|(_, v)| v))
+                .collect::<heed::Result<Vec<RecorderPoint>>>()?;
+            for point in &mut points {
+                self.recompute_reorder_point_in(wtxn, point, policy, today)?;
+            }
+            Ok(points)
+        })
+    }
+
+    // ========== INVENTORY QUERIES ==========
+    pub fn get_inventory_levels(
+        &self,
+        location: &str
+    ) -> Result<Option<HashMap<String, u64>>, heed::Error> {
+        let rtxn = self.env.read_txn()?;
+        let Some(location) = self.resolve_location(&rtxn, location)? else {
+            return Ok(None);
+        };
+        let mut levels = HashMap::new();
+
+        for result in self.components_db.iter(&rtxn)? {
+            let (_, component) = result?;
+            let quantity = component.quantity_at(&location);
+            levels.insert(component.component_name, quantity);
+        }
+
+        Ok(Some(levels))
+    }
+}
+
+/// Collects up to `request.limit` records `keep` accepts, starting after the
+/// cursor. The next cursor is only set when another accepted record exists,
+/// so the last page never points at an empty one.
+fn read_page<T>(
+    rtxn: &RoTxn,
+    db: &Database<Str, SerdeBincode<T>>,
+    request: &PageRequest,
+    keep: impl FnMut(&T) -> bool
+) -> Result<Page<T>, heed::Error>
+    where T: DeserializeOwned
+{
+    let after = request.cursor.as_deref().map_or(Bound::Unbounded, Bound::Excluded);
+    let entries: Box<dyn Iterator<Item = heed::Result<(&str, T)>>> = match request.order {
+        SortOrder::Asc => Box::new(db.range(rtxn, &(after, Bound::Unbounded))?),
+        SortOrder::Desc => Box::new(db.rev_range(rtxn, &(Bound::Unbounded, after))?),
+    };
+    collect_page(entries, request.limit, keep)
+}
+
+/// Like [`read_page`], but over the records stored under `keys` when set,
+/// typically the matches of a secondary index. Pages still follow primary
+/// key order so cursors mean the same either way.
+fn read_page_in<T>(
+    rtxn: &RoTxn,
+    db: &Database<Str, SerdeBincode<T>>,
+    keys: Option<Vec<String>>,
+    request: &PageRequest,
+    keep: impl FnMut(&T) -> bool
+) -> Result<Page<T>, heed::Error>
+    where T: DeserializeOwned
+{
+    let Some(mut keys) = keys else {
+        return read_page(rtxn, db, request, keep);
+    };
+    keys.sort_unstable();
+    keys.dedup();
+    if let Some(cursor) = request.cursor.as_deref() {
+        keys.retain(|key| {
+            match request.order {
+                SortOrder::Asc => key.as_str() > cursor,
+                SortOrder::Desc => key.as_str() < cursor,
+            }
+        });
+    }
+    if matches!(request.order, SortOrder::Desc) {
+        keys.reverse();
+    }
+    // Keys whose record has gone are stale index entries; skip them.
+    let entries = keys.into_iter().filter_map(|key| {
+        db.get(rtxn, &key)
+            .transpose()
+            .map(|res| res.map(|value| (key, value)))
+    });
+    collect_page(entries, request.limit, keep)
+}
+
+fn collect_page<K, T>(
+    entries: impl Iterator<Item = heed::Result<(K, T)>>,
+    limit: usize,
+    mut keep: impl FnMut(&T) -> bool
+) -> Result<Page<T>, heed::Error>
+    where K: ToString
+{
+    let mut items = Vec::new();
+    let mut last_key = None;
+    let mut next_cursor = None;
+    for entry in entries {
+        let (key, value) = entry?;
+        if !keep(&value) {
+            continue;
+        }
+        if items.len() == limit {
+            next_cursor = last_key;
+            break;
+        }
+        last_key = Some(key.to_string());
+        items.push(value);
+    }
+    Ok(Page { items, next_cursor })
+}
+
+fn with_totals<I: StockItem>(mut item: I) -> I {
+    item.refresh_totals();
+    item
+}
+
+/// Rejects a status change the entity's lifecycle does not allow.
+fn check_transition<S: Status>(entity: String, from: S, to: S) -> Result<(), InventoryError> {
+    if from.can_become(to) {
+        Ok(())
+    } else {
+        Err(InventoryError::InvalidTransition {
+            entity,
+            from: format!("{:?}", from),
+            to: format!("{:?}", to),
+        })
+    }
+}
+
+/// Fails unless `db` holds `id`; `field` names the field referring to it.
+fn require<T>(rtxn: &RoTxn, db: &Database<Str, SerdeBincode<T>>, field: &str, id: &str) -> Result<(), InventoryError> {
+    if db.remap_data_type::<DecodeIgnore>().get(rtxn, id)?.is_none() {
+        return Err(InventoryError::UnknownReference {
+            field: field.to_string(),
+            id: id.to_string(),
+        });
+    }
+    Ok(())
+}
+
+/// Fails when another record than `id` already goes by `name` in the name
+/// index `names`.
+fn claim_name(
+    rtxn: &RoTxn,
+    names: &Database<Str, Str>,
+    entity: &str,
+    name: &str,
+    id: &str
+) -> Result<(), InventoryError> {
+    match names.get(rtxn, name)? {
+        Some(owner) if owner != id =>
+            Err(InventoryError::Conflict(format!("{} name '{}' is already used by '{}'", entity, name, owner))),
+        _ => Ok(()),
+    }
+}
+
+/// Drops `name` from the name index `names` if it still points at `id`.
+fn unlink_name(wtxn: &mut RwTxn, names: &Database<Str, Str>, name: &str, id: &str) -> Result<(), heed::Error> {
+    if names.get(wtxn, name)? == Some(id) {
+        names.delete(wtxn, name)?;
+    }
+    Ok(())
+}
+
+/// Keys of the records in `db` that `matches` accepts.
+fn matching_keys<T>(
+    rtxn: &RoTxn,
+    db: &Database<Str, SerdeBincode<T>>,
+    mut matches: impl FnMut(&T) -> bool
+) -> Result<Vec<String>, heed::Error>
+    where T: DeserializeOwned
+{
+    let mut keys = Vec::new();
+    for item in db.iter(rtxn)? {
+        let (key, value) = item?;
+        if matches(&value) {
+            keys.push(key.to_string());
+        }
+    }
+    Ok(keys)
+}
+
+fn described(kind: &str, keys: Vec<String>) -> Vec<String> {
+    keys.into_iter().map(|key| format!("{} '{}'", kind, key)).collect()
+}
+
+/// Gives `record` the version after the stored copy's.
+fn bump_version<T: Versioned>(record: &mut T, stored: Option<&T>) {
+    record.set_version(stored.map_or(0, Versioned::version) + 1);
+}
+
+/// Reads every record of `db` as the baseline layout `B` wrote it.
+fn read_baseline<T, B>(rtxn: &RoTxn, db: &Database<Str, SerdeBincode<T>>) -> Result<Vec<(String, B)>, heed::Error>
+    where B: DeserializeOwned + 'static
+{
+    db.remap_data_type::<SerdeBincode<B>>()
+        .iter(rtxn)?
+        .map(|res| res.map(|(key, record)| (key.to_string(), record)))
+        .collect()
+}
+
+fn unconvertible(entity: &str, key: &str, reason: String) -> InventoryError {
+    InventoryError::Validation(format!("cannot migrate {} '{}': {}", entity, key, reason))
+}
+
+fn unsupported_schema(version: u64) -> InventoryError {
+    InventoryError::Conflict(
+        format!("database is at schema version {}, this build reads version {}", version, SCHEMA_VERSION)
+    )
+}
+
+/// Version the next write of the record under `key` gets.
+fn next_version<T>(rtxn: &RoTxn, db: &Database<Str, SerdeBincode<T>>, key: &str) -> Result<u64, heed::Error>
+    where T: DeserializeOwned + Versioned
+{
+    Ok(db.get(rtxn, key)?.map_or(0, |stored| stored.version()) + 1)
+}
+
+/// Fails unless the record under `key` is still at one of the versions the
+/// client named; `None` skips the check.
+fn check_version<T>(
+    rtxn: &RoTxn,
+    db: &Database<Str, SerdeBincode<T>>,
+    entity: &str,
+    key: &str,
+    expected: Option<&[u64]>
+) -> Result<(), InventoryError>
+    where T: DeserializeOwned + Versioned
+{
+    let Some(expected) = expected else {
+        return Ok(());
+    };
+    let current = db.get(rtxn, key)?.map_or(0, |stored| stored.version());
+    if !expected.contains(&current) {
+        return Err(InventoryError::PreconditionFailed {
+            entity: format!("{} '{}'", entity, key),
+            expected: expected.to_vec(),
+            current,
+        });
+    }
+    Ok(())
+}
+
+/// Applies a JSON Merge Patch (RFC 7386) to the stored record `id` and
+/// checks the result. The key field always keeps the stored ID, and the
+/// stored version has to be one of the `expected` ones.
+fn patched<T>(
+    rtxn: &RoTxn,
+    db: &Database<Str, SerdeBincode<T>>,
+    entity: &str,
+    key_field: &str,
+    id: &str,
+    patch: &Value,
+    expected: Option<&[u64]>
+) -> Result<T, InventoryError>
+    where T: Serialize + DeserializeOwned + Validate + Versioned
+{
+    patched_by(rtxn, db, id, &format!("{} '{}'", entity, id), &[(key_field, id)], patch, expected)
+}
+
+/// [`patched`] for records stored under a composite key: `described` names
+/// the record in errors and `key_fields` all keep their stored values.
+fn patched_by<T>(
+    rtxn: &RoTxn,
+    db: &Database<Str, SerdeBincode<T>>,
+    key: &str,
+    described: &str,
+    key_fields: &[(&str, &str)],
+    patch: &Value,
+    expected: Option<&[u64]>
+) -> Result<T, InventoryError>
+    where T: Serialize + DeserializeOwned + Validate + Versioned
+{
+    let Some(stored) = db.get(rtxn, key)? else {
+        return Err(InventoryError::NotFound(described.to_string()));
+    };
+    if let Some(expected) = expected && !expected.contains(&stored.version()) {
+        return Err(InventoryError::PreconditionFailed {
+            entity: described.to_string(),
+            expected: expected.to_vec(),
+            current: stored.version(),
+        });
+    }
+    let mut record = serde_json::to_value(&stored).map_err(|e| InventoryError::Malformed(e.to_string()))?;
+    merge_patch(&mut record, patch);
+    if let Value::Object(fields) = &mut record {
+        for (field, value) in key_fields {
+            fields.insert(field.to_string(), Value::String(value.to_string()));
+        }
+    }
+    let record: T = serde_json::from_value(record).map_err(|e| InventoryError::Malformed(e.to_string()))?;
+    record.validate()?;
+    Ok(record)
+}
+
+/// Merges `patch` into `target`: objects merge key by key, `null` removes a
+/// key and anything else replaces the target value.
+fn merge_patch(target: &mut Value, patch: &Value) {
+    let Value::Object(patch) = patch else {
+        *target = patch.clone();
+        return;
+    };
+    if !target.is_object() {
+        *target = Value::Object(Map::new());
+    }
+    if let Value::Object(target) = target {
+        for (key, value) in patch {
+            if value.is_null() {
+                target.remove(key);
+            } else {
+                merge_patch(target.entry(key.as_str()).or_insert(Value::Null), value);
+            }
+        }
+    }
+}
+
+/// Refuses to delete `entity` while `referrers` still point at it.
+fn restrict(entity: String, referrers: Vec<String>) -> Result<(), InventoryError> {
+    if referrers.is_empty() {
+        return Ok(());
+    }
+    Err(InventoryError::Referenced { entity, referrers })
+}
+
+/// Completed movement booked by assembly execution; the caller fills in the
+/// item it moves, and the ID is claimed when it is stored.
+fn assembly_movement(
+    timeline: &AssemblyTimeLine,
+    date: NaiveDate,
+    source: &str,
+    destination: &str,
+    quantity: u64
+) -> Movements {
+    Movements {
+        movement_id: String::new(),
+        transaction_id: timeline.assembly_id.clone(),
+        date,
+        movement_type: MovementType::Component,
+        component_id: None,
+        component_name: None,
+        product_id: None,
+        product_name: None,
+        source_location: source.to_string(),
+        destination_location: destination.to_string(),
+        quantity,
+        notes: None,
+        status: MovementStatus::Completed,
+        supplier_order_id: None,
+        received_quantity: 0,
+        returned_quantity: 0,
+        receipts: Vec::new(),
+        version: 0,
+    }
+}
+
+/// Catalogue key of a supplier's component. Keys share the supplier prefix
+/// so a supplier's catalogue is one prefix scan.
+fn catalogue_key(supplier_id: &str, component_id: &str) -> String {
+    format!("{}\0{}", supplier_id, component_id)
+}
+
+/// Takes `quantity` of an item out of a location, refusing to overdraw it.
+fn take_stock<I: StockItem + ?Sized>(
+    item: &mut I,
+    location: &Locations,
+    quantity: u64
+) -> Result<(), InventoryError> {
+    let on_hand = item.quantity_at_mut(location);
+    let Some(remaining) = on_hand.checked_sub(quantity) else {
+        return Err(InventoryError::InsufficientStock {
+            item: item.item_id().to_string(),
+            location: location.location_id.clone(),
+            available: item.quantity_at(location),
+            requested: quantity,
+        });
+    };
+    *on_hand = remaining;
+    Ok(())
+}
+
+/// Inverse of the standard normal CDF (Acklam's rational approximation,
+/// relative error below 1.2e-9), for `0 < p < 1`.
+fn normal_quantile(p: f64) -> f64 {
+    const A: [f64; 6] = [
+        -3.969683028665376e1, 2.209460984245205e2, -2.759285104469687e2,
+        1.38357751867269e2, -3.066479806614716e1, 2.506628277459239,
+    ];
+    const B: [f64; 5] = [
+        -5.447609879822406e1, 1.615858368580409e2, -1.556989798598866e2,
+        6.680131188771972e1, -1.328068155288572e1,
+    ];
+    const C: [f64; 6] = [
+        -7.784894002430293e-3, -3.223964580411365e-1, -2.400758277161838,
+        -2.549732539343734, 4.374664141464968, 2.938163982698783,
+    ];
+    const D: [f64; 4] = [
+        7.784695709041462e-3, 3.224671290700398e-1, 2.445134137142996, 3.754408661907416,
+    ];
+    const P_LOW: f64 = 0.02425;
+
+    let tail = |q: f64| {
+        (((((C[0] * q + C[1]) * q + C[2]) * q + C[3]) * q + C[4]) * q + C[5]) /
+            ((((D[0] * q + D[1]) * q + D[2]) * q + D[3]) * q + 1.0)
+    };
+    if p < P_LOW {
+        tail((-2.0 * p.ln()).sqrt())
+    } else if p > 1.0 - P_LOW {
+        -tail((-2.0 * (1.0 - p).ln()).sqrt())
+    } else {
+        let q = p - 0.5;
+        let r = q * q;
+        ((((((A[0] * r + A[1]) * r + A[2]) * r + A[3]) * r + A[4]) * r + A[5]) * q) /
+            (((((B[0] * r + B[1]) * r + B[2]) * r + B[3]) * r + B[4]) * r + 1.0)
+    }
+}
+
+fn take_in_transit<I: StockItem + ?Sized>(item: &mut I, quantity: u64) -> Result<(), InventoryError> {
+    let in_transit = item.in_transit_mut();
+    let Some(remaining) = in_transit.checked_sub(quantity) else {
+        return Err(InventoryError::InsufficientStock {
+            item: item.item_id().to_string(),
+            location: "in transit".to_string(),
+            available: *item.in_transit_mut(),
+            requested: quantity,
+        });
+    };
+    *in_transit = remaining;
+    Ok(())
+}
+
+enum Command {
+    Serve,
+    Seed {
+        force: bool,
+    },
+    RebuildIndexes,
+    Migrate,
+}
+
+struct Cli {
+    command: Command,
+    config_path: Option<PathBuf>,
+}
+
+fn parse_cli(mut args: impl Iterator<Item = String>) -> Result<Cli, String> {
+    let mut command = None;
+    let mut config_path = None;
+    let mut force = false;
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--config" => {
+                let path = args.next().ok_or("--config needs a file path")?;
+                config_path = Some(PathBuf::from(path));
+            }
+            "--force" => {
+                force = true;
+            }
+            "serve" | "seed" | "rebuild-indexes" | "migrate" if command.is_none() => {
+                command = Some(arg);
+            }
+            other => {
+                return Err(format!("unexpected argument: {}", other));
+            }
+        }
+    }
+
+    let command = match command.as_deref() {
+        None | Some("serve") if !force => Command::Serve,
+        Some("seed") => Command::Seed { force },
+        Some("rebuild-indexes") if !force => Command::RebuildIndexes,
+        Some("migrate") if !force => Command::Migrate,
+        _ => {
+            return Err("--force is only valid with seed".to_string());
+        }
+    };
+    Ok(Cli { command, config_path })
+}
 
+fn cors(allowed_origins: &[String]) -> actix_cors::Cors {
+    let mut cors = actix_cors::Cors::default().allow_any_method().allow_any_header().max_age(3600);
+    if allowed_origins.iter().any(|origin| origin == "*") {
+        cors = cors.allow_any_origin();
+    } else {
+        for origin in allowed_origins {
+            cors = cors.allowed_origin(origin);
+        }
+    }
+    cors
+}
 
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
     println!("cargo:rustc-link-lib=advapi32");
-    let db_path = Path::new("./inventory_db");
-    let inventory_db = InventoryDB::new(db_path).expect("Failed to create database");
-    
-    // Initialize with sample data
-    inventory_db.initialize_sample_data().expect("Failed to initialize sample data");
-    println!("Database initialized with sample data");
-
-    // Print inventory summary (for debugging)
-    inventory_db.print_inventory_summary().expect("Failed to print inventory summary");
+    // Errors are logged even when RUST_LOG is unset.
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info")).init();
+    let cli = match parse_cli(std::env::args().skip(1)) {
+        Ok(cli) => cli,
+        Err(e) => {
+            eprintln!("{}", e);
+            eprintln!("usage: project [--config <file>] [serve | seed [--force] | rebuild-indexes | migrate]");
+            std::process::exit(2);
+        }
+    };
+    let config = match Config::load(cli.config_path.as_deref()) {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(2);
+        }
+    };
 
-    // Example: Get a product (for debugging)
-    if let Some(product) = inventory_db.get_product("PROD-001").expect("Failed to get product") {
-        println!("\nRetrieved product: {}", product.product_name);
+    if let Command::Migrate = cli.command {
+        match InventoryDB::migrate(&config.db_path, config.map_size) {
+            Ok(converted) => println!("Database is up to date ({} records converted)", converted),
+            Err(e) => {
+                eprintln!("Failed to migrate database at {}: {}", config.db_path.display(), e);
+                std::process::exit(1);
+            }
+        }
+        return Ok(());
     }
 
-    // Example: Get inventory levels (for debugging)
-    let cn_levels = inventory_db.get_inventory_levels("CN").expect("Failed to get inventory levels");
-    println!("\nCN Inventory Levels:");
-    for (component, quantity) in cn_levels {
-        println!("{}: {}", component, quantity);
+    let inventory_db = match InventoryDB::new(&config.db_path, config.map_size) {
+        Ok(db) => db,
+        Err(e) => {
+            eprintln!("Failed to open database at {}: {}", config.db_path.display(), e);
+            std::process::exit(1);
+        }
+    };
+
+    match cli.command {
+        Command::Seed { force } => {
+            if let Err(e) = inventory_db.initialize_sample_data(force) {
+                eprintln!("Failed to initialize sample data: {}", e);
+                std::process::exit(1);
+            }
+            println!("Database initialized with sample data");
+            inventory_db.print_inventory_summary().expect("Failed to print inventory summary");
+            return Ok(());
+        }
+        Command::RebuildIndexes => {
+            match inventory_db.rebuild_indexes() {
+                Ok(entries) => println!("Rebuilt secondary indexes ({} entries)", entries),
+                Err(e) => {
+                    eprintln!("Failed to rebuild indexes: {}", e);
+                    std::process::exit(1);
+                }
+            }
+            return Ok(());
+        }
+        Command::Serve if config.seed_on_startup => {
+            match inventory_db.initialize_sample_data(false) {
+                Ok(()) => println!("Database initialized with sample data"),
+                Err(InventoryError::Conflict(_)) => println!("Database not empty, skipping seed"),
+                Err(e) => {
+                    eprintln!("Failed to initialize sample data: {}", e);
+                    std::process::exit(1);
+                }
+            }
+        }
+        Command::Serve | Command::Migrate => {}
     }
 
     // Create app state with Arc-wrapped database
     let app_state = routes::AppState {
         db: Arc::new(inventory_db),
+        reorder_policy: config.reorder_policy(),
     };
 
     // Start HTTP server
-    println!("Starting server at http://localhost:8080");
-    HttpServer::new(move || {
+    println!("Starting server at http://{}", config.bind_address);
+    let allowed_origins = config.cors_allowed_origins.clone();
+    let mut server = HttpServer::new(move || {
         App::new()
-            .wrap(actix_cors::Cors::default()
-                .allow_any_origin()
-                .allow_any_method()
-                .allow_any_header()
-                .max_age(3600))
+            .wrap(cors(&allowed_origins))
             .wrap(actix_web::middleware::Logger::default())
             .app_data(web::Data::new(app_state.clone()))
             .configure(routes::init_routes)
-    })
-    .bind("127.0.0.1:8080")?
-    .run()
-    .await
-}
\ No newline at end of file
+    });
+    if let Some(workers) = config.workers {
+        server = server.workers(workers);
+    }
+    server.bind(&config.bind_address)?.run().await
+}
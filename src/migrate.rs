@@ -0,0 +1,436 @@
+use chrono::NaiveDate;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use crate::r#struct::{
+    AssemblyTimeLine,
+    Components,
+    Movements,
+    MovementStatus,
+    MovementType,
+    Orders,
+    Procurement,
+    Procurements,
+    ProductionRate,
+    Products,
+    RecorderPoint,
+    SuppliersOrders,
+    Watches,
+};
+
+/// Layout of the stored records this build reads and writes. Bump it with
+/// every change to a stored struct and add the step converting older records
+/// to `InventoryDB::migrate`.
+pub const SCHEMA_VERSION: u64 = 1;
+
+/// Locations the fixed stock fields of the baseline layout stand for, in
+/// field order; `InventoryDB::migrate` registers them.
+pub const BASELINE_SITES: [&str; 5] = ["CN", "KLING", "ST-JAKOB", "WURENLOS", "FLF"];
+
+fn stock(quantities: [u64; 5]) -> BTreeMap<String, u64> {
+    BASELINE_SITES
+        .iter()
+        .zip(quantities)
+        .map(|(location_id, quantity)| (location_id.to_string(), quantity))
+        .collect()
+}
+
+/// Records as the first release stored them: stock held in one field per
+/// site and every status a free-form string. Bincode is not self-describing,
+/// so these can only be read through the exact struct that wrote them.
+pub mod baseline {
+    use super::*;
+
+    #[derive(Debug, Deserialize)]
+    pub struct Products {
+        pub product_name: String,
+        pub product_id: String,
+        pub components: Option<Vec<String>>,
+        pub cn: u64,
+        pub kling: u64,
+        pub st_jacob: u64,
+        pub wurenlos: u64,
+        pub wurenlos_sold: u64,
+        pub flf: u64,
+        pub in_transit: u64,
+        pub total_available: u64,
+        pub reserver_for_orders: u64,
+        pub waste: u64,
+        pub customer: u64,
+    }
+
+    #[derive(Debug, Deserialize)]
+    pub struct Components {
+        pub product_id: String,
+        pub product_name: String,
+        pub component_name: String,
+        pub component_id: String,
+        pub cn: u64,
+        pub kling: u64,
+        pub st_jacob: u64,
+        pub wurenlos: u64,
+        pub wurenlos_sold: u64,
+        pub flf: u64,
+        pub in_transit: u64,
+        pub total_available: u64,
+        pub ordered_surplus: f64,
+        pub reserver_for_orders: u64,
+        pub waste: u64,
+        pub customer: u64,
+        pub assembly_line: u64,
+    }
+
+    #[derive(Debug, Deserialize)]
+    pub struct Movements {
+        pub movement_id: String,
+        pub transaction_id: String,
+        pub date: NaiveDate,
+        pub movement_type: String,
+        pub component_name: Option<String>,
+        pub product_name: Option<String>,
+        pub source_location: String,
+        pub destination_location: String,
+        pub quantity: u64,
+        pub notes: Option<String>,
+        pub status: String,
+        pub supplier_order_id: Option<String>,
+    }
+
+    #[derive(Debug, Deserialize)]
+    pub struct SuppliersOrders {
+        pub supplier_id: String,
+        pub component_name: String,
+        pub procurement_id: String,
+        pub order_id: String,
+        pub total_components_required: u64,
+        pub components_roundof: u64,
+        pub status: String,
+        pub order_date: NaiveDate,
+        pub expected_delivery_date: NaiveDate,
+    }
+
+    #[derive(Debug, Deserialize)]
+    pub struct Orders {
+        pub order_id: String,
+        pub procurements: Option<Vec<String>>,
+        pub supplier_orders: Option<Vec<String>>,
+        pub quanity_ordered: u64,
+        pub product_id: String,
+        pub product: String,
+        pub quantity_required: u64,
+        pub expected_delivery_date: NaiveDate,
+        pub production_start_date: NaiveDate,
+        pub expected_ship_date: NaiveDate,
+        pub recid: String,
+        pub order_status: String,
+        pub total_components_booked: u64,
+        pub components_notes: Option<String>,
+        pub components_required: u64,
+        pub total_gap_components: Option<Vec<u64>>,
+        pub components: Option<Vec<String>>,
+    }
+
+    #[derive(Debug, Deserialize)]
+    pub struct Procurement {
+        pub procurement_id: String,
+        pub order_id: String,
+        pub components: Option<Vec<String>>,
+        pub quantity: u64,
+        pub status: String,
+        pub product: String,
+    }
+
+    #[derive(Debug, Deserialize)]
+    pub struct Procurements {
+        pub procurement_id: String,
+        pub order_id: String,
+        pub procurements: Vec<Procurement>,
+    }
+
+    #[derive(Debug, Deserialize)]
+    pub struct AssemblyTimeLine {
+        pub assembly_id: String,
+        pub order: String,
+        pub product: String,
+        pub movements: Vec<String>,
+        pub components_required: u64,
+        pub total_components_booked: u64,
+        pub components: Vec<String>,
+        pub total_gap_components: Option<Vec<u64>>,
+        pub assembly_location: String,
+        pub components_received_date: NaiveDate,
+        pub assembly_start_date: NaiveDate,
+        pub assembly_end_date: NaiveDate,
+        pub assembly_status: String,
+        pub total_duration: u64,
+        pub assembly_notes: Option<String>,
+    }
+
+    #[derive(Debug, Deserialize)]
+    pub struct ProductionRate {
+        pub prodction_rate_id: String,
+        pub watch_model_id: String,
+        pub assembly_time_per_watch: u64,
+        pub daily_production_capacity: u64,
+    }
+
+    #[derive(Debug, Deserialize)]
+    pub struct RecorderPoint {
+        pub recorder_point_id: String,
+        pub component_name: String,
+        pub supplier_lead_time: u64,
+        pub assumed_daily_usage: f64,
+        pub lead_time_demand: f64,
+        pub safety_stock: f64,
+        pub reorder_point: u64,
+        pub need_to_order: bool,
+    }
+
+    #[derive(Debug, Deserialize)]
+    pub struct Watches {
+        pub watch_id: String,
+        pub watch_model_id: String,
+        pub brand: String,
+        pub component_id: String,
+        pub required_quantity: u64,
+    }
+}
+
+// Each conversion takes the references the baseline layout only held by
+// name, resolved by the caller. Derived totals and versions are left for the
+// write helpers to fill in.
+
+impl baseline::Products {
+    pub fn upgrade(self) -> Products {
+        Products {
+            product_name: self.product_name,
+            product_id: self.product_id,
+            watch_model_id: None,
+            components: self.components,
+            stock: stock([self.cn, self.kling, self.st_jacob, self.wurenlos, self.flf]),
+            wurenlos_sold: self.wurenlos_sold,
+            in_transit: self.in_transit,
+            on_hand: 0,
+            total_available: self.total_available,
+            free_to_promise: 0,
+            reserver_for_orders: self.reserver_for_orders,
+            waste: self.waste,
+            customer: self.customer,
+            version: 0,
+        }
+    }
+}
+
+impl baseline::Components {
+    pub fn upgrade(self) -> Components {
+        Components {
+            product_id: self.product_id,
+            product_name: self.product_name,
+            component_name: self.component_name,
+            component_id: self.component_id,
+            stock: stock([self.cn, self.kling, self.st_jacob, self.wurenlos, self.flf]),
+            wurenlos_sold: self.wurenlos_sold,
+            in_transit: self.in_transit,
+            on_hand: 0,
+            total_available: self.total_available,
+            free_to_promise: 0,
+            ordered_surplus: self.ordered_surplus,
+            reserver_for_orders: self.reserver_for_orders,
+            waste: self.waste,
+            customer: self.customer,
+            assembly_line: self.assembly_line,
+            version: 0,
+        }
+    }
+}
+
+impl baseline::Movements {
+    pub fn upgrade(
+        self,
+        component_id: Option<String>,
+        product_id: Option<String>
+    ) -> Result<Movements, String> {
+        let movement_type = match self.movement_type.as_str() {
+            "Component" => MovementType::Component,
+            "Product" => MovementType::Product,
+            other => {
+                return Err(format!("unknown movement type '{}'", other));
+            }
+        };
+        let status = match self.status.as_str() {
+            "Completed" => MovementStatus::Completed,
+            "Dispatched" => MovementStatus::Dispatched,
+            "PartiallyReceived" => MovementStatus::PartiallyReceived,
+            "Received" => MovementStatus::Received,
+            "Cancelled" => MovementStatus::Cancelled,
+            other => {
+                return Err(format!("unknown movement status '{}'", other));
+            }
+        };
+        Ok(Movements {
+            movement_id: self.movement_id,
+            transaction_id: self.transaction_id,
+            date: self.date,
+            movement_type,
+            component_id,
+            component_name: self.component_name,
+            product_id,
+            product_name: self.product_name,
+            source_location: self.source_location,
+            destination_location: self.destination_location,
+            quantity: self.quantity,
+            notes: self.notes,
+            status,
+            supplier_order_id: self.supplier_order_id,
+            received_quantity: 0,
+            returned_quantity: 0,
+            receipts: Vec::new(),
+            version: 0,
+        })
+    }
+}
+
+impl baseline::SuppliersOrders {
+    pub fn upgrade(self, component_id: String) -> Result<SuppliersOrders, String> {
+        Ok(SuppliersOrders {
+            supplier_id: self.supplier_id,
+            component_id,
+            component_name: self.component_name,
+            procurement_id: self.procurement_id,
+            order_id: self.order_id,
+            total_components_required: self.total_components_required,
+            components_roundof: self.components_roundof,
+            status: self.status.try_into()?,
+            order_date: self.order_date,
+            expected_delivery_date: self.expected_delivery_date,
+            supplier_sku: String::new(),
+            unit_price: 0.0,
+            total_price: 0.0,
+            currency: String::new(),
+            received_quantity: 0,
+            movements: Vec::new(),
+            version: 0,
+        })
+    }
+}
+
+impl baseline::Orders {
+    pub fn upgrade(self) -> Result<Orders, String> {
+        Ok(Orders {
+            order_id: self.order_id,
+            procurements: self.procurements,
+            supplier_orders: self.supplier_orders,
+            quanity_ordered: self.quanity_ordered,
+            product_id: self.product_id,
+            product: self.product,
+            quantity_required: self.quantity_required,
+            expected_delivery_date: self.expected_delivery_date,
+            production_start_date: self.production_start_date,
+            expected_ship_date: self.expected_ship_date,
+            recid: self.recid,
+            order_status: self.order_status.try_into()?,
+            total_components_booked: self.total_components_booked,
+            components_notes: self.components_notes,
+            components_required: self.components_required,
+            total_gap_components: self.total_gap_components,
+            components: self.components,
+            components_booked: None,
+            version: 0,
+        })
+    }
+}
+
+impl baseline::Procurements {
+    pub fn upgrade(self) -> Result<Procurements, String> {
+        let procurements = self.procurements
+            .into_iter()
+            .map(|line| {
+                Ok(Procurement {
+                    procurement_id: line.procurement_id,
+                    order_id: line.order_id,
+                    components: line.components,
+                    quantity: line.quantity,
+                    status: line.status.try_into()?,
+                    product: line.product,
+                })
+            })
+            .collect::<Result<Vec<Procurement>, String>>()?;
+        Ok(Procurements {
+            procurement_id: self.procurement_id,
+            order_id: self.order_id,
+            procurements,
+            version: 0,
+        })
+    }
+}
+
+impl baseline::AssemblyTimeLine {
+    /// `quantity` is the watch count of the timeline's order; the baseline
+    /// layout did not record it.
+    pub fn upgrade(self, quantity: u64) -> Result<AssemblyTimeLine, String> {
+        Ok(AssemblyTimeLine {
+            assembly_id: self.assembly_id,
+            order: self.order,
+            product: self.product,
+            movements: self.movements,
+            components_required: self.components_required,
+            total_components_booked: self.total_components_booked,
+            components: self.components,
+            total_gap_components: self.total_gap_components,
+            assembly_location: self.assembly_location,
+            components_received_date: self.components_received_date,
+            assembly_start_date: self.assembly_start_date,
+            assembly_end_date: self.assembly_end_date,
+            assembly_status: self.assembly_status.try_into()?,
+            total_duration: self.total_duration,
+            assembly_notes: self.assembly_notes,
+            quantity,
+            produced_quantity: 0,
+            scrapped: BTreeMap::new(),
+            version: 0,
+        })
+    }
+}
+
+impl baseline::ProductionRate {
+    pub fn upgrade(self) -> ProductionRate {
+        ProductionRate {
+            prodction_rate_id: self.prodction_rate_id,
+            watch_model_id: self.watch_model_id,
+            assembly_time_per_watch: self.assembly_time_per_watch,
+            daily_production_capacity: self.daily_production_capacity,
+            version: 0,
+        }
+    }
+}
+
+impl baseline::RecorderPoint {
+    pub fn upgrade(self, component_id: String) -> RecorderPoint {
+        RecorderPoint {
+            recorder_point_id: self.recorder_point_id,
+            component_id,
+            component_name: self.component_name,
+            supplier_lead_time: self.supplier_lead_time,
+            assumed_daily_usage: self.assumed_daily_usage,
+            daily_usage_std_dev: 0.0,
+            lead_time_demand: self.lead_time_demand,
+            safety_stock: self.safety_stock,
+            reorder_point: self.reorder_point,
+            need_to_order: self.need_to_order,
+            last_computed: None,
+            version: 0,
+        }
+    }
+}
+
+impl baseline::Watches {
+    pub fn upgrade(self) -> Watches {
+        Watches {
+            watch_id: self.watch_id,
+            watch_model_id: self.watch_model_id,
+            brand: self.brand,
+            component_id: self.component_id,
+            required_quantity: self.required_quantity,
+            version: 0,
+        }
+    }
+}
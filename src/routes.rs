@@ -1,25 +1,57 @@
 use actix_web::{
     web,
+    http::StatusCode,
+    http::header::{ self, ETag, EntityTag },
+    HttpRequest,
     HttpResponse,
+    HttpResponseBuilder,
     Responder,
+    ResponseError,
     Result,
     get,
     post,
     put,
+    patch,
     delete,
 };
 use serde::{ Serialize, Deserialize };
-use std::collections::HashMap;
+use chrono::{ Local, NaiveDate };
+use serde_json::Value;
+use std::collections::{ BTreeMap, HashMap };
+use std::fmt;
 use std::sync::Arc;
 
-use crate::r#struct::{AssemblyTimeLine, Components, Movements, Orders, Procurements, ProductionRate, Products, RecorderPoint, SuppliersOrders, Watches};
+use crate::r#struct::{AssemblyFilter, AssemblySchedule, AssemblyStatus, AssemblyTimeLine, BomExplosion, CatalogueItem, ComponentFilter, Components, Locations, MovementFilter, Movements, OrderFilter, OrderStatus, Orders, Page, PageRequest, ProcurementPlan, ProcurementStatus, Procurements, ProductFilter, ProductionRate, Products, RecorderPoint, SortOrder, SupplierOrderFilter, SupplierOrderStatus, Suppliers, SuppliersOrders, Watches};
 use crate::InventoryDB;
+use crate::config::ReorderPolicy;
+use crate::error::{ FieldError, InventoryError };
 
 #[derive(Debug, Serialize, Deserialize)]
 struct ApiResponse<T> {
     success: bool,
     data: Option<T>,
     message: Option<String>,
+    /// Set on paginated list responses.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pagination: Option<Pagination>,
+    /// Set on failed responses.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<ErrorBody>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct ErrorBody {
+    /// Machine-readable error kind, see [`InventoryError::code`].
+    code: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    details: Vec<FieldError>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Pagination {
+    limit: usize,
+    /// Pass as `cursor` to fetch the next page; null on the last page.
+    next_cursor: Option<String>,
 }
 
 impl<T> ApiResponse<T> {
@@ -28,14 +60,224 @@ impl<T> ApiResponse<T> {
             success: true,
             data: Some(data),
             message: None,
+            pagination: None,
+            error: None,
+        }
+    }
+
+    fn page(page: Page<T>, limit: usize) -> ApiResponse<Vec<T>> {
+        ApiResponse {
+            success: true,
+            data: Some(page.items),
+            message: None,
+            pagination: Some(Pagination {
+                limit,
+                next_cursor: page.next_cursor,
+            }),
+            error: None,
         }
     }
 
-    fn error(message: &str) -> ApiResponse<String> {
+    fn failure(message: String, e: &InventoryError) -> ApiResponse<T> {
         ApiResponse {
             success: false,
             data: None,
-            message: Some(message.to_string()),
+            message: Some(message),
+            pagination: None,
+            error: Some(ErrorBody {
+                code: e.code().to_string(),
+                details: e.details(),
+            }),
+        }
+    }
+}
+
+impl ResponseError for InventoryError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            InventoryError::Storage(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            InventoryError::Malformed(_) => StatusCode::BAD_REQUEST,
+            InventoryError::NotFound(_) => StatusCode::NOT_FOUND,
+            InventoryError::PreconditionFailed { .. } => StatusCode::PRECONDITION_FAILED,
+            InventoryError::Conflict(_) |
+            InventoryError::Referenced { .. } |
+            InventoryError::InsufficientStock { .. } |
+            InventoryError::InvalidTransition { .. } => StatusCode::CONFLICT,
+            InventoryError::Validation(_) |
+            InventoryError::InvalidFields(_) |
+            InventoryError::UnknownLocation(_) |
+            InventoryError::InactiveLocation(_) |
+            InventoryError::UnknownItem(_) |
+            InventoryError::UnknownReference { .. } |
+            InventoryError::InvalidMovement(_) |
+            InventoryError::BomCycle(_) => StatusCode::UNPROCESSABLE_ENTITY,
+        }
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        // Storage failures can expose paths and LMDB internals; log them and
+        // give the client a generic message.
+        let message = match self {
+            InventoryError::Storage(e) => {
+                log::error!("storage error: {}", e);
+                "internal storage error".to_string()
+            }
+            e => e.to_string(),
+        };
+        HttpResponse::build(self.status_code()).json(ApiResponse::<()>::failure(message, self))
+    }
+}
+
+/// Answers undecodable bodies, query strings and paths in the usual
+/// envelope instead of actix's plain-text 400.
+fn malformed(e: impl fmt::Display) -> actix_web::Error {
+    InventoryError::Malformed(e.to_string()).into()
+}
+
+/// Success response for a single record, with its version as the `ETag`.
+fn tagged<T: Serialize>(version: u64, record: T) -> HttpResponse {
+    versioned(HttpResponse::Ok(), version, record)
+}
+
+/// `201 Created` response for a new record, tagged like `tagged` does.
+fn created<T: Serialize>(version: u64, record: T) -> HttpResponse {
+    versioned(HttpResponse::Created(), version, record)
+}
+
+fn versioned<T: Serialize>(mut response: HttpResponseBuilder, version: u64, record: T) -> HttpResponse {
+    response
+        .insert_header(ETag(EntityTag::new_strong(version.to_string())))
+        .json(ApiResponse::success(record))
+}
+
+/// Versions named by the `If-Match` header(s), compared the strong way RFC
+/// 9110 asks for: weak tags and tags that are no version never match, so they
+/// are dropped and can leave the list empty. `None` when there is no header
+/// or it is `*`, so the write goes ahead unconditionally.
+fn if_match(req: &HttpRequest) -> Result<Option<Vec<u64>>, InventoryError> {
+    let mut versions = Vec::new();
+    let mut present = false;
+    for value in req.headers().get_all(header::IF_MATCH) {
+        present = true;
+        let value = value
+            .to_str()
+            .map_err(|_| InventoryError::Malformed("If-Match is not valid text".to_string()))?;
+        for tag in value.split(',').map(str::trim).filter(|tag| !tag.is_empty()) {
+            if tag == "*" {
+                return Ok(None);
+            }
+            let opaque = tag.strip_prefix("W/").unwrap_or(tag);
+            let Some(opaque) = opaque.strip_prefix('"').and_then(|opaque| opaque.strip_suffix('"')) else {
+                return Err(InventoryError::Malformed(format!("If-Match '{}' is not an entity tag", tag)));
+            };
+            if !tag.starts_with("W/") && let Ok(version) = opaque.parse() {
+                versions.push(version);
+            }
+        }
+    }
+    Ok(present.then_some(versions))
+}
+
+const DEFAULT_PAGE_LIMIT: usize = 50;
+const MAX_PAGE_LIMIT: usize = 500;
+
+/// Pagination parameters shared by the list endpoints.
+#[derive(Debug, Deserialize)]
+struct PageQuery {
+    /// `next_cursor` of the previous page.
+    cursor: Option<String>,
+    limit: Option<usize>,
+    /// Key order, `asc` or `desc`.
+    sort: Option<SortOrder>,
+}
+
+impl PageQuery {
+    fn request(self) -> PageRequest {
+        PageRequest {
+            cursor: self.cursor.filter(|cursor| !cursor.is_empty()),
+            limit: self.limit.unwrap_or(DEFAULT_PAGE_LIMIT).clamp(1, MAX_PAGE_LIMIT),
+            order: self.sort.unwrap_or_default(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct ReceiveMovementRequest {
+    quantity: Option<u64>,
+    date: Option<NaiveDate>,
+    notes: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct CancelMovementRequest {
+    notes: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct BomQuery {
+    quantity: Option<u64>,
+}
+
+/// Body of the status transition endpoints.
+#[derive(Debug, Deserialize)]
+struct StatusRequest<S> {
+    status: S,
+}
+
+#[derive(Debug, Deserialize)]
+struct SupplierReceiptRequest {
+    location: String,
+    /// Everything outstanding when unset.
+    quantity: Option<u64>,
+    date: Option<NaiveDate>,
+    notes: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct ScheduleAssemblyRequest {
+    /// First day that may be scheduled; today when unset.
+    start_date: Option<NaiveDate>,
+    /// Store the proposed timelines instead of only returning them.
+    #[serde(default)]
+    persist: bool,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct StartAssemblyRequest {
+    date: Option<NaiveDate>,
+    notes: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct CompleteAssemblyRequest {
+    /// Watches finished; everything started when unset.
+    produced: Option<u64>,
+    /// Components scrapped during assembly, by component ID.
+    #[serde(default)]
+    scrapped: BTreeMap<String, u64>,
+    date: Option<NaiveDate>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct GenerateProcurementsRequest {
+    /// Picks the cheapest catalogue supplier per component when unset.
+    supplier_id: Option<String>,
+    order_date: Option<NaiveDate>,
+    expected_delivery_date: Option<NaiveDate>,
+}
+
+/// Overrides for the configured reorder policy.
+#[derive(Debug, Deserialize)]
+struct RecomputeQuery {
+    window_days: Option<u32>,
+    service_level: Option<f64>,
+}
+
+impl RecomputeQuery {
+    fn policy(&self, configured: ReorderPolicy) -> ReorderPolicy {
+        ReorderPolicy {
+            window_days: self.window_days.unwrap_or(configured.window_days),
+            service_level: self.service_level.unwrap_or(configured.service_level),
         }
     }
 }
@@ -43,13 +285,19 @@ impl<T> ApiResponse<T> {
 #[derive(Clone)]
 pub(crate) struct AppState {
     pub(crate) db: Arc<InventoryDB>,
+    pub(crate) reorder_policy: ReorderPolicy,
 }
 
 #[get("/api/products")]
-async fn get_all_products(data: web::Data<AppState>) -> Result<impl Responder> {
-    match data.db.get_all_products() {
-        Ok(products) => Ok(HttpResponse::Ok().json(ApiResponse::<Vec<Products>>::success(products))),
-        Err(e) => Ok(HttpResponse::InternalServerError().json(ApiResponse::<String>::error(&e.to_string()))),
+async fn get_all_products(
+    data: web::Data<AppState>,
+    page: web::Query<PageQuery>,
+    filter: web::Query<ProductFilter>
+) -> Result<impl Responder> {
+    let request = page.into_inner().request();
+    match data.db.list_products(&filter, &request) {
+        Ok(page) => Ok(HttpResponse::Ok().json(ApiResponse::page(page, request.limit))),
+        Err(e) => Err(e.into()),
     }
 }
 
@@ -57,9 +305,9 @@ async fn get_all_products(data: web::Data<AppState>) -> Result<impl Responder> {
 async fn get_product(data: web::Data<AppState>, path: web::Path<String>) -> Result<impl Responder> {
     let id = path.into_inner();
     match data.db.get_product(&id) {
-        Ok(Some(product)) => Ok(HttpResponse::Ok().json(ApiResponse::<Products>::success(product))),
-        Ok(None) => Ok(HttpResponse::NotFound().json(ApiResponse::<String>::error("Product not found"))),
-        Err(e) => Ok(HttpResponse::InternalServerError().json(ApiResponse::<String>::error(&e.to_string()))),
+        Ok(Some(product)) => Ok(tagged(product.version, product)),
+        Ok(None) => Err(InventoryError::NotFound(format!("product '{}'", id)).into()),
+        Err(e) => Err(InventoryError::from(e).into()),
     }
 }
 
@@ -68,14 +316,15 @@ async fn create_product(
     data: web::Data<AppState>,
     product: web::Json<Products>
 ) -> Result<impl Responder> {
-    match data.db.create_product(&product.into_inner()) {
-        Ok(_) => Ok(HttpResponse::Created().json(ApiResponse::<&str>::success("Product created"))),
-        Err(e) => Ok(HttpResponse::InternalServerError().json(ApiResponse::<String>::error(&e.to_string()))),
+    match data.db.create_product(product.into_inner()) {
+        Ok(product) => Ok(created(product.version, product)),
+        Err(e) => Err(e.into()),
     }
 }
 
 #[put("/api/products/{id}")]
 async fn update_product(
+    req: HttpRequest,
     data: web::Data<AppState>,
     path: web::Path<String>,
     product: web::Json<Products>
@@ -84,32 +333,51 @@ async fn update_product(
     let mut product = product.into_inner();
     product.product_id = id;
 
-    match data.db.update_product(&product) {
-        Ok(_) => Ok(HttpResponse::Ok().json(ApiResponse::<&str>::success("Product updated"))),
-        Err(e) => Ok(HttpResponse::InternalServerError().json(ApiResponse::<String>::error(&e.to_string()))),
+    match data.db.update_product(product, if_match(&req)?.as_deref()) {
+        Ok(record) => Ok(tagged(record.version, record)),
+        Err(e) => Err(e.into()),
+    }
+}
+
+#[patch("/api/products/{id}")]
+async fn patch_product(
+    req: HttpRequest,
+    data: web::Data<AppState>,
+    path: web::Path<String>,
+    patch: web::Json<Value>
+) -> Result<impl Responder> {
+    match data.db.patch_product(&path.into_inner(), &patch, if_match(&req)?.as_deref()) {
+        Ok(record) => Ok(tagged(record.version, record)),
+        Err(e) => Err(e.into()),
     }
 }
 
 #[delete("/api/products/{id}")]
 async fn delete_product(
+    req: HttpRequest,
     data: web::Data<AppState>,
     path: web::Path<String>
 ) -> Result<impl Responder> {
     let id = path.into_inner();
-    match data.db.delete_product(&id) {
+    match data.db.delete_product(&id, if_match(&req)?.as_deref()) {
         Ok(true) => Ok(HttpResponse::Ok().json(ApiResponse::<&str>::success("Product deleted"))),
-        Ok(false) => Ok(HttpResponse::NotFound().json(ApiResponse::<String>::error("Product not found"))),
-        Err(e) => Ok(HttpResponse::InternalServerError().json(ApiResponse::<String>::error(&e.to_string()))),
+        Ok(false) => Err(InventoryError::NotFound(format!("product '{}'", id)).into()),
+        Err(e) => Err(e.into()),
     }
 }
 
 // ========== COMPONENTS API ==========
 
 #[get("/api/components")]
-async fn get_all_components(data: web::Data<AppState>) -> Result<impl Responder> {
-    match data.db.get_all_components() {
-        Ok(components) => Ok(HttpResponse::Ok().json(ApiResponse::<Vec<Components>>::success(components))),
-        Err(e) => Ok(HttpResponse::InternalServerError().json(ApiResponse::<String>::error(&e.to_string()))),
+async fn get_all_components(
+    data: web::Data<AppState>,
+    page: web::Query<PageQuery>,
+    filter: web::Query<ComponentFilter>
+) -> Result<impl Responder> {
+    let request = page.into_inner().request();
+    match data.db.list_components(&filter, &request) {
+        Ok(page) => Ok(HttpResponse::Ok().json(ApiResponse::page(page, request.limit))),
+        Err(e) => Err(e.into()),
     }
 }
 
@@ -120,9 +388,9 @@ async fn get_component(
 ) -> Result<impl Responder> {
     let id = path.into_inner();
     match data.db.get_component(&id) {
-        Ok(Some(component)) => Ok(HttpResponse::Ok().json(ApiResponse::<Components>::success(component))),
-        Ok(None) => Ok(HttpResponse::NotFound().json(ApiResponse::<String>::error("Component not found"))),
-        Err(e) => Ok(HttpResponse::InternalServerError().json(ApiResponse::<String>::error(&e.to_string()))),
+        Ok(Some(component)) => Ok(tagged(component.version, component)),
+        Ok(None) => Err(InventoryError::NotFound(format!("component '{}'", id)).into()),
+        Err(e) => Err(InventoryError::from(e).into()),
     }
 }
 
@@ -131,14 +399,15 @@ async fn create_component(
     data: web::Data<AppState>,
     component: web::Json<Components>
 ) -> Result<impl Responder> {
-    match data.db.create_component(&component.into_inner()) {
-        Ok(_) => Ok(HttpResponse::Created().json(ApiResponse::<&str>::success("Component created"))),
-        Err(e) => Ok(HttpResponse::InternalServerError().json(ApiResponse::<String>::error(&e.to_string()))),
+    match data.db.create_component(component.into_inner()) {
+        Ok(component) => Ok(created(component.version, component)),
+        Err(e) => Err(e.into()),
     }
 }
 
 #[put("/api/components/{id}")]
 async fn update_component(
+    req: HttpRequest,
     data: web::Data<AppState>,
     path: web::Path<String>,
     component: web::Json<Components>
@@ -147,32 +416,133 @@ async fn update_component(
     let mut component = component.into_inner();
     component.component_id = id;
 
-    match data.db.update_component(&component) {
-        Ok(_) => Ok(HttpResponse::Ok().json(ApiResponse::<&str>::success("Component updated"))),
-        Err(e) => Ok(HttpResponse::InternalServerError().json(ApiResponse::<String>::error(&e.to_string()))),
+    match data.db.update_component(component, if_match(&req)?.as_deref()) {
+        Ok(record) => Ok(tagged(record.version, record)),
+        Err(e) => Err(e.into()),
+    }
+}
+
+#[patch("/api/components/{id}")]
+async fn patch_component(
+    req: HttpRequest,
+    data: web::Data<AppState>,
+    path: web::Path<String>,
+    patch: web::Json<Value>
+) -> Result<impl Responder> {
+    match data.db.patch_component(&path.into_inner(), &patch, if_match(&req)?.as_deref()) {
+        Ok(record) => Ok(tagged(record.version, record)),
+        Err(e) => Err(e.into()),
     }
 }
 
 #[delete("/api/components/{id}")]
 async fn delete_component(
+    req: HttpRequest,
     data: web::Data<AppState>,
     path: web::Path<String>
 ) -> Result<impl Responder> {
     let id = path.into_inner();
-    match data.db.delete_component(&id) {
+    match data.db.delete_component(&id, if_match(&req)?.as_deref()) {
         Ok(true) => Ok(HttpResponse::Ok().json(ApiResponse::<&str>::success("Component deleted"))),
-        Ok(false) => Ok(HttpResponse::NotFound().json(ApiResponse::<String>::error("Component not found"))),
-        Err(e) => Ok(HttpResponse::InternalServerError().json(ApiResponse::<String>::error(&e.to_string()))),
+        Ok(false) => Err(InventoryError::NotFound(format!("component '{}'", id)).into()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+// ========== LOCATIONS API ==========
+
+#[get("/api/locations")]
+async fn get_all_locations(
+    data: web::Data<AppState>,
+    page: web::Query<PageQuery>
+) -> Result<impl Responder> {
+    let request = page.into_inner().request();
+    match data.db.list_locations(&request) {
+        Ok(page) => Ok(HttpResponse::Ok().json(ApiResponse::page(page, request.limit))),
+        Err(e) => Err(InventoryError::from(e).into()),
+    }
+}
+
+#[get("/api/locations/{id}")]
+async fn get_location(
+    data: web::Data<AppState>,
+    path: web::Path<String>
+) -> Result<impl Responder> {
+    let id = path.into_inner();
+    match data.db.get_location(&id) {
+        Ok(Some(location)) => Ok(tagged(location.version, location)),
+        Ok(None) => Err(InventoryError::NotFound(format!("location '{}'", id)).into()),
+        Err(e) => Err(InventoryError::from(e).into()),
+    }
+}
+
+#[post("/api/locations")]
+async fn create_location(
+    data: web::Data<AppState>,
+    location: web::Json<Locations>
+) -> Result<impl Responder> {
+    match data.db.create_location(location.into_inner()) {
+        Ok(location) => Ok(created(location.version, location)),
+        Err(e) => Err(e.into()),
+    }
+}
+
+#[put("/api/locations/{id}")]
+async fn update_location(
+    req: HttpRequest,
+    data: web::Data<AppState>,
+    path: web::Path<String>,
+    location: web::Json<Locations>
+) -> Result<impl Responder> {
+    let id = path.into_inner();
+    let mut location = location.into_inner();
+    location.location_id = id;
+
+    match data.db.update_location(location, if_match(&req)?.as_deref()) {
+        Ok(record) => Ok(tagged(record.version, record)),
+        Err(e) => Err(e.into()),
+    }
+}
+
+#[patch("/api/locations/{id}")]
+async fn patch_location(
+    req: HttpRequest,
+    data: web::Data<AppState>,
+    path: web::Path<String>,
+    patch: web::Json<Value>
+) -> Result<impl Responder> {
+    match data.db.patch_location(&path.into_inner(), &patch, if_match(&req)?.as_deref()) {
+        Ok(record) => Ok(tagged(record.version, record)),
+        Err(e) => Err(e.into()),
+    }
+}
+
+#[delete("/api/locations/{id}")]
+async fn delete_location(
+    req: HttpRequest,
+    data: web::Data<AppState>,
+    path: web::Path<String>
+) -> Result<impl Responder> {
+    let id = path.into_inner();
+    match data.db.delete_location(&id, if_match(&req)?.as_deref()) {
+        Ok(true) => Ok(HttpResponse::Ok().json(ApiResponse::<&str>::success("Location deleted"))),
+        Ok(false) => Err(InventoryError::NotFound(format!("location '{}'", id)).into()),
+        Err(e) => Err(e.into()),
     }
 }
 
 // ========== MOVEMENTS API ==========
 
 #[get("/api/movements")]
-async fn get_all_movements(data: web::Data<AppState>) -> Result<impl Responder> {
-    match data.db.get_all_movements() {
-        Ok(movements) => Ok(HttpResponse::Ok().json(ApiResponse::<Vec<Movements>>::success(movements))),
-        Err(e) => Ok(HttpResponse::InternalServerError().json(ApiResponse::<String>::error(&e.to_string()))),
+async fn get_all_movements(
+    data: web::Data<AppState>,
+    page: web::Query<PageQuery>,
+    filter: web::Query<MovementFilter>
+) -> Result<impl Responder> {
+    let request = page.into_inner().request();
+    match data.db.list_movements(&filter, &request) {
+        Ok(page) => Ok(HttpResponse::Ok().json(ApiResponse::page(page, request.limit))),
+        Err(e) => Err(InventoryError::from(e).into()),
     }
 }
 
@@ -183,9 +553,9 @@ async fn get_movement(
 ) -> Result<impl Responder> {
     let id = path.into_inner();
     match data.db.get_movement(&id) {
-        Ok(Some(movement)) => Ok(HttpResponse::Ok().json(ApiResponse::<Movements>::success(movement))),
-        Ok(None) => Ok(HttpResponse::NotFound().json(ApiResponse::<String>::error("Movement not found"))),
-        Err(e) => Ok(HttpResponse::InternalServerError().json(ApiResponse::<String>::error(&e.to_string()))),
+        Ok(Some(movement)) => Ok(tagged(movement.version, movement)),
+        Ok(None) => Err(InventoryError::NotFound(format!("movement '{}'", id)).into()),
+        Err(e) => Err(InventoryError::from(e).into()),
     }
 }
 
@@ -194,19 +564,53 @@ async fn record_movement(
     data: web::Data<AppState>,
     movement: web::Json<Movements>
 ) -> Result<impl Responder> {
-    match data.db.record_movement(&movement.into_inner()) {
-        Ok(_) => Ok(HttpResponse::Created().json(ApiResponse::<&str>::success("Movement recorded"))),
-        Err(e) => Ok(HttpResponse::InternalServerError().json(ApiResponse::<String>::error(&e.to_string()))),
+    match data.db.record_movement(movement.into_inner()) {
+        Ok(movement) => Ok(created(movement.version, movement)),
+        Err(e) => Err(e.into()),
+    }
+}
+
+#[post("/api/movements/{id}/receive")]
+async fn receive_movement(
+    data: web::Data<AppState>,
+    path: web::Path<String>,
+    receipt: web::Json<ReceiveMovementRequest>
+) -> Result<impl Responder> {
+    let id = path.into_inner();
+    let receipt = receipt.into_inner();
+    let date = receipt.date.unwrap_or_else(|| Local::now().date_naive());
+    match data.db.receive_movement(&id, receipt.quantity, date, receipt.notes) {
+        Ok(movement) => Ok(tagged(movement.version, movement)),
+        Err(e) => Err(e.into()),
+    }
+}
+
+#[post("/api/movements/{id}/cancel")]
+async fn cancel_movement(
+    data: web::Data<AppState>,
+    path: web::Path<String>,
+    request: Option<web::Json<CancelMovementRequest>>
+) -> Result<impl Responder> {
+    let id = path.into_inner();
+    let request = request.map(|r| r.into_inner()).unwrap_or_default();
+    match data.db.cancel_movement(&id, request.notes) {
+        Ok(movement) => Ok(tagged(movement.version, movement)),
+        Err(e) => Err(e.into()),
     }
 }
 
 // ========== ORDERS API ==========
 
 #[get("/api/orders")]
-async fn get_all_orders(data: web::Data<AppState>) -> Result<impl Responder> {
-    match data.db.get_all_orders() {
-        Ok(orders) => Ok(HttpResponse::Ok().json(ApiResponse::<Vec<Orders>>::success(orders))),
-        Err(e) => Ok(HttpResponse::InternalServerError().json(ApiResponse::<String>::error(&e.to_string()))),
+async fn get_all_orders(
+    data: web::Data<AppState>,
+    page: web::Query<PageQuery>,
+    filter: web::Query<OrderFilter>
+) -> Result<impl Responder> {
+    let request = page.into_inner().request();
+    match data.db.list_orders(&filter, &request) {
+        Ok(page) => Ok(HttpResponse::Ok().json(ApiResponse::page(page, request.limit))),
+        Err(e) => Err(InventoryError::from(e).into()),
     }
 }
 
@@ -214,9 +618,9 @@ async fn get_all_orders(data: web::Data<AppState>) -> Result<impl Responder> {
 async fn get_order(data: web::Data<AppState>, path: web::Path<String>) -> Result<impl Responder> {
     let id = path.into_inner();
     match data.db.get_order(&id) {
-        Ok(Some(order)) => Ok(HttpResponse::Ok().json(ApiResponse::<Orders>::success(order))),
-        Ok(None) => Ok(HttpResponse::NotFound().json(ApiResponse::<String>::error("Order not found"))),
-        Err(e) => Ok(HttpResponse::InternalServerError().json(ApiResponse::<String>::error(&e.to_string()))),
+        Ok(Some(order)) => Ok(tagged(order.version, order)),
+        Ok(None) => Err(InventoryError::NotFound(format!("order '{}'", id)).into()),
+        Err(e) => Err(InventoryError::from(e).into()),
     }
 }
 
@@ -225,14 +629,15 @@ async fn create_order(
     data: web::Data<AppState>,
     order: web::Json<Orders>
 ) -> Result<impl Responder> {
-    match data.db.create_order(&order.into_inner()) {
-        Ok(_) => Ok(HttpResponse::Created().json(ApiResponse::<&str>::success("Order created"))),
-        Err(e) => Ok(HttpResponse::InternalServerError().json(ApiResponse::<String>::error(&e.to_string()))),
+    match data.db.create_order(order.into_inner()) {
+        Ok(order) => Ok(created(order.version, order)),
+        Err(e) => Err(e.into()),
     }
 }
 
 #[put("/api/orders/{id}")]
 async fn update_order(
+    req: HttpRequest,
     data: web::Data<AppState>,
     path: web::Path<String>,
     order: web::Json<Orders>
@@ -241,22 +646,95 @@ async fn update_order(
     let mut order = order.into_inner();
     order.order_id = id;
 
-    match data.db.update_order(&order) {
-        Ok(_) => Ok(HttpResponse::Ok().json(ApiResponse::<&str>::success("Order updated"))),
-        Err(e) => Ok(HttpResponse::InternalServerError().json(ApiResponse::<String>::error(&e.to_string()))),
+    match data.db.update_order(order, if_match(&req)?.as_deref()) {
+        Ok(record) => Ok(tagged(record.version, record)),
+        Err(e) => Err(e.into()),
+    }
+}
+
+#[patch("/api/orders/{id}")]
+async fn patch_order(
+    req: HttpRequest,
+    data: web::Data<AppState>,
+    path: web::Path<String>,
+    patch: web::Json<Value>
+) -> Result<impl Responder> {
+    match data.db.patch_order(&path.into_inner(), &patch, if_match(&req)?.as_deref()) {
+        Ok(record) => Ok(tagged(record.version, record)),
+        Err(e) => Err(e.into()),
     }
 }
 
 #[delete("/api/orders/{id}")]
 async fn delete_order(
+    req: HttpRequest,
     data: web::Data<AppState>,
     path: web::Path<String>
 ) -> Result<impl Responder> {
     let id = path.into_inner();
-    match data.db.delete_order(&id) {
+    match data.db.delete_order(&id, if_match(&req)?.as_deref()) {
         Ok(true) => Ok(HttpResponse::Ok().json(ApiResponse::<&str>::success("Order deleted"))),
-        Ok(false) => Ok(HttpResponse::NotFound().json(ApiResponse::<String>::error("Order not found"))),
-        Err(e) => Ok(HttpResponse::InternalServerError().json(ApiResponse::<String>::error(&e.to_string()))),
+        Ok(false) => Err(InventoryError::NotFound(format!("order '{}'", id)).into()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+#[post("/api/orders/{id}/reserve")]
+async fn reserve_order(
+    data: web::Data<AppState>,
+    path: web::Path<String>
+) -> Result<impl Responder> {
+    let id = path.into_inner();
+    match data.db.reserve_order(&id) {
+        Ok(order) => Ok(tagged(order.version, order)),
+        Err(e) => Err(e.into()),
+    }
+}
+
+#[post("/api/orders/{id}/release")]
+async fn release_order(
+    data: web::Data<AppState>,
+    path: web::Path<String>
+) -> Result<impl Responder> {
+    let id = path.into_inner();
+    match data.db.release_order(&id) {
+        Ok(order) => Ok(tagged(order.version, order)),
+        Err(e) => Err(e.into()),
+    }
+}
+
+#[post("/api/orders/{id}/status")]
+async fn transition_order(
+    data: web::Data<AppState>,
+    path: web::Path<String>,
+    request: web::Json<StatusRequest<OrderStatus>>
+) -> Result<impl Responder> {
+    let id = path.into_inner();
+    match data.db.transition_order(&id, request.into_inner().status) {
+        Ok(order) => Ok(tagged(order.version, order)),
+        Err(e) => Err(e.into()),
+    }
+}
+
+#[post("/api/orders/{id}/procurements")]
+async fn generate_procurements(
+    data: web::Data<AppState>,
+    path: web::Path<String>,
+    request: Option<web::Json<GenerateProcurementsRequest>>
+) -> Result<impl Responder> {
+    let id = path.into_inner();
+    let request = request.map(|r| r.into_inner()).unwrap_or_default();
+    let order_date = request.order_date.unwrap_or_else(|| Local::now().date_naive());
+    match
+        data.db.generate_procurements(
+            &id,
+            request.supplier_id.as_deref(),
+            order_date,
+            request.expected_delivery_date
+        )
+    {
+        Ok(plan) => Ok(HttpResponse::Ok().json(ApiResponse::<ProcurementPlan>::success(plan))),
+        Err(e) => Err(e.into()),
     }
 }
 
@@ -269,8 +747,9 @@ async fn get_inventory_levels(
 ) -> Result<impl Responder> {
     let location = path.into_inner();
     match data.db.get_inventory_levels(&location) {
-        Ok(levels) => Ok(HttpResponse::Ok().json(ApiResponse::<HashMap<String, u64>>::success(levels))),
-        Err(e) => Ok(HttpResponse::InternalServerError().json(ApiResponse::<String>::error(&e.to_string()))),
+        Ok(Some(levels)) => Ok(HttpResponse::Ok().json(ApiResponse::<HashMap<String, u64>>::success(levels))),
+        Ok(None) => Err(InventoryError::NotFound(format!("location '{}'", location)).into()),
+        Err(e) => Err(InventoryError::from(e).into()),
     }
 }
 
@@ -282,7 +761,7 @@ async fn get_product_components(
     let id = path.into_inner();
     match data.db.get_product_components(&id) {
         Ok(components) => Ok(HttpResponse::Ok().json(ApiResponse::<Vec<Components>>::success(components))),
-        Err(e) => Ok(HttpResponse::InternalServerError().json(ApiResponse::<String>::error(&e.to_string()))),
+        Err(e) => Err(InventoryError::from(e).into()),
     }
 }
 
@@ -294,23 +773,175 @@ async fn add_component_to_product(
     let (product_id, component_id) = path.into_inner();
     match data.db.add_component_to_product(&product_id, &component_id) {
         Ok(_) => Ok(HttpResponse::Ok().json(ApiResponse::<&str>::success("Component added to product"))),
-        Err(e) => Ok(HttpResponse::InternalServerError().json(ApiResponse::<String>::error(&e.to_string()))),
+        Err(e) => Err(e.into()),
+    }
+}
+
+// ========== SUPPLIERS API ==========
+
+#[get("/api/suppliers")]
+async fn get_all_suppliers(
+    data: web::Data<AppState>,
+    page: web::Query<PageQuery>
+) -> Result<impl Responder> {
+    let request = page.into_inner().request();
+    match data.db.list_suppliers(&request) {
+        Ok(page) => Ok(HttpResponse::Ok().json(ApiResponse::page(page, request.limit))),
+        Err(e) => Err(InventoryError::from(e).into()),
+    }
+}
+
+#[get("/api/suppliers/{id}")]
+async fn get_supplier(
+    data: web::Data<AppState>,
+    path: web::Path<String>
+) -> Result<impl Responder> {
+    let id = path.into_inner();
+    match data.db.get_supplier(&id) {
+        Ok(Some(supplier)) => Ok(tagged(supplier.version, supplier)),
+        Ok(None) => Err(InventoryError::NotFound(format!("supplier '{}'", id)).into()),
+        Err(e) => Err(InventoryError::from(e).into()),
+    }
+}
+
+#[post("/api/suppliers")]
+async fn create_supplier(
+    data: web::Data<AppState>,
+    supplier: web::Json<Suppliers>
+) -> Result<impl Responder> {
+    match data.db.create_supplier(supplier.into_inner()) {
+        Ok(supplier) => Ok(created(supplier.version, supplier)),
+        Err(e) => Err(e.into()),
+    }
+}
+
+#[put("/api/suppliers/{id}")]
+async fn update_supplier(
+    req: HttpRequest,
+    data: web::Data<AppState>,
+    path: web::Path<String>,
+    supplier: web::Json<Suppliers>
+) -> Result<impl Responder> {
+    let id = path.into_inner();
+    let mut supplier = supplier.into_inner();
+    supplier.supplier_id = id;
+
+    match data.db.update_supplier(supplier, if_match(&req)?.as_deref()) {
+        Ok(record) => Ok(tagged(record.version, record)),
+        Err(e) => Err(e.into()),
+    }
+}
+
+#[patch("/api/suppliers/{id}")]
+async fn patch_supplier(
+    req: HttpRequest,
+    data: web::Data<AppState>,
+    path: web::Path<String>,
+    patch: web::Json<Value>
+) -> Result<impl Responder> {
+    match data.db.patch_supplier(&path.into_inner(), &patch, if_match(&req)?.as_deref()) {
+        Ok(record) => Ok(tagged(record.version, record)),
+        Err(e) => Err(e.into()),
+    }
+}
+
+#[delete("/api/suppliers/{id}")]
+async fn delete_supplier(
+    req: HttpRequest,
+    data: web::Data<AppState>,
+    path: web::Path<String>
+) -> Result<impl Responder> {
+    let id = path.into_inner();
+    match data.db.delete_supplier(&id, if_match(&req)?.as_deref()) {
+        Ok(true) => Ok(HttpResponse::Ok().json(ApiResponse::<&str>::success("Supplier deleted"))),
+        Ok(false) => Err(InventoryError::NotFound(format!("supplier '{}'", id)).into()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+#[get("/api/suppliers/{id}/catalogue")]
+async fn get_supplier_catalogue(
+    data: web::Data<AppState>,
+    path: web::Path<String>
+) -> Result<impl Responder> {
+    let id = path.into_inner();
+    match data.db.get_supplier_catalogue(&id) {
+        Ok(items) => Ok(HttpResponse::Ok().json(ApiResponse::<Vec<CatalogueItem>>::success(items))),
+        Err(e) => Err(InventoryError::from(e).into()),
+    }
+}
+
+#[get("/api/suppliers/{id}/catalogue/{component_id}")]
+async fn get_catalogue_item(
+    data: web::Data<AppState>,
+    path: web::Path<(String, String)>
+) -> Result<impl Responder> {
+    let (id, component_id) = path.into_inner();
+    match data.db.get_catalogue_item(&id, &component_id) {
+        Ok(Some(item)) => Ok(tagged(item.version, item)),
+        Ok(None) => Err(InventoryError::NotFound(format!("catalogue item '{}' of supplier '{}'", component_id, id)).into()),
+        Err(e) => Err(InventoryError::from(e).into()),
+    }
+}
+
+#[put("/api/suppliers/{id}/catalogue/{component_id}")]
+async fn put_catalogue_item(
+    req: HttpRequest,
+    data: web::Data<AppState>,
+    path: web::Path<(String, String)>,
+    item: web::Json<CatalogueItem>
+) -> Result<impl Responder> {
+    let (id, component_id) = path.into_inner();
+    let mut item = item.into_inner();
+    item.supplier_id = id;
+    item.component_id = component_id;
+
+    match data.db.put_catalogue_item(item, if_match(&req)?.as_deref()) {
+        Ok(record) => Ok(tagged(record.version, record)),
+        Err(e) => Err(e.into()),
+    }
+}
+
+#[patch("/api/suppliers/{id}/catalogue/{component_id}")]
+async fn patch_catalogue_item(
+    req: HttpRequest,
+    data: web::Data<AppState>,
+    path: web::Path<(String, String)>,
+    patch: web::Json<Value>
+) -> Result<impl Responder> {
+    let (id, component_id) = path.into_inner();
+    match data.db.patch_catalogue_item(&id, &component_id, &patch, if_match(&req)?.as_deref()) {
+        Ok(item) => Ok(tagged(item.version, item)),
+        Err(e) => Err(e.into()),
+    }
+}
+
+#[delete("/api/suppliers/{id}/catalogue/{component_id}")]
+async fn delete_catalogue_item(
+    req: HttpRequest,
+    data: web::Data<AppState>,
+    path: web::Path<(String, String)>
+) -> Result<impl Responder> {
+    let (id, component_id) = path.into_inner();
+    match data.db.delete_catalogue_item(&id, &component_id, if_match(&req)?.as_deref()) {
+        Ok(true) => Ok(HttpResponse::Ok().json(ApiResponse::<&str>::success("Catalogue item deleted"))),
+        Ok(false) => Err(InventoryError::NotFound(format!("catalogue item '{}' of supplier '{}'", component_id, id)).into()),
+        Err(e) => Err(e.into()),
     }
 }
-// Add these to your existing routes.rs file
 
 // ========== SUPPLIER ORDERS API ==========
 
 #[get("/api/supplier-orders")]
-async fn get_all_supplier_orders(data: web::Data<AppState>) -> Result<impl Responder> {
-    match data.db.with_read_txn(|rtxn| {
-        data.db.suppliers_orders_db
-            .iter(&rtxn)?
-            .map(|res| res.map(|(_, v)| v))
-            .collect::<heed::Result<Vec<SuppliersOrders>>>()
-    }) {
-        Ok(orders) => Ok(HttpResponse::Ok().json(ApiResponse::<Vec<SuppliersOrders>>::success(orders))),
-        Err(e) => Ok(HttpResponse::InternalServerError().json(ApiResponse::<String>::error(&e.to_string()))),
+async fn get_all_supplier_orders(
+    data: web::Data<AppState>,
+    page: web::Query<PageQuery>,
+    filter: web::Query<SupplierOrderFilter>
+) -> Result<impl Responder> {
+    let request = page.into_inner().request();
+    match data.db.list_supplier_orders(&filter, &request) {
+        Ok(page) => Ok(HttpResponse::Ok().json(ApiResponse::page(page, request.limit))),
+        Err(e) => Err(InventoryError::from(e).into()),
     }
 }
 
@@ -320,10 +951,10 @@ async fn get_supplier_order(
     path: web::Path<String>
 ) -> Result<impl Responder> {
     let id = path.into_inner();
-    match data.db.with_read_txn(|rtxn| data.db.suppliers_orders_db.get(&rtxn, &id)) {
-        Ok(Some(order)) => Ok(HttpResponse::Ok().json(ApiResponse::<SuppliersOrders>::success(order))),
-        Ok(None) => Ok(HttpResponse::NotFound().json(ApiResponse::<String>::error("Supplier order not found"))),
-        Err(e) => Ok(HttpResponse::InternalServerError().json(ApiResponse::<String>::error(&e.to_string()))),
+    match data.db.with_read_txn(|rtxn| data.db.suppliers_orders_db.get(rtxn, &id)) {
+        Ok(Some(order)) => Ok(tagged(order.version, order)),
+        Ok(None) => Err(InventoryError::NotFound(format!("supplier order '{}'", id)).into()),
+        Err(e) => Err(InventoryError::from(e).into()),
     }
 }
 
@@ -332,17 +963,15 @@ async fn create_supplier_order(
     data: web::Data<AppState>,
     order: web::Json<SuppliersOrders>
 ) -> Result<impl Responder> {
-    let order = order.into_inner();
-    match data.db.with_write_txn(|wtxn| {
-        data.db.suppliers_orders_db.put(wtxn, &order.order_id, &order)
-    }) {
-        Ok(_) => Ok(HttpResponse::Created().json(ApiResponse::<&str>::success("Supplier order created"))),
-        Err(e) => Ok(HttpResponse::InternalServerError().json(ApiResponse::<String>::error(&e.to_string()))),
+    match data.db.create_supplier_order(order.into_inner()) {
+        Ok(order) => Ok(created(order.version, order)),
+        Err(e) => Err(e.into()),
     }
 }
 
 #[put("/api/supplier-orders/{id}")]
 async fn update_supplier_order(
+    req: HttpRequest,
     data: web::Data<AppState>,
     path: web::Path<String>,
     order: web::Json<SuppliersOrders>
@@ -351,41 +980,86 @@ async fn update_supplier_order(
     let mut order = order.into_inner();
     order.order_id = id;
 
-    match data.db.with_write_txn(|wtxn| {
-        data.db.suppliers_orders_db.put(wtxn, &order.order_id, &order)
-    }) {
-        Ok(_) => Ok(HttpResponse::Ok().json(ApiResponse::<&str>::success("Supplier order updated"))),
-        Err(e) => Ok(HttpResponse::InternalServerError().json(ApiResponse::<String>::error(&e.to_string()))),
+    match data.db.update_supplier_order(order, if_match(&req)?.as_deref()) {
+        Ok(record) => Ok(tagged(record.version, record)),
+        Err(e) => Err(e.into()),
+    }
+}
+
+#[patch("/api/supplier-orders/{id}")]
+async fn patch_supplier_order(
+    req: HttpRequest,
+    data: web::Data<AppState>,
+    path: web::Path<String>,
+    patch: web::Json<Value>
+) -> Result<impl Responder> {
+    match data.db.patch_supplier_order(&path.into_inner(), &patch, if_match(&req)?.as_deref()) {
+        Ok(record) => Ok(tagged(record.version, record)),
+        Err(e) => Err(e.into()),
     }
 }
 
 #[delete("/api/supplier-orders/{id}")]
 async fn delete_supplier_order(
+    req: HttpRequest,
     data: web::Data<AppState>,
     path: web::Path<String>
 ) -> Result<impl Responder> {
     let id = path.into_inner();
-    match data.db.with_write_txn(|wtxn| {
-        data.db.suppliers_orders_db.delete(wtxn, &id)
-    }) {
+    match data.db.delete_supplier_order(&id, if_match(&req)?.as_deref()) {
         Ok(true) => Ok(HttpResponse::Ok().json(ApiResponse::<&str>::success("Supplier order deleted"))),
-        Ok(false) => Ok(HttpResponse::NotFound().json(ApiResponse::<String>::error("Supplier order not found"))),
-        Err(e) => Ok(HttpResponse::InternalServerError().json(ApiResponse::<String>::error(&e.to_string()))),
+        Ok(false) => Err(InventoryError::NotFound(format!("supplier order '{}'", id)).into()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+#[post("/api/supplier-orders/{id}/status")]
+async fn transition_supplier_order(
+    data: web::Data<AppState>,
+    path: web::Path<String>,
+    request: web::Json<StatusRequest<SupplierOrderStatus>>
+) -> Result<impl Responder> {
+    let id = path.into_inner();
+    match data.db.transition_supplier_order(&id, request.into_inner().status) {
+        Ok(record) => Ok(tagged(record.version, record)),
+        Err(e) => Err(e.into()),
+    }
+}
+
+#[post("/api/supplier-orders/{id}/receipts")]
+async fn receive_supplier_order(
+    data: web::Data<AppState>,
+    path: web::Path<String>,
+    request: web::Json<SupplierReceiptRequest>
+) -> Result<impl Responder> {
+    let id = path.into_inner();
+    let request = request.into_inner();
+    let date = request.date.unwrap_or_else(|| Local::now().date_naive());
+    match
+        data.db.receive_supplier_order(
+            &id,
+            &request.location,
+            request.quantity,
+            date,
+            request.notes
+        )
+    {
+        Ok(record) => Ok(tagged(record.version, record)),
+        Err(e) => Err(e.into()),
     }
 }
 
 // ========== PROCUREMENTS API ==========
 
 #[get("/api/procurements")]
-async fn get_all_procurements(data: web::Data<AppState>) -> Result<impl Responder> {
-    match data.db.with_read_txn(|rtxn| {
-        data.db.procurements_db
-            .iter(&rtxn)?
-            .map(|res| res.map(|(_, v)| v))
-            .collect::<heed::Result<Vec<Procurements>>>()
-    }) {
-        Ok(procurements) => Ok(HttpResponse::Ok().json(ApiResponse::<Vec<Procurements>>::success(procurements))),
-        Err(e) => Ok(HttpResponse::InternalServerError().json(ApiResponse::<String>::error(&e.to_string()))),
+async fn get_all_procurements(
+    data: web::Data<AppState>,
+    page: web::Query<PageQuery>
+) -> Result<impl Responder> {
+    let request = page.into_inner().request();
+    match data.db.page(&data.db.procurements_db, &request, |_| true) {
+        Ok(page) => Ok(HttpResponse::Ok().json(ApiResponse::page(page, request.limit))),
+        Err(e) => Err(InventoryError::from(e).into()),
     }
 }
 
@@ -395,10 +1069,10 @@ async fn get_procurement(
     path: web::Path<String>
 ) -> Result<impl Responder> {
     let id = path.into_inner();
-    match data.db.with_read_txn(|rtxn| data.db.procurements_db.get(&rtxn, &id)) {
-        Ok(Some(procurement)) => Ok(HttpResponse::Ok().json(ApiResponse::<Procurements>::success(procurement))),
-        Ok(None) => Ok(HttpResponse::NotFound().json(ApiResponse::<String>::error("Procurement not found"))),
-        Err(e) => Ok(HttpResponse::InternalServerError().json(ApiResponse::<String>::error(&e.to_string()))),
+    match data.db.with_read_txn(|rtxn| data.db.procurements_db.get(rtxn, &id)) {
+        Ok(Some(procurement)) => Ok(tagged(procurement.version, procurement)),
+        Ok(None) => Err(InventoryError::NotFound(format!("procurement '{}'", id)).into()),
+        Err(e) => Err(InventoryError::from(e).into()),
     }
 }
 
@@ -407,17 +1081,15 @@ async fn create_procurement(
     data: web::Data<AppState>,
     procurement: web::Json<Procurements>
 ) -> Result<impl Responder> {
-    let procurement = procurement.into_inner();
-    match data.db.with_write_txn(|wtxn| {
-        data.db.procurements_db.put(wtxn, &procurement.procurement_id, &procurement)
-    }) {
-        Ok(_) => Ok(HttpResponse::Created().json(ApiResponse::<&str>::success("Procurement created"))),
-        Err(e) => Ok(HttpResponse::InternalServerError().json(ApiResponse::<String>::error(&e.to_string()))),
+    match data.db.create_procurements(procurement.into_inner()) {
+        Ok(procurement) => Ok(created(procurement.version, procurement)),
+        Err(e) => Err(e.into()),
     }
 }
 
 #[put("/api/procurements/{id}")]
 async fn update_procurement(
+    req: HttpRequest,
     data: web::Data<AppState>,
     path: web::Path<String>,
     procurement: web::Json<Procurements>
@@ -426,41 +1098,120 @@ async fn update_procurement(
     let mut procurement = procurement.into_inner();
     procurement.procurement_id = id;
 
-    match data.db.with_write_txn(|wtxn| {
-        data.db.procurements_db.put(wtxn, &procurement.procurement_id, &procurement)
-    }) {
-        Ok(_) => Ok(HttpResponse::Ok().json(ApiResponse::<&str>::success("Procurement updated"))),
-        Err(e) => Ok(HttpResponse::InternalServerError().json(ApiResponse::<String>::error(&e.to_string()))),
+    match data.db.put_procurements(procurement, if_match(&req)?.as_deref()) {
+        Ok(record) => Ok(tagged(record.version, record)),
+        Err(e) => Err(e.into()),
+    }
+}
+
+#[patch("/api/procurements/{id}")]
+async fn patch_procurements(
+    req: HttpRequest,
+    data: web::Data<AppState>,
+    path: web::Path<String>,
+    patch: web::Json<Value>
+) -> Result<impl Responder> {
+    match data.db.patch_procurements(&path.into_inner(), &patch, if_match(&req)?.as_deref()) {
+        Ok(record) => Ok(tagged(record.version, record)),
+        Err(e) => Err(e.into()),
+    }
+}
+
+#[post("/api/procurements/{id}/lines/{line_id}/status")]
+async fn transition_procurement(
+    data: web::Data<AppState>,
+    path: web::Path<(String, String)>,
+    request: web::Json<StatusRequest<ProcurementStatus>>
+) -> Result<impl Responder> {
+    let (id, line_id) = path.into_inner();
+    match data.db.transition_procurement(&id, &line_id, request.into_inner().status) {
+        Ok(group) => Ok(tagged(group.version, group)),
+        Err(e) => Err(e.into()),
     }
 }
 
 #[delete("/api/procurements/{id}")]
 async fn delete_procurement(
+    req: HttpRequest,
     data: web::Data<AppState>,
     path: web::Path<String>
 ) -> Result<impl Responder> {
     let id = path.into_inner();
-    match data.db.with_write_txn(|wtxn| {
-        data.db.procurements_db.delete(wtxn, &id)
-    }) {
+    match data.db.delete_procurements(&id, if_match(&req)?.as_deref()) {
         Ok(true) => Ok(HttpResponse::Ok().json(ApiResponse::<&str>::success("Procurement deleted"))),
-        Ok(false) => Ok(HttpResponse::NotFound().json(ApiResponse::<String>::error("Procurement not found"))),
-        Err(e) => Ok(HttpResponse::InternalServerError().json(ApiResponse::<String>::error(&e.to_string()))),
+        Ok(false) => Err(InventoryError::NotFound(format!("procurement '{}'", id)).into()),
+        Err(e) => Err(e.into()),
     }
 }
 
 // ========== ASSEMBLY TIMELINE API ==========
 
 #[get("/api/assembly-timeline")]
-async fn get_all_assembly_timelines(data: web::Data<AppState>) -> Result<impl Responder> {
-    match data.db.with_read_txn(|rtxn| {
-        data.db.assembly_timeline_db
-            .iter(&rtxn)?
-            .map(|res| res.map(|(_, v)| v))
-            .collect::<heed::Result<Vec<AssemblyTimeLine>>>()
-    }) {
-        Ok(timelines) => Ok(HttpResponse::Ok().json(ApiResponse::<Vec<AssemblyTimeLine>>::success(timelines))),
-        Err(e) => Ok(HttpResponse::InternalServerError().json(ApiResponse::<String>::error(&e.to_string()))),
+async fn get_all_assembly_timelines(
+    data: web::Data<AppState>,
+    page: web::Query<PageQuery>,
+    filter: web::Query<AssemblyFilter>
+) -> Result<impl Responder> {
+    let request = page.into_inner().request();
+    match data.db.list_assembly_timelines(&filter, &request) {
+        Ok(page) => Ok(HttpResponse::Ok().json(ApiResponse::page(page, request.limit))),
+        Err(e) => Err(InventoryError::from(e).into()),
+    }
+}
+
+#[post("/api/assembly-timeline/schedule")]
+async fn schedule_assembly(
+    data: web::Data<AppState>,
+    request: Option<web::Json<ScheduleAssemblyRequest>>
+) -> Result<impl Responder> {
+    let request = request.map(|r| r.into_inner()).unwrap_or_default();
+    let start_date = request.start_date.unwrap_or_else(|| Local::now().date_naive());
+    match data.db.schedule_assembly(start_date, request.persist) {
+        Ok(schedule) => Ok(HttpResponse::Ok().json(ApiResponse::<AssemblySchedule>::success(schedule))),
+        Err(e) => Err(e.into()),
+    }
+}
+
+#[post("/api/assembly-timeline/{id}/start")]
+async fn start_assembly(
+    data: web::Data<AppState>,
+    path: web::Path<String>,
+    request: Option<web::Json<StartAssemblyRequest>>
+) -> Result<impl Responder> {
+    let id = path.into_inner();
+    let request = request.map(|r| r.into_inner()).unwrap_or_default();
+    let date = request.date.unwrap_or_else(|| Local::now().date_naive());
+    match data.db.start_assembly(&id, date, request.notes) {
+        Ok(timeline) => Ok(tagged(timeline.version, timeline)),
+        Err(e) => Err(e.into()),
+    }
+}
+
+#[post("/api/assembly-timeline/{id}/complete")]
+async fn complete_assembly(
+    data: web::Data<AppState>,
+    path: web::Path<String>,
+    request: Option<web::Json<CompleteAssemblyRequest>>
+) -> Result<impl Responder> {
+    let id = path.into_inner();
+    let request = request.map(|r| r.into_inner()).unwrap_or_default();
+    let date = request.date.unwrap_or_else(|| Local::now().date_naive());
+    match data.db.complete_assembly(&id, request.produced, request.scrapped, date) {
+        Ok(timeline) => Ok(tagged(timeline.version, timeline)),
+        Err(e) => Err(e.into()),
+    }
+}
+
+#[post("/api/assembly-timeline/{id}/status")]
+async fn transition_assembly(
+    data: web::Data<AppState>,
+    path: web::Path<String>,
+    request: web::Json<StatusRequest<AssemblyStatus>>
+) -> Result<impl Responder> {
+    let id = path.into_inner();
+    match data.db.transition_assembly(&id, request.into_inner().status) {
+        Ok(timeline) => Ok(tagged(timeline.version, timeline)),
+        Err(e) => Err(e.into()),
     }
 }
 
@@ -470,10 +1221,10 @@ async fn get_assembly_timeline(
     path: web::Path<String>
 ) -> Result<impl Responder> {
     let id = path.into_inner();
-    match data.db.with_read_txn(|rtxn| data.db.assembly_timeline_db.get(&rtxn, &id)) {
-        Ok(Some(timeline)) => Ok(HttpResponse::Ok().json(ApiResponse::<AssemblyTimeLine>::success(timeline))),
-        Ok(None) => Ok(HttpResponse::NotFound().json(ApiResponse::<String>::error("Assembly timeline not found"))),
-        Err(e) => Ok(HttpResponse::InternalServerError().json(ApiResponse::<String>::error(&e.to_string()))),
+    match data.db.with_read_txn(|rtxn| data.db.assembly_timeline_db.get(rtxn, &id)) {
+        Ok(Some(timeline)) => Ok(tagged(timeline.version, timeline)),
+        Ok(None) => Err(InventoryError::NotFound(format!("assembly timeline '{}'", id)).into()),
+        Err(e) => Err(InventoryError::from(e).into()),
     }
 }
 
@@ -482,17 +1233,15 @@ async fn create_assembly_timeline(
     data: web::Data<AppState>,
     timeline: web::Json<AssemblyTimeLine>
 ) -> Result<impl Responder> {
-    let timeline = timeline.into_inner();
-    match data.db.with_write_txn(|wtxn| {
-        data.db.assembly_timeline_db.put(wtxn, &timeline.assembly_id, &timeline)
-    }) {
-        Ok(_) => Ok(HttpResponse::Created().json(ApiResponse::<&str>::success("Assembly timeline created"))),
-        Err(e) => Ok(HttpResponse::InternalServerError().json(ApiResponse::<String>::error(&e.to_string()))),
+    match data.db.create_assembly_timeline(timeline.into_inner()) {
+        Ok(timeline) => Ok(created(timeline.version, timeline)),
+        Err(e) => Err(e.into()),
     }
 }
 
 #[put("/api/assembly-timeline/{id}")]
 async fn update_assembly_timeline(
+    req: HttpRequest,
     data: web::Data<AppState>,
     path: web::Path<String>,
     timeline: web::Json<AssemblyTimeLine>
@@ -501,41 +1250,50 @@ async fn update_assembly_timeline(
     let mut timeline = timeline.into_inner();
     timeline.assembly_id = id;
 
-    match data.db.with_write_txn(|wtxn| {
-        data.db.assembly_timeline_db.put(wtxn, &timeline.assembly_id, &timeline)
-    }) {
-        Ok(_) => Ok(HttpResponse::Ok().json(ApiResponse::<&str>::success("Assembly timeline updated"))),
-        Err(e) => Ok(HttpResponse::InternalServerError().json(ApiResponse::<String>::error(&e.to_string()))),
+    match data.db.put_assembly_timeline(timeline, if_match(&req)?.as_deref()) {
+        Ok(record) => Ok(tagged(record.version, record)),
+        Err(e) => Err(e.into()),
+    }
+}
+
+#[patch("/api/assembly-timeline/{id}")]
+async fn patch_assembly_timeline(
+    req: HttpRequest,
+    data: web::Data<AppState>,
+    path: web::Path<String>,
+    patch: web::Json<Value>
+) -> Result<impl Responder> {
+    match data.db.patch_assembly_timeline(&path.into_inner(), &patch, if_match(&req)?.as_deref()) {
+        Ok(record) => Ok(tagged(record.version, record)),
+        Err(e) => Err(e.into()),
     }
 }
 
 #[delete("/api/assembly-timeline/{id}")]
 async fn delete_assembly_timeline(
+    req: HttpRequest,
     data: web::Data<AppState>,
     path: web::Path<String>
 ) -> Result<impl Responder> {
     let id = path.into_inner();
-    match data.db.with_write_txn(|wtxn| {
-        data.db.assembly_timeline_db.delete(wtxn, &id)
-    }) {
+    match data.db.delete_assembly_timeline(&id, if_match(&req)?.as_deref()) {
         Ok(true) => Ok(HttpResponse::Ok().json(ApiResponse::<&str>::success("Assembly timeline deleted"))),
-        Ok(false) => Ok(HttpResponse::NotFound().json(ApiResponse::<String>::error("Assembly timeline not found"))),
-        Err(e) => Ok(HttpResponse::InternalServerError().json(ApiResponse::<String>::error(&e.to_string()))),
+        Ok(false) => Err(InventoryError::NotFound(format!("assembly timeline '{}'", id)).into()),
+        Err(e) => Err(e.into()),
     }
 }
 
 // ========== PRODUCTION RATE API ==========
 
 #[get("/api/production-rates")]
-async fn get_all_production_rates(data: web::Data<AppState>) -> Result<impl Responder> {
-    match data.db.with_read_txn(|rtxn| {
-        data.db.production_rate_db
-            .iter(&rtxn)?
-            .map(|res| res.map(|(_, v)| v))
-            .collect::<heed::Result<Vec<ProductionRate>>>()
-    }) {
-        Ok(rates) => Ok(HttpResponse::Ok().json(ApiResponse::<Vec<ProductionRate>>::success(rates))),
-        Err(e) => Ok(HttpResponse::InternalServerError().json(ApiResponse::<String>::error(&e.to_string()))),
+async fn get_all_production_rates(
+    data: web::Data<AppState>,
+    page: web::Query<PageQuery>
+) -> Result<impl Responder> {
+    let request = page.into_inner().request();
+    match data.db.page(&data.db.production_rate_db, &request, |_| true) {
+        Ok(page) => Ok(HttpResponse::Ok().json(ApiResponse::page(page, request.limit))),
+        Err(e) => Err(InventoryError::from(e).into()),
     }
 }
 
@@ -545,10 +1303,10 @@ async fn get_production_rate(
     path: web::Path<String>
 ) -> Result<impl Responder> {
     let id = path.into_inner();
-    match data.db.with_read_txn(|rtxn| data.db.production_rate_db.get(&rtxn, &id)) {
-        Ok(Some(rate)) => Ok(HttpResponse::Ok().json(ApiResponse::<ProductionRate>::success(rate))),
-        Ok(None) => Ok(HttpResponse::NotFound().json(ApiResponse::<String>::error("Production rate not found"))),
-        Err(e) => Ok(HttpResponse::InternalServerError().json(ApiResponse::<String>::error(&e.to_string()))),
+    match data.db.with_read_txn(|rtxn| data.db.production_rate_db.get(rtxn, &id)) {
+        Ok(Some(rate)) => Ok(tagged(rate.version, rate)),
+        Ok(None) => Err(InventoryError::NotFound(format!("production rate '{}'", id)).into()),
+        Err(e) => Err(InventoryError::from(e).into()),
     }
 }
 
@@ -557,17 +1315,15 @@ async fn create_production_rate(
     data: web::Data<AppState>,
     rate: web::Json<ProductionRate>
 ) -> Result<impl Responder> {
-    let rate = rate.into_inner();
-    match data.db.with_write_txn(|wtxn| {
-        data.db.production_rate_db.put(wtxn, &rate.prodction_rate_id, &rate)
-    }) {
-        Ok(_) => Ok(HttpResponse::Created().json(ApiResponse::<&str>::success("Production rate created"))),
-        Err(e) => Ok(HttpResponse::InternalServerError().json(ApiResponse::<String>::error(&e.to_string()))),
+    match data.db.create_production_rate(rate.into_inner()) {
+        Ok(rate) => Ok(created(rate.version, rate)),
+        Err(e) => Err(e.into()),
     }
 }
 
 #[put("/api/production-rates/{id}")]
 async fn update_production_rate(
+    req: HttpRequest,
     data: web::Data<AppState>,
     path: web::Path<String>,
     rate: web::Json<ProductionRate>
@@ -576,41 +1332,50 @@ async fn update_production_rate(
     let mut rate = rate.into_inner();
     rate.prodction_rate_id = id;
 
-    match data.db.with_write_txn(|wtxn| {
-        data.db.production_rate_db.put(wtxn, &rate.prodction_rate_id, &rate)
-    }) {
-        Ok(_) => Ok(HttpResponse::Ok().json(ApiResponse::<&str>::success("Production rate updated"))),
-        Err(e) => Ok(HttpResponse::InternalServerError().json(ApiResponse::<String>::error(&e.to_string()))),
+    match data.db.update_production_rate(rate, if_match(&req)?.as_deref()) {
+        Ok(record) => Ok(tagged(record.version, record)),
+        Err(e) => Err(e.into()),
+    }
+}
+
+#[patch("/api/production-rates/{id}")]
+async fn patch_production_rate(
+    req: HttpRequest,
+    data: web::Data<AppState>,
+    path: web::Path<String>,
+    patch: web::Json<Value>
+) -> Result<impl Responder> {
+    match data.db.patch_production_rate(&path.into_inner(), &patch, if_match(&req)?.as_deref()) {
+        Ok(record) => Ok(tagged(record.version, record)),
+        Err(e) => Err(e.into()),
     }
 }
 
 #[delete("/api/production-rates/{id}")]
 async fn delete_production_rate(
+    req: HttpRequest,
     data: web::Data<AppState>,
     path: web::Path<String>
 ) -> Result<impl Responder> {
     let id = path.into_inner();
-    match data.db.with_write_txn(|wtxn| {
-        data.db.production_rate_db.delete(wtxn, &id)
-    }) {
+    match data.db.delete_production_rate(&id, if_match(&req)?.as_deref()) {
         Ok(true) => Ok(HttpResponse::Ok().json(ApiResponse::<&str>::success("Production rate deleted"))),
-        Ok(false) => Ok(HttpResponse::NotFound().json(ApiResponse::<String>::error("Production rate not found"))),
-        Err(e) => Ok(HttpResponse::InternalServerError().json(ApiResponse::<String>::error(&e.to_string()))),
+        Ok(false) => Err(InventoryError::NotFound(format!("production rate '{}'", id)).into()),
+        Err(e) => Err(e.into()),
     }
 }
 
 // ========== REORDER POINTS API ==========
 
 #[get("/api/reorder-points")]
-async fn get_all_reorder_points(data: web::Data<AppState>) -> Result<impl Responder> {
-    match data.db.with_read_txn(|rtxn| {
-        data.db.recorder_point_db
-            .iter(&rtxn)?
-            .map(|res| res.map(|(_, v)| v))
-            .collect::<heed::Result<Vec<RecorderPoint>>>()
-    }) {
-        Ok(points) => Ok(HttpResponse::Ok().json(ApiResponse::<Vec<RecorderPoint>>::success(points))),
-        Err(e) => Ok(HttpResponse::InternalServerError().json(ApiResponse::<String>::error(&e.to_string()))),
+async fn get_all_reorder_points(
+    data: web::Data<AppState>,
+    page: web::Query<PageQuery>
+) -> Result<impl Responder> {
+    let request = page.into_inner().request();
+    match data.db.page(&data.db.recorder_point_db, &request, |_| true) {
+        Ok(page) => Ok(HttpResponse::Ok().json(ApiResponse::page(page, request.limit))),
+        Err(e) => Err(InventoryError::from(e).into()),
     }
 }
 
@@ -620,10 +1385,10 @@ async fn get_reorder_point(
     path: web::Path<String>
 ) -> Result<impl Responder> {
     let id = path.into_inner();
-    match data.db.with_read_txn(|rtxn| data.db.recorder_point_db.get(&rtxn, &id)) {
-        Ok(Some(point)) => Ok(HttpResponse::Ok().json(ApiResponse::<RecorderPoint>::success(point))),
-        Ok(None) => Ok(HttpResponse::NotFound().json(ApiResponse::<String>::error("Reorder point not found"))),
-        Err(e) => Ok(HttpResponse::InternalServerError().json(ApiResponse::<String>::error(&e.to_string()))),
+    match data.db.with_read_txn(|rtxn| data.db.recorder_point_db.get(rtxn, &id)) {
+        Ok(Some(point)) => Ok(tagged(point.version, point)),
+        Ok(None) => Err(InventoryError::NotFound(format!("reorder point '{}'", id)).into()),
+        Err(e) => Err(InventoryError::from(e).into()),
     }
 }
 
@@ -632,17 +1397,15 @@ async fn create_reorder_point(
     data: web::Data<AppState>,
     point: web::Json<RecorderPoint>
 ) -> Result<impl Responder> {
-    let point = point.into_inner();
-    match data.db.with_write_txn(|wtxn| {
-        data.db.recorder_point_db.put(wtxn, &point.recorder_point_id, &point)
-    }) {
-        Ok(_) => Ok(HttpResponse::Created().json(ApiResponse::<&str>::success("Reorder point created"))),
-        Err(e) => Ok(HttpResponse::InternalServerError().json(ApiResponse::<String>::error(&e.to_string()))),
+    match data.db.create_reorder_point(point.into_inner()) {
+        Ok(point) => Ok(created(point.version, point)),
+        Err(e) => Err(e.into()),
     }
 }
 
 #[put("/api/reorder-points/{id}")]
 async fn update_reorder_point(
+    req: HttpRequest,
     data: web::Data<AppState>,
     path: web::Path<String>,
     point: web::Json<RecorderPoint>
@@ -651,41 +1414,76 @@ async fn update_reorder_point(
     let mut point = point.into_inner();
     point.recorder_point_id = id;
 
-    match data.db.with_write_txn(|wtxn| {
-        data.db.recorder_point_db.put(wtxn, &point.recorder_point_id, &point)
-    }) {
-        Ok(_) => Ok(HttpResponse::Ok().json(ApiResponse::<&str>::success("Reorder point updated"))),
-        Err(e) => Ok(HttpResponse::InternalServerError().json(ApiResponse::<String>::error(&e.to_string()))),
+    match data.db.put_reorder_point(point, if_match(&req)?.as_deref()) {
+        Ok(record) => Ok(tagged(record.version, record)),
+        Err(e) => Err(e.into()),
+    }
+}
+
+#[patch("/api/reorder-points/{id}")]
+async fn patch_reorder_point(
+    req: HttpRequest,
+    data: web::Data<AppState>,
+    path: web::Path<String>,
+    patch: web::Json<Value>
+) -> Result<impl Responder> {
+    match data.db.patch_reorder_point(&path.into_inner(), &patch, if_match(&req)?.as_deref()) {
+        Ok(record) => Ok(tagged(record.version, record)),
+        Err(e) => Err(e.into()),
+    }
+}
+
+#[post("/api/reorder-points/recompute")]
+async fn recompute_reorder_points(
+    data: web::Data<AppState>,
+    query: web::Query<RecomputeQuery>
+) -> Result<impl Responder> {
+    let policy = query.policy(data.reorder_policy);
+    match data.db.recompute_reorder_points(policy, Local::now().date_naive()) {
+        Ok(points) => Ok(HttpResponse::Ok().json(ApiResponse::<Vec<RecorderPoint>>::success(points))),
+        Err(e) => Err(e.into()),
+    }
+}
+
+#[post("/api/reorder-points/{id}/recompute")]
+async fn recompute_reorder_point(
+    data: web::Data<AppState>,
+    path: web::Path<String>,
+    query: web::Query<RecomputeQuery>
+) -> Result<impl Responder> {
+    let id = path.into_inner();
+    let policy = query.policy(data.reorder_policy);
+    match data.db.recompute_reorder_point(&id, policy, Local::now().date_naive()) {
+        Ok(record) => Ok(tagged(record.version, record)),
+        Err(e) => Err(e.into()),
     }
 }
 
 #[delete("/api/reorder-points/{id}")]
 async fn delete_reorder_point(
+    req: HttpRequest,
     data: web::Data<AppState>,
     path: web::Path<String>
 ) -> Result<impl Responder> {
     let id = path.into_inner();
-    match data.db.with_write_txn(|wtxn| {
-        data.db.recorder_point_db.delete(wtxn, &id)
-    }) {
+    match data.db.delete_reorder_point(&id, if_match(&req)?.as_deref()) {
         Ok(true) => Ok(HttpResponse::Ok().json(ApiResponse::<&str>::success("Reorder point deleted"))),
-        Ok(false) => Ok(HttpResponse::NotFound().json(ApiResponse::<String>::error("Reorder point not found"))),
-        Err(e) => Ok(HttpResponse::InternalServerError().json(ApiResponse::<String>::error(&e.to_string()))),
+        Ok(false) => Err(InventoryError::NotFound(format!("reorder point '{}'", id)).into()),
+        Err(e) => Err(e.into()),
     }
 }
 
 // ========== WATCHES API ==========
 
 #[get("/api/watches")]
-async fn get_all_watches(data: web::Data<AppState>) -> Result<impl Responder> {
-    match data.db.with_read_txn(|rtxn| {
-        data.db.watches_db
-            .iter(&rtxn)?
-            .map(|res| res.map(|(_, v)| v))
-            .collect::<heed::Result<Vec<Watches>>>()
-    }) {
-        Ok(watches) => Ok(HttpResponse::Ok().json(ApiResponse::<Vec<Watches>>::success(watches))),
-        Err(e) => Ok(HttpResponse::InternalServerError().json(ApiResponse::<String>::error(&e.to_string()))),
+async fn get_all_watches(
+    data: web::Data<AppState>,
+    page: web::Query<PageQuery>
+) -> Result<impl Responder> {
+    let request = page.into_inner().request();
+    match data.db.page(&data.db.watches_db, &request, |_| true) {
+        Ok(page) => Ok(HttpResponse::Ok().json(ApiResponse::page(page, request.limit))),
+        Err(e) => Err(InventoryError::from(e).into()),
     }
 }
 
@@ -695,10 +1493,10 @@ async fn get_watch(
     path: web::Path<String>
 ) -> Result<impl Responder> {
     let id = path.into_inner();
-    match data.db.with_read_txn(|rtxn| data.db.watches_db.get(&rtxn, &id)) {
-        Ok(Some(watch)) => Ok(HttpResponse::Ok().json(ApiResponse::<Watches>::success(watch))),
-        Ok(None) => Ok(HttpResponse::NotFound().json(ApiResponse::<String>::error("Watch not found"))),
-        Err(e) => Ok(HttpResponse::InternalServerError().json(ApiResponse::<String>::error(&e.to_string()))),
+    match data.db.with_read_txn(|rtxn| data.db.watches_db.get(rtxn, &id)) {
+        Ok(Some(watch)) => Ok(tagged(watch.version, watch)),
+        Ok(None) => Err(InventoryError::NotFound(format!("watch '{}'", id)).into()),
+        Err(e) => Err(InventoryError::from(e).into()),
     }
 }
 
@@ -707,17 +1505,15 @@ async fn create_watch(
     data: web::Data<AppState>,
     watch: web::Json<Watches>
 ) -> Result<impl Responder> {
-    let watch = watch.into_inner();
-    match data.db.with_write_txn(|wtxn| {
-        data.db.watches_db.put(wtxn, &watch.watch_id, &watch)
-    }) {
-        Ok(_) => Ok(HttpResponse::Created().json(ApiResponse::<&str>::success("Watch created"))),
-        Err(e) => Ok(HttpResponse::InternalServerError().json(ApiResponse::<String>::error(&e.to_string()))),
+    match data.db.create_watch(watch.into_inner()) {
+        Ok(watch) => Ok(created(watch.version, watch)),
+        Err(e) => Err(e.into()),
     }
 }
 
 #[put("/api/watches/{id}")]
 async fn update_watch(
+    req: HttpRequest,
     data: web::Data<AppState>,
     path: web::Path<String>,
     watch: web::Json<Watches>
@@ -726,79 +1522,154 @@ async fn update_watch(
     let mut watch = watch.into_inner();
     watch.watch_id = id;
 
-    match data.db.with_write_txn(|wtxn| {
-        data.db.watches_db.put(wtxn, &watch.watch_id, &watch)
-    }) {
-        Ok(_) => Ok(HttpResponse::Ok().json(ApiResponse::<&str>::success("Watch updated"))),
-        Err(e) => Ok(HttpResponse::InternalServerError().json(ApiResponse::<String>::error(&e.to_string()))),
+    match data.db.update_watch(watch, if_match(&req)?.as_deref()) {
+        Ok(record) => Ok(tagged(record.version, record)),
+        Err(e) => Err(e.into()),
+    }
+}
+
+#[patch("/api/watches/{id}")]
+async fn patch_watch(
+    req: HttpRequest,
+    data: web::Data<AppState>,
+    path: web::Path<String>,
+    patch: web::Json<Value>
+) -> Result<impl Responder> {
+    match data.db.patch_watch(&path.into_inner(), &patch, if_match(&req)?.as_deref()) {
+        Ok(record) => Ok(tagged(record.version, record)),
+        Err(e) => Err(e.into()),
     }
 }
 
 #[delete("/api/watches/{id}")]
 async fn delete_watch(
+    req: HttpRequest,
     data: web::Data<AppState>,
     path: web::Path<String>
 ) -> Result<impl Responder> {
     let id = path.into_inner();
-    match data.db.with_write_txn(|wtxn| {
-        data.db.watches_db.delete(wtxn, &id)
-    }) {
+    match data.db.delete_watch(&id, if_match(&req)?.as_deref()) {
         Ok(true) => Ok(HttpResponse::Ok().json(ApiResponse::<&str>::success("Watch deleted"))),
-        Ok(false) => Ok(HttpResponse::NotFound().json(ApiResponse::<String>::error("Watch not found"))),
-        Err(e) => Ok(HttpResponse::InternalServerError().json(ApiResponse::<String>::error(&e.to_string()))),
+        Ok(false) => Err(InventoryError::NotFound(format!("watch '{}'", id)).into()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+// ========== BILL OF MATERIALS API ==========
+
+#[get("/api/watch-models/{id}/bom")]
+async fn get_watch_model_bom(
+    data: web::Data<AppState>,
+    path: web::Path<String>,
+    query: web::Query<BomQuery>
+) -> Result<impl Responder> {
+    let id = path.into_inner();
+    let quantity = query.quantity.unwrap_or(1);
+    match data.db.explode_bom(&id, quantity) {
+        Ok(Some(bom)) => Ok(HttpResponse::Ok().json(ApiResponse::<BomExplosion>::success(bom))),
+        Ok(None) => Err(InventoryError::NotFound(format!("watch model '{}'", id)).into()),
+        Err(e) => Err(e.into()),
     }
 }
 
-// Update the init_routes function to include all new routes
 pub fn init_routes(cfg: &mut web::ServiceConfig) {
+    cfg.app_data(
+        web::JsonConfig::default()
+            .content_type(|mime| mime.essence_str() == "application/merge-patch+json")
+            .error_handler(|e, _| malformed(e))
+    )
+        .app_data(web::QueryConfig::default().error_handler(|e, _| malformed(e)))
+        .app_data(web::PathConfig::default().error_handler(|e, _| malformed(e)));
     cfg.service(get_all_products)
         .service(get_product)
         .service(create_product)
         .service(update_product)
+        .service(patch_product)
         .service(delete_product)
         .service(get_all_components)
         .service(get_component)
         .service(create_component)
         .service(update_component)
+        .service(patch_component)
         .service(delete_component)
+        .service(get_all_locations)
+        .service(get_location)
+        .service(create_location)
+        .service(update_location)
+        .service(patch_location)
+        .service(delete_location)
         .service(get_all_movements)
         .service(get_movement)
         .service(record_movement)
+        .service(receive_movement)
+        .service(cancel_movement)
         .service(get_all_orders)
         .service(get_order)
         .service(create_order)
         .service(update_order)
+        .service(patch_order)
         .service(delete_order)
+        .service(reserve_order)
+        .service(release_order)
+        .service(transition_order)
+        .service(generate_procurements)
+        .service(get_all_suppliers)
+        .service(get_supplier)
+        .service(create_supplier)
+        .service(update_supplier)
+        .service(patch_supplier)
+        .service(delete_supplier)
+        .service(get_supplier_catalogue)
+        .service(get_catalogue_item)
+        .service(put_catalogue_item)
+        .service(patch_catalogue_item)
+        .service(delete_catalogue_item)
         .service(get_all_supplier_orders)
         .service(get_supplier_order)
         .service(create_supplier_order)
         .service(update_supplier_order)
+        .service(patch_supplier_order)
         .service(delete_supplier_order)
+        .service(receive_supplier_order)
+        .service(transition_supplier_order)
         .service(get_all_procurements)
         .service(get_procurement)
         .service(create_procurement)
         .service(update_procurement)
+        .service(patch_procurements)
         .service(delete_procurement)
+        .service(transition_procurement)
         .service(get_all_assembly_timelines)
+        .service(schedule_assembly)
+        .service(start_assembly)
+        .service(complete_assembly)
+        .service(transition_assembly)
         .service(get_assembly_timeline)
         .service(create_assembly_timeline)
         .service(update_assembly_timeline)
+        .service(patch_assembly_timeline)
         .service(delete_assembly_timeline)
         .service(get_all_production_rates)
         .service(get_production_rate)
         .service(create_production_rate)
         .service(update_production_rate)
+        .service(patch_production_rate)
         .service(delete_production_rate)
         .service(get_all_reorder_points)
         .service(get_reorder_point)
         .service(create_reorder_point)
         .service(update_reorder_point)
+        .service(patch_reorder_point)
+        .service(recompute_reorder_points)
+        .service(recompute_reorder_point)
         .service(delete_reorder_point)
         .service(get_all_watches)
         .service(get_watch)
         .service(create_watch)
         .service(update_watch)
+        .service(patch_watch)
         .service(delete_watch)
+        .service(get_watch_model_bom)
         .service(get_inventory_levels)
         .service(get_product_components)
         .service(add_component_to_product);
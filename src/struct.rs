@@ -1,22 +1,214 @@
 use serde::{ Serialize, Deserialize };
 use chrono::NaiveDate;
+use std::collections::BTreeMap;
+use std::fmt;
+use crate::error::{ FieldError, InventoryError };
+
+/// Lifecycle status of a record. Each status type lists the moves it allows;
+/// anything else is rejected as an invalid transition.
+pub trait Status: Copy + fmt::Debug {
+    fn can_become(self, next: Self) -> bool;
+}
+
+/// Field rules a payload has to meet before it is written.
+pub trait Validate {
+    fn rules(&self, rules: &mut Rules);
+
+    /// Runs every rule, reporting all violations at once.
+    fn validate(&self) -> Result<(), InventoryError> {
+        let mut rules = Rules::default();
+        self.rules(&mut rules);
+        if rules.errors.is_empty() {
+            Ok(())
+        } else {
+            Err(InventoryError::InvalidFields(rules.errors))
+        }
+    }
+}
+
+/// Collects the rules a payload breaks, keyed by field name.
+#[derive(Default)]
+pub struct Rules {
+    errors: Vec<FieldError>,
+}
+
+impl Rules {
+    pub fn check(&mut self, field: &str, ok: bool, message: &str) -> &mut Self {
+        if !ok {
+            self.errors.push(FieldError::new(field, message));
+        }
+        self
+    }
+
+    /// Record ID; an empty one is generated on create, but a given one ends
+    /// up in index keys and must be clean.
+    pub fn key(&mut self, field: &str, value: &str) -> &mut Self {
+        self.check(
+            field,
+            value == value.trim() && !value.chars().any(char::is_control),
+            "must not contain control characters or surrounding spaces"
+        )
+    }
+
+    pub fn required(&mut self, field: &str, value: &str) -> &mut Self {
+        self.check(field, !value.trim().is_empty(), "must not be empty")
+    }
+
+    pub fn positive(&mut self, field: &str, value: u64) -> &mut Self {
+        self.check(field, value > 0, "must be at least 1")
+    }
+
+    pub fn non_negative(&mut self, field: &str, value: f64) -> &mut Self {
+        self.check(field, value.is_finite() && value >= 0.0, "must be a non-negative number")
+    }
+
+    pub fn not_before(
+        &mut self,
+        field: &str,
+        date: NaiveDate,
+        earlier_field: &str,
+        earlier: NaiveDate
+    ) -> &mut Self {
+        if date < earlier {
+            self.errors.push(FieldError::new(field, format!("must not be before {}", earlier_field)));
+        }
+        self
+    }
+
+    /// Per-location stock; the keys are location IDs and end up in lookups
+    /// against the location store, so they are held to the same rules as IDs.
+    pub fn stock(&mut self, stock: &BTreeMap<String, u64>) -> &mut Self {
+        for location in stock.keys() {
+            let field = format!("stock.{}", location);
+            self.required(&field, location).key(&field, location);
+        }
+        self
+    }
+
+    /// Runs the rules of a nested record, prefixing its field names.
+    pub fn nested(&mut self, prefix: &str, item: &impl Validate) -> &mut Self {
+        let mut nested = Rules::default();
+        item.rules(&mut nested);
+        self.errors.extend(
+            nested.errors
+                .into_iter()
+                .map(|error| FieldError::new(&format!("{}.{}", prefix, error.field), error.message))
+        );
+        self
+    }
+}
+
+/// Declares a status enum stored and sent as its variant name, the string
+/// the baseline layout kept, so `migrate` converts old statuses through
+/// `TryFrom<String>`. Extra string literals are accepted as aliases when
+/// reading.
+macro_rules! string_status {
+    ($(#[$meta:meta])* $name:ident { $($variant:ident $(| $alias:literal)*),+ $(,)? }) => {
+        $(#[$meta])*
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+        #[serde(into = "String", try_from = "String")]
+        pub enum $name {
+            $($variant),+
+        }
+
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                match self {
+                    $($name::$variant => f.write_str(stringify!($variant))),+
+                }
+            }
+        }
+
+        impl From<$name> for String {
+            fn from(status: $name) -> String {
+                status.to_string()
+            }
+        }
+
+        impl TryFrom<String> for $name {
+            type Error = String;
+
+            fn try_from(value: String) -> Result<Self, String> {
+                match value.as_str() {
+                    $(stringify!($variant) $(| $alias)* => Ok($name::$variant),)+
+                    _ => Err(format!("unknown {} '{}'", stringify!($name), value)),
+                }
+            }
+        }
+    };
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum LocationKind {
+    #[default]
+    Warehouse,
+    Assembly,
+    Customer,
+    Scrap,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Locations {
+    #[serde(default)]
+    pub location_id: String,
+    pub location_name: String,
+    #[serde(default)]
+    pub kind: LocationKind,
+    #[serde(default = "default_active")]
+    pub active: bool,
+    pub address: Option<String>,
+    #[serde(default)]
+    pub version: u64,
+}
+
+impl Validate for Locations {
+    fn rules(&self, rules: &mut Rules) {
+        rules.key("location_id", &self.location_id).required("location_name", &self.location_name);
+    }
+}
+
+fn default_active() -> bool {
+    true
+}
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Products {
     pub product_name: String,
+    #[serde(default)]
     pub product_id: String,
+    /// Watch model whose BOM builds this product; the product ID is used
+    /// when unset.
+    #[serde(default)]
+    pub watch_model_id: Option<String>,
     pub components: Option<Vec<String>>,
-    pub cn: u64,
-    pub kling: u64,
-    pub st_jacob: u64,
-    pub wurenlos: u64,
+    pub stock: BTreeMap<String, u64>,
     pub wurenlos_sold: u64,
-    pub flf: u64,
+    #[serde(default)]
     pub in_transit: u64,
+    #[serde(default)]
+    pub on_hand: u64,
+    #[serde(default)]
     pub total_available: u64,
+    #[serde(default)]
+    pub free_to_promise: u64,
+    #[serde(default)]
     pub reserver_for_orders: u64,
     pub waste: u64,
     pub customer: u64,
+    #[serde(default)]
+    pub version: u64,
+}
+
+impl Validate for Products {
+    fn rules(&self, rules: &mut Rules) {
+        rules
+            .key("product_id", &self.product_id)
+            .required("product_name", &self.product_name)
+            .stock(&self.stock);
+        for (i, component) in self.components.iter().flatten().enumerate() {
+            rules.required(&format!("components[{}]", i), component);
+        }
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -24,53 +216,448 @@ pub struct Components {
     pub product_id: String,
     pub product_name: String,
     pub component_name: String,
+    #[serde(default)]
     pub component_id: String,
-    pub cn: u64,
-    pub kling: u64,
-    pub st_jacob: u64,
-    pub wurenlos: u64,
+    pub stock: BTreeMap<String, u64>,
     pub wurenlos_sold: u64,
-    pub flf: u64,
+    #[serde(default)]
     pub in_transit: u64,
+    #[serde(default)]
+    pub on_hand: u64,
+    #[serde(default)]
     pub total_available: u64,
+    #[serde(default)]
+    pub free_to_promise: u64,
     pub ordered_surplus: f64,
+    #[serde(default)]
     pub reserver_for_orders: u64,
     pub waste: u64,
     pub customer: u64,
     pub assembly_line: u64,
+    #[serde(default)]
+    pub version: u64,
+}
+
+impl Validate for Components {
+    fn rules(&self, rules: &mut Rules) {
+        rules
+            .key("component_id", &self.component_id)
+            .required("component_name", &self.component_name)
+            .non_negative("ordered_surplus", self.ordered_surplus)
+            .stock(&self.stock);
+    }
+}
+
+/// Stored records carry a version that every write bumps, so a client can
+/// tell whether the copy it edited is still the current one.
+pub trait Versioned {
+    fn version(&self) -> u64;
+
+    fn set_version(&mut self, version: u64);
+}
+
+macro_rules! impl_versioned {
+    ($($item:ty),+ $(,)?) => {
+        $(
+            impl Versioned for $item {
+                fn version(&self) -> u64 {
+                    self.version
+                }
+
+                fn set_version(&mut self, version: u64) {
+                    self.version = version;
+                }
+            }
+        )+
+    };
+}
+
+impl_versioned!(
+    Locations,
+    Products,
+    Components,
+    Movements,
+    Suppliers,
+    CatalogueItem,
+    SuppliersOrders,
+    Orders,
+    Procurements,
+    AssemblyTimeLine,
+    ProductionRate,
+    RecorderPoint,
+    Watches,
+);
+
+/// Per-location stock bookkeeping shared by products and components.
+pub trait StockItem {
+    fn item_id(&self) -> &str;
+
+    fn in_transit_mut(&mut self) -> &mut u64;
+
+    /// Quantity slot a location maps to: stock-holding sites index `stock`
+    /// by location ID, customer and scrap sites feed the running counters.
+    fn quantity_at_mut(&mut self, location: &Locations) -> &mut u64;
+
+    fn quantity_at(&self, location: &Locations) -> u64;
+
+    /// Recomputes the derived figures from per-location stock:
+    /// `on_hand` is everything held at stock-holding sites, `total_available`
+    /// is what is left once order reservations are taken out, and
+    /// `free_to_promise` additionally counts stock already in transit.
+    fn refresh_totals(&mut self);
+
+    /// Takes the server-maintained counters (`in_transit`,
+    /// `reserver_for_orders`, and `assembly_line` for components) from the
+    /// stored record, so client payloads can never set them.
+    fn keep_bookkeeping(&mut self, stored: Option<&Self>) where Self: Sized;
+}
+
+macro_rules! impl_stock_item {
+    ($item:ty, $id:ident $(, $counter:ident)*) => {
+        impl StockItem for $item {
+            fn item_id(&self) -> &str {
+                &self.$id
+            }
+
+            fn in_transit_mut(&mut self) -> &mut u64 {
+                &mut self.in_transit
+            }
+
+            fn quantity_at_mut(&mut self, location: &Locations) -> &mut u64 {
+                match location.kind {
+                    LocationKind::Customer => &mut self.customer,
+                    LocationKind::Scrap => &mut self.waste,
+                    _ => self.stock.entry(location.location_id.clone()).or_insert(0),
+                }
+            }
+
+            fn quantity_at(&self, location: &Locations) -> u64 {
+                match location.kind {
+                    LocationKind::Customer => self.customer,
+                    LocationKind::Scrap => self.waste,
+                    _ => self.stock.get(&location.location_id).copied().unwrap_or(0),
+                }
+            }
+
+            fn refresh_totals(&mut self) {
+                self.on_hand = self.stock.values().sum();
+                self.total_available = self.on_hand.saturating_sub(self.reserver_for_orders);
+                self.free_to_promise = (self.on_hand + self.in_transit).saturating_sub(
+                    self.reserver_for_orders
+                );
+            }
+
+            fn keep_bookkeeping(&mut self, stored: Option<&Self>) {
+                self.in_transit = stored.map_or(0, |stored| stored.in_transit);
+                self.reserver_for_orders = stored.map_or(0, |stored| stored.reserver_for_orders);
+                $(self.$counter = stored.map_or(0, |stored| stored.$counter);)*
+            }
+        }
+    };
+}
+
+impl_stock_item!(Products, product_id);
+impl_stock_item!(Components, component_id, assembly_line);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MovementType {
+    Component,
+    Product,
+}
+
+/// `Completed` movements are applied in one step. `Dispatched` ones take
+/// stock out of the source into `in_transit` until they are received or
+/// cancelled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MovementStatus {
+    Completed,
+    Dispatched,
+    PartiallyReceived,
+    Received,
+    Cancelled,
+}
+
+impl Status for MovementStatus {
+    fn can_become(self, next: Self) -> bool {
+        use MovementStatus::*;
+        matches!(
+            (self, next),
+            (Dispatched | PartiallyReceived, PartiallyReceived | Received | Cancelled)
+        )
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MovementReceipt {
+    pub date: NaiveDate,
+    pub quantity: u64,
+    pub notes: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Movements {
+    #[serde(default)]
     pub movement_id: String,
     pub transaction_id: String,
     pub date: NaiveDate,
-    pub movement_type: String,
+    pub movement_type: MovementType,
+    #[serde(default)]
+    pub component_id: Option<String>,
     pub component_name: Option<String>,
+    #[serde(default)]
+    pub product_id: Option<String>,
     pub product_name: Option<String>,
     pub source_location: String,
     pub destination_location: String,
     pub quantity: u64,
     pub notes: Option<String>,
-    pub status: String,
+    pub status: MovementStatus,
     pub supplier_order_id: Option<String>,
+    #[serde(default)]
+    pub received_quantity: u64,
+    #[serde(default)]
+    pub returned_quantity: u64,
+    #[serde(default)]
+    pub receipts: Vec<MovementReceipt>,
+    #[serde(default)]
+    pub version: u64,
+}
+
+impl Validate for Movements {
+    fn rules(&self, rules: &mut Rules) {
+        rules
+            .key("movement_id", &self.movement_id)
+            .required("source_location", &self.source_location)
+            .required("destination_location", &self.destination_location)
+            .positive("quantity", self.quantity);
+    }
+}
+
+impl Movements {
+    /// Quantity dispatched but neither received nor returned yet.
+    pub fn outstanding(&self) -> u64 {
+        self.quantity.saturating_sub(self.received_quantity).saturating_sub(self.returned_quantity)
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Suppliers {
+    #[serde(default)]
+    pub supplier_id: String,
+    pub supplier_name: String,
+    pub contact_name: Option<String>,
+    pub email: Option<String>,
+    pub phone: Option<String>,
+    pub address: Option<String>,
+    /// ISO 4217 code the supplier invoices in, e.g. "CHF".
+    pub currency: String,
+    /// Days from order to delivery when a supplier order gives no date.
+    pub default_lead_time: u64,
+    #[serde(default)]
+    pub version: u64,
+}
+
+impl Validate for Suppliers {
+    fn rules(&self, rules: &mut Rules) {
+        rules
+            .key("supplier_id", &self.supplier_id)
+            .required("supplier_name", &self.supplier_name)
+            .check(
+                "currency",
+                self.currency.len() == 3 && self.currency.bytes().all(|b| b.is_ascii_uppercase()),
+                "must be an ISO 4217 code"
+            );
+    }
+}
+
+/// Unit price that applies from `min_quantity` units upwards.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PriceBreak {
+    pub min_quantity: u64,
+    pub unit_price: f64,
+}
+
+impl Validate for PriceBreak {
+    fn rules(&self, rules: &mut Rules) {
+        rules.non_negative("unit_price", self.unit_price);
+    }
+}
+
+/// A component as a supplier sells it. Stored per supplier and component.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CatalogueItem {
+    pub supplier_id: String,
+    pub component_id: String,
+    pub supplier_sku: String,
+    /// Sorted by `min_quantity` when stored.
+    pub price_breaks: Vec<PriceBreak>,
+    /// Minimum order quantity.
+    pub moq: u64,
+    pub pack_size: u64,
+    #[serde(default)]
+    pub version: u64,
+}
+
+impl Validate for CatalogueItem {
+    fn rules(&self, rules: &mut Rules) {
+        rules
+            .required("supplier_id", &self.supplier_id)
+            .required("component_id", &self.component_id)
+            .positive("pack_size", self.pack_size)
+            .check("price_breaks", !self.price_breaks.is_empty(), "at least one price break is required");
+        for (i, price_break) in self.price_breaks.iter().enumerate() {
+            rules.nested(&format!("price_breaks[{}]", i), price_break);
+        }
+        let mut quantities: Vec<u64> = self.price_breaks
+            .iter()
+            .map(|price_break| price_break.min_quantity)
+            .collect();
+        quantities.sort_unstable();
+        rules.check(
+            "price_breaks",
+            quantities.windows(2).all(|pair| pair[0] != pair[1]),
+            "price breaks must have distinct quantities"
+        );
+    }
+}
+
+impl CatalogueItem {
+    /// Smallest quantity covering `required` that the supplier accepts: at
+    /// least the MOQ and a whole number of packs.
+    pub fn order_quantity(&self, required: u64) -> u64 {
+        let pack_size = self.pack_size.max(1);
+        required.max(self.moq).div_ceil(pack_size) * pack_size
+    }
+
+    /// Unit price of the highest break `quantity` reaches; quantities below
+    /// every break pay the first one.
+    pub fn unit_price(&self, quantity: u64) -> Option<f64> {
+        self.price_breaks
+            .iter()
+            .rev()
+            .find(|price_break| price_break.min_quantity <= quantity)
+            .or(self.price_breaks.first())
+            .map(|price_break| price_break.unit_price)
+    }
+}
+
+string_status! {
+    /// `Draft` orders are proposals; `Pending` ones have been placed with the
+    /// supplier. The received states are reached through receipts only.
+    SupplierOrderStatus {
+        Draft,
+        Pending,
+        PartiallyReceived,
+        Received,
+        Cancelled,
+    }
+}
+
+impl Status for SupplierOrderStatus {
+    fn can_become(self, next: Self) -> bool {
+        use SupplierOrderStatus::*;
+        matches!(
+            (self, next),
+            (Draft, Pending | Cancelled) |
+                (Pending | PartiallyReceived, PartiallyReceived | Received | Cancelled)
+        )
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct SuppliersOrders {
     pub supplier_id: String,
+    #[serde(default)]
+    pub component_id: String,
     pub component_name: String,
     pub procurement_id: String,
+    #[serde(default)]
     pub order_id: String,
     pub total_components_required: u64,
+    /// Quantity actually ordered, rounded to the supplier's MOQ and pack size.
     pub components_roundof: u64,
-    pub status: String,
+    pub status: SupplierOrderStatus,
     pub order_date: NaiveDate,
     pub expected_delivery_date: NaiveDate,
+    /// Pricing is taken from the supplier catalogue when the order is stored.
+    #[serde(default)]
+    pub supplier_sku: String,
+    #[serde(default)]
+    pub unit_price: f64,
+    #[serde(default)]
+    pub total_price: f64,
+    #[serde(default)]
+    pub currency: String,
+    /// Booked through supplier order receipts; client payloads cannot set it.
+    #[serde(default)]
+    pub received_quantity: u64,
+    /// Receipt movements booked against this order, oldest first.
+    #[serde(default)]
+    pub movements: Vec<String>,
+    #[serde(default)]
+    pub version: u64,
+}
+
+impl Validate for SuppliersOrders {
+    fn rules(&self, rules: &mut Rules) {
+        rules
+            .key("order_id", &self.order_id)
+            .required("supplier_id", &self.supplier_id)
+            .check(
+                "component_id",
+                !self.component_id.trim().is_empty() || !self.component_name.trim().is_empty(),
+                "component_id or component_name is required"
+            )
+            .positive("total_components_required", self.total_components_required)
+            .not_before("expected_delivery_date", self.expected_delivery_date, "order_date", self.order_date);
+    }
+}
+
+impl SuppliersOrders {
+    /// Ordered quantity not delivered yet.
+    pub fn outstanding(&self) -> u64 {
+        self.components_roundof.saturating_sub(self.received_quantity)
+    }
+
+    /// Takes the receipt bookkeeping from the stored record.
+    pub fn keep_receipts(&mut self, stored: Option<&SuppliersOrders>) {
+        self.received_quantity = stored.map_or(0, |stored| stored.received_quantity);
+        self.movements = stored.map_or_else(Vec::new, |stored| stored.movements.clone());
+    }
+}
+
+string_status! {
+    OrderStatus {
+        Pending,
+        Processing,
+        Shipped,
+        Completed,
+        Cancelled,
+    }
+}
+
+impl OrderStatus {
+    /// Still to be built and shipped.
+    pub fn is_open(self) -> bool {
+        matches!(self, OrderStatus::Pending | OrderStatus::Processing)
+    }
+}
+
+impl Status for OrderStatus {
+    fn can_become(self, next: Self) -> bool {
+        use OrderStatus::*;
+        matches!(
+            (self, next),
+            (Pending, Processing | Cancelled) |
+                (Processing, Shipped | Cancelled) |
+                (Shipped, Completed)
+        )
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Orders {
+    #[serde(default)]
     pub order_id: String,
     pub procurements: Option<Vec<String>>,
     pub supplier_orders: Option<Vec<String>>,
@@ -82,12 +669,78 @@ pub struct Orders {
     pub production_start_date: NaiveDate,
     pub expected_ship_date: NaiveDate,
     pub recid: String,
-    pub order_status: String,
+    pub order_status: OrderStatus,
     pub total_components_booked: u64,
     pub components_notes: Option<String>,
     pub components_required: u64,
     pub total_gap_components: Option<Vec<u64>>,
     pub components: Option<Vec<String>>,
+    /// Quantity reserved per entry of `components`, alongside the matching
+    /// `total_gap_components` entry.
+    #[serde(default)]
+    pub components_booked: Option<Vec<u64>>,
+    #[serde(default)]
+    pub version: u64,
+}
+
+impl Validate for Orders {
+    fn rules(&self, rules: &mut Rules) {
+        rules
+            .key("order_id", &self.order_id)
+            .required("product_id", &self.product_id)
+            .positive("quantity_required", self.quantity_required)
+            .not_before(
+                "expected_ship_date",
+                self.expected_ship_date,
+                "production_start_date",
+                self.production_start_date
+            )
+            .not_before(
+                "expected_delivery_date",
+                self.expected_delivery_date,
+                "expected_ship_date",
+                self.expected_ship_date
+            );
+    }
+}
+
+impl Orders {
+    /// Takes the reservation figures from the stored record; only the
+    /// reservation engine writes them.
+    pub fn keep_reservation(&mut self, stored: Option<&Orders>) {
+        match stored {
+            Some(stored) => {
+                self.components = stored.components.clone();
+                self.components_booked = stored.components_booked.clone();
+                self.total_gap_components = stored.total_gap_components.clone();
+                self.total_components_booked = stored.total_components_booked;
+                self.components_required = stored.components_required;
+            }
+            None => {
+                self.components = None;
+                self.components_booked = None;
+                self.total_gap_components = None;
+                self.total_components_booked = 0;
+                self.components_required = 0;
+            }
+        }
+    }
+}
+
+string_status! {
+    ProcurementStatus {
+        Pending,
+        Ordered,
+        Received,
+        Cancelled,
+    }
+}
+
+impl Status for ProcurementStatus {
+    fn can_become(self, next: Self) -> bool {
+        use ProcurementStatus::*;
+        matches!((self, next), (Pending, Ordered | Cancelled) | (Ordered, Received | Cancelled))
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -96,19 +749,63 @@ pub struct Procurement {
     pub order_id: String,
     pub components: Option<Vec<String>>,
     pub quantity: u64,
-    pub status: String,
+    pub status: ProcurementStatus,
     pub product: String,
 }
 
+impl Validate for Procurement {
+    fn rules(&self, rules: &mut Rules) {
+        rules.required("procurement_id", &self.procurement_id).positive("quantity", self.quantity);
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Procurements {
+    #[serde(default)]
     pub procurement_id: String,
     pub order_id: String,
     pub procurements: Vec<Procurement>,
+    #[serde(default)]
+    pub version: u64,
+}
+
+impl Validate for Procurements {
+    fn rules(&self, rules: &mut Rules) {
+        rules.key("procurement_id", &self.procurement_id).required("order_id", &self.order_id);
+        for (i, line) in self.procurements.iter().enumerate() {
+            rules.nested(&format!("procurements[{}]", i), line);
+        }
+    }
+}
+
+string_status! {
+    /// `Planned` timelines are scheduler proposals. Starting and completing
+    /// an assembly moves stock, so `InProgress` and `Completed` are only
+    /// reached through those endpoints.
+    AssemblyStatus {
+        Planned,
+        Scheduled,
+        InProgress | "In Progress",
+        Completed,
+        Cancelled,
+    }
+}
+
+impl Status for AssemblyStatus {
+    fn can_become(self, next: Self) -> bool {
+        use AssemblyStatus::*;
+        matches!(
+            (self, next),
+            (Planned, Scheduled | InProgress | Cancelled) |
+                (Scheduled, Planned | InProgress | Cancelled) |
+                (InProgress, Completed)
+        )
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct AssemblyTimeLine {
+    #[serde(default)]
     pub assembly_id: String,
     pub order: String,
     pub product: String,
@@ -121,36 +818,277 @@ pub struct AssemblyTimeLine {
     pub components_received_date: NaiveDate,
     pub assembly_start_date: NaiveDate,
     pub assembly_end_date: NaiveDate,
-    pub assembly_status: String,
+    pub assembly_status: AssemblyStatus,
+    /// Working days from start to end, both included.
     pub total_duration: u64,
     pub assembly_notes: Option<String>,
+    /// Watches to assemble.
+    #[serde(default)]
+    pub quantity: u64,
+    /// Watches finished when the assembly was completed.
+    #[serde(default)]
+    pub produced_quantity: u64,
+    /// Components scrapped during assembly, by component ID.
+    #[serde(default)]
+    pub scrapped: BTreeMap<String, u64>,
+    #[serde(default)]
+    pub version: u64,
+}
+
+impl Validate for AssemblyTimeLine {
+    fn rules(&self, rules: &mut Rules) {
+        rules
+            .key("assembly_id", &self.assembly_id)
+            .required("order", &self.order)
+            .required("assembly_location", &self.assembly_location)
+            .not_before(
+                "assembly_end_date",
+                self.assembly_end_date,
+                "assembly_start_date",
+                self.assembly_start_date
+            );
+    }
+}
+
+impl AssemblyTimeLine {
+    /// Drops the execution bookkeeping a client sent with a timeline that is
+    /// new to the store; only starting and completing it fill that in.
+    pub fn reset_execution(&mut self) {
+        self.assembly_status = AssemblyStatus::Planned;
+        self.movements.clear();
+        self.produced_quantity = 0;
+        self.scrapped.clear();
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct ProductionRate {
+    #[serde(default)]
     pub prodction_rate_id: String,
     pub watch_model_id: String,
     pub assembly_time_per_watch: u64,
     pub daily_production_capacity: u64,
+    #[serde(default)]
+    pub version: u64,
+}
+
+impl Validate for ProductionRate {
+    fn rules(&self, rules: &mut Rules) {
+        rules
+            .key("prodction_rate_id", &self.prodction_rate_id)
+            .required("watch_model_id", &self.watch_model_id)
+            .positive("daily_production_capacity", self.daily_production_capacity);
+    }
 }
 
+/// Reorder point of a component. Usage figures are computed from movement
+/// history; `need_to_order` follows the component's free-to-promise stock.
 #[derive(Debug, Serialize, Deserialize)]
 pub struct RecorderPoint {
+    #[serde(default)]
     pub recorder_point_id: String,
+    /// Resolved from `component_name` when not given.
+    #[serde(default)]
+    pub component_id: String,
     pub component_name: String,
+    /// Supplier lead time in days.
     pub supplier_lead_time: u64,
     pub assumed_daily_usage: f64,
+    /// Standard deviation of daily usage over the same window.
+    #[serde(default)]
+    pub daily_usage_std_dev: f64,
     pub lead_time_demand: f64,
     pub safety_stock: f64,
     pub reorder_point: u64,
     pub need_to_order: bool,
+    /// Day the usage figures were last computed; unset for hand-entered ones.
+    #[serde(default)]
+    pub last_computed: Option<NaiveDate>,
+    #[serde(default)]
+    pub version: u64,
+}
+
+impl Validate for RecorderPoint {
+    fn rules(&self, rules: &mut Rules) {
+        rules
+            .key("recorder_point_id", &self.recorder_point_id)
+            .check(
+                "component_id",
+                !self.component_id.trim().is_empty() || !self.component_name.trim().is_empty(),
+                "component_id or component_name is required"
+            )
+            .non_negative("assumed_daily_usage", self.assumed_daily_usage)
+            .non_negative("daily_usage_std_dev", self.daily_usage_std_dev)
+            .non_negative("lead_time_demand", self.lead_time_demand)
+            .non_negative("safety_stock", self.safety_stock);
+    }
+}
+
+impl RecorderPoint {
+    pub fn applies_to(&self, component: &Components) -> bool {
+        if self.component_id.is_empty() {
+            self.component_name == component.component_name
+        } else {
+            self.component_id == component.component_id
+        }
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Watches {
+    #[serde(default)]
     pub watch_id: String,
     pub watch_model_id: String,
     pub brand: String,
     pub component_id: String,
     pub required_quantity: u64,
+    #[serde(default)]
+    pub version: u64,
+}
+
+impl Validate for Watches {
+    fn rules(&self, rules: &mut Rules) {
+        rules
+            .key("watch_id", &self.watch_id)
+            .required("watch_model_id", &self.watch_model_id)
+            .required("component_id", &self.component_id)
+            .positive("required_quantity", self.required_quantity);
+    }
+}
+
+/// One component of an exploded BOM, set against current stock.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BomRequirement {
+    pub component_id: String,
+    pub component_name: Option<String>,
+    /// Shallowest BOM level the component appears at; 1 is a direct line.
+    pub level: u32,
+    pub sub_assembly: bool,
+    pub required_quantity: u64,
+    pub available: BTreeMap<String, u64>,
+    pub total_available: u64,
+    pub shortfall: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BomExplosion {
+    pub watch_model_id: String,
+    pub quantity: u64,
+    pub components: Vec<BomRequirement>,
+}
+
+/// Result of turning an order's component gaps into procurement lines and
+/// draft supplier orders.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ProcurementPlan {
+    pub procurements: Procurements,
+    pub supplier_orders: Vec<SuppliersOrders>,
+}
+
+/// An order the scheduler finishes after its `expected_ship_date`.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LateOrder {
+    pub order_id: String,
+    pub expected_ship_date: NaiveDate,
+    pub assembly_end_date: NaiveDate,
+    pub days_late: i64,
+}
+
+/// An open order the scheduler could not place, and why.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UnscheduledOrder {
+    pub order_id: String,
+    pub reason: String,
+}
+
+/// Assembly plan proposed for the open orders.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AssemblySchedule {
+    pub timelines: Vec<AssemblyTimeLine>,
+    pub late_orders: Vec<LateOrder>,
+    pub unscheduled: Vec<UnscheduledOrder>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SortOrder {
+    #[default]
+    Asc,
+    Desc,
+}
+
+/// One page of a collection in key order. `cursor` is the last key of the
+/// previous page; the page starts right after it.
+#[derive(Debug)]
+pub struct PageRequest {
+    pub cursor: Option<String>,
+    pub limit: usize,
+    pub order: SortOrder,
+}
+
+#[derive(Debug)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    /// Cursor for the following page; unset on the last one.
+    pub next_cursor: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct ProductFilter {
+    /// Only items with stock at this location.
+    pub location: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct ComponentFilter {
+    pub product: Option<String>,
+    /// Only items with stock at this location.
+    pub location: Option<String>,
+}
+
+/// Date bounds are inclusive and apply to the movement date.
+#[derive(Debug, Default, Deserialize)]
+pub struct MovementFilter {
+    pub status: Option<MovementStatus>,
+    pub movement_type: Option<MovementType>,
+    pub from: Option<NaiveDate>,
+    pub to: Option<NaiveDate>,
+    /// Matches either end of the movement.
+    pub location: Option<String>,
+    pub product: Option<String>,
+    pub component: Option<String>,
+}
+
+/// Date bounds are inclusive and apply to `expected_ship_date`.
+#[derive(Debug, Default, Deserialize)]
+pub struct OrderFilter {
+    pub status: Option<OrderStatus>,
+    pub product: Option<String>,
+    pub from: Option<NaiveDate>,
+    pub to: Option<NaiveDate>,
+}
+
+/// Date bounds are inclusive and apply to `order_date`.
+#[derive(Debug, Default, Deserialize)]
+pub struct SupplierOrderFilter {
+    pub status: Option<SupplierOrderStatus>,
+    pub supplier: Option<String>,
+    pub component: Option<String>,
+    pub from: Option<NaiveDate>,
+    pub to: Option<NaiveDate>,
+}
+
+/// Date bounds are inclusive and apply to `assembly_start_date`.
+#[derive(Debug, Default, Deserialize)]
+pub struct AssemblyFilter {
+    pub status: Option<AssemblyStatus>,
+    pub location: Option<String>,
+    pub order: Option<String>,
+    pub from: Option<NaiveDate>,
+    pub to: Option<NaiveDate>,
+}
+
+/// True when `date` lies within the optional inclusive bounds.
+pub fn within(date: NaiveDate, from: Option<NaiveDate>, to: Option<NaiveDate>) -> bool {
+    from.is_none_or(|from| date >= from) && to.is_none_or(|to| date <= to)
 }
@@ -0,0 +1,974 @@
+use super::*;
+use std::sync::atomic::{ AtomicUsize, Ordering };
+
+const MAP_SIZE: usize = 1 << 30;
+
+/// Database directory under the system temp dir, removed again on drop.
+struct TempDir {
+    path: PathBuf,
+}
+
+impl TempDir {
+    fn new(name: &str) -> Self {
+        static COUNTER: AtomicUsize = AtomicUsize::new(0);
+        let path = std::env::temp_dir().join(
+            format!("inventory-test-{}-{}-{}", std::process::id(), COUNTER.fetch_add(1, Ordering::Relaxed), name)
+        );
+        std::fs::create_dir_all(&path).unwrap();
+        TempDir { path }
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.path);
+    }
+}
+
+/// Fresh database loaded with the sample data set. Keep the directory alive
+/// as long as the database.
+fn seeded(name: &str) -> (InventoryDB, TempDir) {
+    let dir = TempDir::new(name);
+    let db = InventoryDB::new(&dir.path, MAP_SIZE).unwrap();
+    db.initialize_sample_data(false).unwrap();
+    (db, dir)
+}
+
+// ========== SCHEMA MIGRATION ==========
+
+#[test]
+fn baseline_database_is_refused_until_migrated() {
+    let dir = TempDir::new("baseline");
+    let baseline = Path::new(env!("CARGO_MANIFEST_DIR")).join("inventory_db/data.mdb");
+    std::fs::copy(baseline, dir.path.join("data.mdb")).unwrap();
+
+    assert!(matches!(InventoryDB::new(&dir.path, MAP_SIZE), Err(InventoryError::Conflict(_))));
+    assert_eq!(InventoryDB::migrate(&dir.path, MAP_SIZE).unwrap(), 11);
+    assert_eq!(InventoryDB::migrate(&dir.path, MAP_SIZE).unwrap(), 0);
+
+    let db = InventoryDB::new(&dir.path, MAP_SIZE).unwrap();
+    let product = db.get_product("PROD-001").unwrap().unwrap();
+    assert_eq!(product.stock["ST-JAKOB"], 75);
+    assert_eq!(product.on_hand, 575);
+    assert_eq!(product.version, 1);
+
+    let order = db.get_order("ORD-001").unwrap().unwrap();
+    assert_eq!(order.order_status, OrderStatus::Processing);
+    let movement = db.get_movement("MOVE-001").unwrap().unwrap();
+    assert_eq!(movement.status, MovementStatus::Completed);
+    assert_eq!(movement.component_id.as_deref(), Some("COMP-001"));
+    let timeline = db.with_read_txn(|rtxn| db.assembly_timeline_db.get(rtxn, "ASSEM-001")).unwrap().unwrap();
+    assert_eq!(timeline.assembly_status, AssemblyStatus::Scheduled);
+    assert_eq!(timeline.quantity, 50);
+    assert!(db.get_location("WURENLOS").unwrap().is_some());
+}
+
+#[test]
+fn empty_database_opens_at_current_schema() {
+    let (db, _dir) = seeded("fresh");
+    let version = db.with_read_txn(|rtxn| db.meta_db.get(rtxn, SCHEMA_VERSION_KEY)).unwrap();
+    assert_eq!(version, Some(SCHEMA_VERSION));
+}
+
+// ========== NAME INDEX ==========
+
+fn product(id: &str, name: &str) -> Products {
+    serde_json::from_value(
+        serde_json::json!({
+            "product_id": id,
+            "product_name": name,
+            "components": null,
+            "stock": {},
+            "wurenlos_sold": 0,
+            "waste": 0,
+            "customer": 0,
+        })
+    ).unwrap()
+}
+
+#[test]
+fn duplicate_product_names_are_rejected() {
+    let (db, _dir) = seeded("product-names");
+    assert!(matches!(db.create_product(product("PROD-002", "BP Watch")), Err(InventoryError::Conflict(_))));
+
+    db.create_product(product("PROD-002", "Diver")).unwrap();
+    assert!(matches!(db.update_product(product("PROD-002", "BP Watch"), None), Err(InventoryError::Conflict(_))));
+    let patch = serde_json::json!({ "product_name": "BP Watch" });
+    assert!(matches!(db.patch_product("PROD-002", &patch, None), Err(InventoryError::Conflict(_))));
+    // The rejected rename left the index entry of PROD-001 in place.
+    let owner = db.with_read_txn(|rtxn| Ok(db.product_names_db.get(rtxn, "BP Watch")?.map(str::to_string)));
+    assert_eq!(owner.unwrap().as_deref(), Some("PROD-001"));
+}
+
+#[test]
+fn renamed_product_frees_its_old_name() {
+    let (db, _dir) = seeded("product-rename");
+    db.create_product(product("PROD-002", "Diver")).unwrap();
+    db.update_product(product("PROD-002", "Diver II"), None).unwrap();
+    db.create_product(product("PROD-003", "Diver")).unwrap();
+    assert!(db.delete_product("PROD-002", None).unwrap());
+
+    let names = db.with_read_txn(|rtxn| {
+        Ok([
+            db.product_names_db.get(rtxn, "Diver")?.map(str::to_string),
+            db.product_names_db.get(rtxn, "Diver II")?.map(str::to_string),
+        ])
+    });
+    assert_eq!(names.unwrap(), [Some("PROD-003".to_string()), None]);
+}
+
+#[test]
+fn duplicate_component_names_are_rejected() {
+    let (db, _dir) = seeded("component-names");
+    let mut component = db.get_component("COMP-002").unwrap().unwrap();
+    component.component_name = "Premium Dial".to_string();
+    assert!(matches!(db.update_component(component, None), Err(InventoryError::Conflict(_))));
+}
+
+// ========== REFERENTIAL INTEGRITY ==========
+
+#[test]
+fn locations_in_use_cannot_be_deleted() {
+    let (db, _dir) = seeded("location-delete");
+    let Err(InventoryError::Referenced { referrers, .. }) = db.delete_location("WURENLOS", None) else {
+        panic!("a location holding stock was deleted");
+    };
+    assert!(referrers.contains(&"component 'COMP-001'".to_string()));
+    assert!(referrers.contains(&"assembly timeline 'ASSEM-001'".to_string()));
+
+    let spare = Locations {
+        location_id: "SPARE".to_string(),
+        location_name: "Spare Shelf".to_string(),
+        kind: LocationKind::Warehouse,
+        active: true,
+        address: None,
+        version: 0,
+    };
+    db.create_location(spare).unwrap();
+    assert!(db.delete_location("SPARE", None).unwrap());
+}
+
+#[test]
+fn locations_in_use_keep_their_kind() {
+    let (db, _dir) = seeded("location-kind");
+    let scrap = serde_json::json!({ "kind": "Scrap" });
+    assert!(matches!(db.patch_location("WURENLOS", &scrap, None), Err(InventoryError::Referenced { .. })));
+    let edited = db.patch_location("WURENLOS", &serde_json::json!({ "address": "Wurenlos AG" }), None).unwrap();
+    assert_eq!(edited.kind, LocationKind::Assembly);
+
+    // Nothing has been kept at the sample scrap location yet.
+    let mut unused = db.get_location("SCRAP").unwrap().unwrap();
+    unused.kind = LocationKind::Warehouse;
+    assert_eq!(db.update_location(unused, None).unwrap().kind, LocationKind::Warehouse);
+}
+
+#[test]
+fn linking_components_checks_both_ends() {
+    let (db, _dir) = seeded("link");
+    assert!(matches!(db.add_component_to_product("PROD-404", "COMP-001"), Err(InventoryError::NotFound(_))));
+    assert!(
+        matches!(
+            db.add_component_to_product("PROD-001", "COMP-404"),
+            Err(InventoryError::UnknownReference { .. })
+        )
+    );
+
+    let version = db.get_product("PROD-001").unwrap().unwrap().version;
+    db.add_component_to_product("PROD-001", "COMP-001").unwrap();
+    db.add_component_to_product("PROD-001", "COMP-001").unwrap();
+    let product = db.get_product("PROD-001").unwrap().unwrap();
+    assert_eq!(product.components.unwrap().iter().filter(|c| *c == "COMP-001").count(), 1);
+    assert!(product.version > version);
+}
+
+#[test]
+fn stock_must_be_kept_at_registered_locations() {
+    let (db, _dir) = seeded("stock-keys");
+    let fields = |result: Result<Products, InventoryError>| match result {
+        Err(InventoryError::InvalidFields(errors)) => errors.into_iter().map(|e| e.field).collect::<Vec<_>>(),
+        other => panic!("expected field errors, got {:?}", other),
+    };
+
+    let mut stray = product("PROD-002", "Diver");
+    stray.stock = BTreeMap::from([("WURENLOS".to_string(), 5), ("MARS".to_string(), 1), ("CUSTOMER".to_string(), 2)]);
+    assert_eq!(fields(db.create_product(stray)), ["stock.CUSTOMER", "stock.MARS"]);
+
+    let mut padded = product("PROD-002", "Diver");
+    padded.stock = BTreeMap::from([(" WURENLOS".to_string(), 5)]);
+    assert_eq!(fields(db.create_product(padded)), ["stock. WURENLOS"]);
+
+    let patch = serde_json::json!({ "stock": { "MARS": 3 } });
+    assert!(matches!(db.patch_component("COMP-001", &patch, None), Err(InventoryError::InvalidFields(_))));
+    assert_eq!(stock_at(&db, "COMP-001", "MARS"), 0);
+}
+
+#[test]
+fn cancelled_movements_return_to_deactivated_sources() {
+    let (db, _dir) = seeded("cancel-inactive");
+    let mut movement = db.get_movement("MOVE-001").unwrap().unwrap();
+    movement.movement_id = String::new();
+    movement.status = MovementStatus::Dispatched;
+    let dispatched = db.record_movement(movement).unwrap();
+    let left = stock_at(&db, "COMP-001", "CN");
+
+    db.patch_location("CN", &serde_json::json!({ "active": false }), None).unwrap();
+    let cancelled = db.cancel_movement(&dispatched.movement_id, None).unwrap();
+    assert_eq!(cancelled.status, MovementStatus::Cancelled);
+    assert_eq!(stock_at(&db, "COMP-001", "CN"), left + dispatched.quantity);
+}
+
+// ========== RESERVATIONS ==========
+
+fn reserved(db: &InventoryDB, component_id: &str) -> u64 {
+    db.get_component(component_id).unwrap().unwrap().reserver_for_orders
+}
+
+#[test]
+fn release_and_reserve_again_restores_the_booking() {
+    let (db, _dir) = seeded("reserve");
+    assert_eq!((reserved(&db, "COMP-001"), reserved(&db, "COMP-002")), (50, 100));
+
+    let order = db.release_order("ORD-001").unwrap();
+    assert_eq!(order.components_booked, None);
+    assert_eq!((reserved(&db, "COMP-001"), reserved(&db, "COMP-002")), (0, 0));
+    db.release_order("ORD-001").unwrap();
+    assert_eq!(reserved(&db, "COMP-001"), 0);
+
+    let order = db.reserve_order("ORD-001").unwrap();
+    assert_eq!(order.components_booked, Some(vec![50, 100]));
+    assert_eq!(order.total_gap_components, Some(vec![0, 0]));
+    // Reserving again replaces the booking instead of adding to it.
+    db.reserve_order("ORD-001").unwrap();
+    assert_eq!((reserved(&db, "COMP-001"), reserved(&db, "COMP-002")), (50, 100));
+}
+
+#[test]
+fn cancelling_releases_and_blocks_new_reservations() {
+    let (db, _dir) = seeded("reserve-cancelled");
+    let order = db.transition_order("ORD-001", OrderStatus::Cancelled).unwrap();
+    assert_eq!(order.components_booked, None);
+    assert_eq!((reserved(&db, "COMP-001"), reserved(&db, "COMP-002")), (0, 0));
+
+    assert!(matches!(db.reserve_order("ORD-001"), Err(InventoryError::Conflict(_))));
+    assert_eq!(reserved(&db, "COMP-001"), 0);
+    assert!(matches!(db.reserve_order("ORD-404"), Err(InventoryError::NotFound(_))));
+}
+
+#[test]
+fn shortfalls_are_recorded_as_gaps() {
+    let (db, _dir) = seeded("reserve-gaps");
+    let patch = serde_json::json!({ "quantity_required": 200 });
+    db.patch_order("ORD-001", &patch, None).unwrap();
+
+    let order = db.reserve_order("ORD-001").unwrap();
+    // 230 dials and 195 hands on hand against 200 and 400 needed.
+    assert_eq!(order.components_booked, Some(vec![200, 195]));
+    assert_eq!(order.total_gap_components, Some(vec![0, 205]));
+    assert_eq!(order.components_required, 600);
+}
+
+// ========== BOM EXPLOSION ==========
+
+fn bom_line(watch_id: &str, model_id: &str, component_id: &str, required_quantity: u64) -> Watches {
+    Watches {
+        watch_id: watch_id.to_string(),
+        watch_model_id: model_id.to_string(),
+        brand: "BrandX".to_string(),
+        component_id: component_id.to_string(),
+        required_quantity,
+        version: 0,
+    }
+}
+
+fn required(explosion: &BomExplosion, component_id: &str) -> (u64, u32, bool) {
+    let requirement = explosion.components
+        .iter()
+        .find(|requirement| requirement.component_id == component_id)
+        .unwrap();
+    (requirement.required_quantity, requirement.level, requirement.sub_assembly)
+}
+
+#[test]
+fn explosion_follows_sub_assemblies() {
+    let (db, _dir) = seeded("bom");
+    let explosion = db.explode_bom("BP-2023-001", 10).unwrap().unwrap();
+    assert_eq!(required(&explosion, "COMP-001"), (10, 1, false));
+    assert_eq!(required(&explosion, "COMP-002"), (20, 1, false));
+    assert!(explosion.components.iter().all(|requirement| requirement.shortfall == 0));
+
+    // Each set of hands now takes three dials as well.
+    db.create_watch(bom_line("WATCH-003", "COMP-002", "COMP-001", 3)).unwrap();
+    let explosion = db.explode_bom("BP-2023-001", 10).unwrap().unwrap();
+    assert_eq!(required(&explosion, "COMP-001"), (70, 1, false));
+    assert_eq!(required(&explosion, "COMP-002"), (20, 1, true));
+
+    assert!(db.explode_bom("NO-SUCH-MODEL", 10).unwrap().is_none());
+}
+
+#[test]
+fn bom_cycles_and_unknown_components_are_rejected() {
+    let (db, _dir) = seeded("bom-cycle");
+    assert!(
+        matches!(
+            db.create_watch(bom_line("WATCH-003", "BP-2023-001", "COMP-404", 1)),
+            Err(InventoryError::UnknownReference { .. })
+        )
+    );
+
+    // A sub-assembly model without a component record has no stock to take.
+    db.create_watch(bom_line("WATCH-005", "CASE-MODEL", "COMP-001", 1)).unwrap();
+    assert!(
+        matches!(
+            db.create_watch(bom_line("WATCH-003", "BP-2023-001", "CASE-MODEL", 1)),
+            Err(InventoryError::UnknownReference { .. })
+        )
+    );
+    db.delete_watch("WATCH-005", None).unwrap();
+
+    db.create_watch(bom_line("WATCH-003", "COMP-002", "COMP-001", 1)).unwrap();
+    db.create_watch(bom_line("WATCH-004", "COMP-001", "COMP-002", 1)).unwrap();
+    assert!(matches!(db.explode_bom("BP-2023-001", 1), Err(InventoryError::BomCycle(_))));
+
+    // A cycle aborts the reservation without touching the existing booking.
+    db.release_order("ORD-001").unwrap();
+    let patch = serde_json::json!({ "quantity_required": 1000 });
+    db.patch_order("ORD-001", &patch, None).unwrap();
+    assert!(matches!(db.reserve_order("ORD-001"), Err(InventoryError::BomCycle(_))));
+    assert_eq!(reserved(&db, "COMP-001"), 0);
+}
+
+// ========== PROCUREMENT PLANNING ==========
+
+fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+    NaiveDate::from_ymd_opt(year, month, day).unwrap()
+}
+
+fn reserve_quantity(db: &InventoryDB, order_id: &str, quantity: u64) {
+    let patch = serde_json::json!({ "quantity_required": quantity });
+    db.patch_order(order_id, &patch, None).unwrap();
+    db.reserve_order(order_id).unwrap();
+}
+
+#[test]
+fn regenerated_procurements_follow_shrinking_gaps() {
+    let (db, _dir) = seeded("procurements");
+    assert!(
+        matches!(
+            db.generate_procurements("ORD-001", None, date(2024, 1, 8), None),
+            Err(InventoryError::Validation(_))
+        )
+    );
+
+    reserve_quantity(&db, "ORD-001", 200);
+    let plan = db.generate_procurements("ORD-001", None, date(2024, 1, 8), None).unwrap();
+    assert_eq!(plan.procurements.procurements.len(), 1);
+    assert_eq!(plan.procurements.procurements[0].quantity, 205);
+    assert_eq!(plan.supplier_orders[0].components_roundof, 225);
+
+    // Running it again changes nothing.
+    let plan = db.generate_procurements("ORD-001", None, date(2024, 1, 8), None).unwrap();
+    assert_eq!(plan.procurements.procurements.len(), 1);
+
+    reserve_quantity(&db, "ORD-001", 100);
+    let plan = db.generate_procurements("ORD-001", None, date(2024, 1, 8), None).unwrap();
+    assert_eq!(plan.procurements.procurements[0].quantity, 5);
+    assert_eq!(plan.supplier_orders[0].components_roundof, 25);
+
+    reserve_quantity(&db, "ORD-001", 50);
+    let plan = db.generate_procurements("ORD-001", None, date(2024, 1, 8), None).unwrap();
+    assert!(plan.procurements.procurements.is_empty());
+    assert!(plan.supplier_orders.is_empty());
+    let supplier_order_id = "SUPP-ORD-ORD-001-COMP-002";
+    let stored = db.with_read_txn(|rtxn| db.suppliers_orders_db.get(rtxn, supplier_order_id)).unwrap();
+    assert!(stored.is_none());
+    let order = db.get_order("ORD-001").unwrap().unwrap();
+    assert!(!order.supplier_orders.unwrap().iter().any(|id| id == supplier_order_id));
+    assert!(!order.procurements.unwrap().iter().any(|id| id == "PROC-ORD-001-COMP-002"));
+}
+
+// ========== ASSEMBLY ==========
+
+/// Sample timeline cut down to `quantity` watches, which the assembly site
+/// has components for.
+fn small_assembly(db: &InventoryDB, quantity: u64) {
+    let patch = serde_json::json!({ "quantity": quantity });
+    db.patch_assembly_timeline("ASSEM-001", &patch, None).unwrap();
+}
+
+fn stock_at(db: &InventoryDB, component_id: &str, location_id: &str) -> u64 {
+    db.get_component(component_id).unwrap().unwrap().stock.get(location_id).copied().unwrap_or(0)
+}
+
+#[test]
+fn assembly_moves_components_through_the_line() {
+    let (db, _dir) = seeded("assembly");
+    small_assembly(&db, 10);
+    let started = db.start_assembly("ASSEM-001", date(2024, 3, 4), None).unwrap();
+    assert_eq!(started.assembly_status, AssemblyStatus::InProgress);
+    let component = db.get_component("COMP-002").unwrap().unwrap();
+    assert_eq!((component.stock["WURENLOS"], component.assembly_line), (50, 20));
+    // What went onto the line no longer counts as reserved for the order.
+    assert_eq!(component.reserver_for_orders, 80);
+    assert_eq!(db.get_order("ORD-001").unwrap().unwrap().components_booked, Some(vec![40, 80]));
+    assert!(
+        matches!(
+            db.start_assembly("ASSEM-001", date(2024, 3, 4), None),
+            Err(InventoryError::InvalidTransition { .. })
+        )
+    );
+
+    let scrapped = BTreeMap::from([("COMP-002".to_string(), 2)]);
+    let completed = db.complete_assembly("ASSEM-001", Some(8), scrapped, date(2024, 3, 6)).unwrap();
+    assert_eq!(completed.total_duration, 3);
+    let component = db.get_component("COMP-002").unwrap().unwrap();
+    assert_eq!((component.stock["WURENLOS"], component.assembly_line, component.waste), (52, 0, 3));
+    assert_eq!(db.get_product("PROD-001").unwrap().unwrap().stock["WURENLOS"], 208);
+}
+
+#[test]
+fn released_order_can_still_be_assembled() {
+    let (db, _dir) = seeded("assembly-released");
+    db.release_order("ORD-001").unwrap();
+    // The full sample timeline needs more hands than the site holds.
+    assert!(
+        matches!(
+            db.start_assembly("ASSEM-001", date(2024, 3, 4), None),
+            Err(InventoryError::InsufficientStock { .. })
+        )
+    );
+    small_assembly(&db, 10);
+    db.start_assembly("ASSEM-001", date(2024, 3, 4), None).unwrap();
+    assert_eq!(reserved(&db, "COMP-002"), 0);
+    assert_eq!(db.get_component("COMP-002").unwrap().unwrap().assembly_line, 20);
+}
+
+#[test]
+fn oversized_assembly_is_rejected_before_moving_stock() {
+    let (db, _dir) = seeded("assembly-overflow");
+    db.patch_watch("WATCH-001", &serde_json::json!({ "required_quantity": 4 }), None).unwrap();
+    small_assembly(&db, u64::MAX / 2 + 1);
+    assert!(
+        matches!(
+            db.start_assembly("ASSEM-001", date(2024, 3, 4), None),
+            Err(InventoryError::Validation(_))
+        )
+    );
+    assert_eq!(stock_at(&db, "COMP-001", "WURENLOS"), 80);
+
+    small_assembly(&db, 1000);
+    assert!(
+        matches!(
+            db.start_assembly("ASSEM-001", date(2024, 3, 4), None),
+            Err(InventoryError::InsufficientStock { .. })
+        )
+    );
+    assert_eq!(stock_at(&db, "COMP-001", "WURENLOS"), 80);
+}
+
+#[test]
+fn scrap_needs_a_scrap_location() {
+    let (db, _dir) = seeded("assembly-scrap");
+    small_assembly(&db, 10);
+    db.start_assembly("ASSEM-001", date(2024, 3, 4), None).unwrap();
+    db.patch_location("SCRAP", &serde_json::json!({ "active": false }), None).unwrap();
+
+    let scrapped = BTreeMap::from([("COMP-001".to_string(), 1)]);
+    assert!(
+        matches!(
+            db.complete_assembly("ASSEM-001", Some(9), scrapped, date(2024, 3, 5)),
+            Err(InventoryError::Validation(_))
+        )
+    );
+    let completed = db.complete_assembly("ASSEM-001", Some(9), BTreeMap::new(), date(2024, 3, 5)).unwrap();
+    assert_eq!(completed.produced_quantity, 9);
+}
+
+#[test]
+fn assemblies_only_produce_what_went_onto_the_line() {
+    let (db, _dir) = seeded("assembly-forged");
+    let mut forged = timeline(&db, "ASSEM-001").unwrap();
+    forged.assembly_id = String::new();
+    forged.assembly_status = AssemblyStatus::InProgress;
+    forged.produced_quantity = 50;
+    let created = db.create_assembly_timeline(forged).unwrap();
+    assert_eq!((created.assembly_status, created.produced_quantity), (AssemblyStatus::Planned, 0));
+
+    // Even a timeline stored as in progress yields nothing without start movements.
+    let mut unstarted = timeline(&db, &created.assembly_id).unwrap();
+    unstarted.assembly_status = AssemblyStatus::InProgress;
+    db.with_write_txn(|wtxn| db.store_assembly_timeline(wtxn, &mut unstarted)).unwrap();
+    let completed = db.complete_assembly(&created.assembly_id, None, BTreeMap::new(), date(2024, 3, 6));
+    assert!(matches!(completed, Err(InventoryError::InvalidMovement(_))));
+    assert_eq!(db.get_product("PROD-001").unwrap().unwrap().stock["WURENLOS"], 200);
+}
+
+#[test]
+fn started_assemblies_keep_their_quantity_and_site() {
+    let (db, _dir) = seeded("assembly-edit");
+    small_assembly(&db, 10);
+    db.start_assembly("ASSEM-001", date(2024, 3, 4), None).unwrap();
+    let patch = serde_json::json!({
+        "quantity": 5,
+        "assembly_location": "ST-JAKOB",
+        "assembly_end_date": "2024-03-08",
+        "assembly_notes": "rush",
+    });
+    let edited = db.patch_assembly_timeline("ASSEM-001", &patch, None).unwrap();
+    assert_eq!((edited.quantity, edited.assembly_location.as_str()), (10, "Wurenlos"));
+    assert_eq!(edited.assembly_notes.as_deref(), Some("rush"));
+
+    db.complete_assembly("ASSEM-001", None, BTreeMap::new(), date(2024, 3, 6)).unwrap();
+    assert_eq!(stock_at(&db, "COMP-002", "WURENLOS"), 50);
+    assert_eq!(db.get_product("PROD-001").unwrap().unwrap().stock["WURENLOS"], 210);
+}
+
+#[test]
+fn starting_hands_back_sub_assembly_bookings() {
+    let (db, _dir) = seeded("assembly-sub");
+    db.release_order("ORD-001").unwrap();
+    let stock = serde_json::json!({
+        "stock": { "CN": null, "KLING": null, "ST-JAKOB": null, "FLF": null, "WURENLOS": 30 },
+    });
+    db.patch_component("COMP-002", &stock, None).unwrap();
+    let mut blanks = db.get_component("COMP-001").unwrap().unwrap();
+    blanks.component_id = "COMP-003".to_string();
+    blanks.component_name = "Hand Blanks".to_string();
+    blanks.stock = BTreeMap::from([("WURENLOS".to_string(), 100)]);
+    db.create_component(blanks).unwrap();
+    db.create_watch(bom_line("WATCH-003", "COMP-002", "COMP-003", 1)).unwrap();
+    db.patch_order("ORD-001", &serde_json::json!({ "quantity_required": 20 }), None).unwrap();
+    db.reserve_order("ORD-001").unwrap();
+    // 30 hands on hand against 40 needed; the other 10 are built from blanks.
+    assert_eq!((reserved(&db, "COMP-002"), reserved(&db, "COMP-003")), (30, 10));
+
+    small_assembly(&db, 10);
+    db.start_assembly("ASSEM-001", date(2024, 3, 4), None).unwrap();
+    assert_eq!((reserved(&db, "COMP-002"), reserved(&db, "COMP-003")), (10, 5));
+}
+
+#[test]
+fn assemblies_of_closed_orders_cannot_start() {
+    let (db, _dir) = seeded("assembly-cancelled");
+    small_assembly(&db, 10);
+    db.transition_order("ORD-001", OrderStatus::Cancelled).unwrap();
+    assert!(
+        matches!(
+            db.start_assembly("ASSEM-001", date(2024, 3, 4), None),
+            Err(InventoryError::Conflict(_))
+        )
+    );
+    assert_eq!(stock_at(&db, "COMP-001", "WURENLOS"), 80);
+}
+
+#[test]
+fn clients_cannot_reset_the_assembly_line() {
+    let (db, _dir) = seeded("assembly-counter");
+    small_assembly(&db, 10);
+    db.start_assembly("ASSEM-001", date(2024, 3, 4), None).unwrap();
+    let patch = serde_json::json!({ "assembly_line": 0, "reserver_for_orders": 0 });
+    let component = db.patch_component("COMP-002", &patch, None).unwrap();
+    assert_eq!((component.assembly_line, component.reserver_for_orders), (20, 80));
+
+    db.complete_assembly("ASSEM-001", None, BTreeMap::new(), date(2024, 3, 6)).unwrap();
+    assert_eq!(db.get_component("COMP-002").unwrap().unwrap().assembly_line, 0);
+}
+
+// ========== SCHEDULING ==========
+
+/// Reserved pending order for `quantity` sample watches.
+fn open_order(db: &InventoryDB, order_id: &str, quantity: u64, ship_date: NaiveDate) {
+    let order: Orders = serde_json::from_value(
+        serde_json::json!({
+            "order_id": order_id,
+            "procurements": null,
+            "supplier_orders": null,
+            "quanity_ordered": quantity,
+            "product_id": "PROD-001",
+            "product": "BP Watch",
+            "quantity_required": quantity,
+            "expected_delivery_date": ship_date,
+            "production_start_date": ship_date,
+            "expected_ship_date": ship_date,
+            "recid": "REC-TEST",
+            "order_status": "Pending",
+            "total_components_booked": 0,
+            "components_notes": null,
+            "components_required": 0,
+            "total_gap_components": null,
+            "components": null,
+        })
+    ).unwrap();
+    db.create_order(order).unwrap();
+    db.reserve_order(order_id).unwrap();
+}
+
+fn timeline(db: &InventoryDB, id: &str) -> Option<AssemblyTimeLine> {
+    db.with_read_txn(|rtxn| db.assembly_timeline_db.get(rtxn, id)).unwrap()
+}
+
+fn proposed(schedule: &AssemblySchedule) -> Vec<&str> {
+    schedule.timelines.iter().map(|timeline| timeline.order.as_str()).collect()
+}
+
+#[test]
+fn orders_with_any_timeline_are_not_replanned() {
+    let (db, _dir) = seeded("schedule");
+    let today = date(2024, 3, 4);
+    assert!(db.schedule_assembly(today, false).unwrap().timelines.is_empty());
+
+    small_assembly(&db, 10);
+    db.start_assembly("ASSEM-001", today, None).unwrap();
+    db.complete_assembly("ASSEM-001", None, BTreeMap::new(), today).unwrap();
+    assert!(db.schedule_assembly(today, true).unwrap().timelines.is_empty());
+    assert_eq!(timeline(&db, "ASSEM-001").unwrap().assembly_status, AssemblyStatus::Completed);
+}
+
+#[test]
+fn persisted_schedule_never_overwrites_a_timeline() {
+    let (db, _dir) = seeded("schedule-persist");
+    let today = date(2024, 3, 4);
+    db.transition_assembly("ASSEM-001", AssemblyStatus::Cancelled).unwrap();
+    db.transition_order("ORD-001", OrderStatus::Cancelled).unwrap();
+    open_order(&db, "ORD-002", 60, date(2024, 3, 4));
+
+    // A dry run stores nothing.
+    let schedule = db.schedule_assembly(today, false).unwrap();
+    assert_eq!(proposed(&schedule), ["ORD-002"]);
+    assert!(timeline(&db, "ASSEM-ORD-002").is_none());
+    assert_eq!(schedule.late_orders.len(), 1);
+    assert_eq!(schedule.late_orders[0].assembly_end_date, date(2024, 3, 5));
+
+    let schedule = db.schedule_assembly(today, true).unwrap();
+    assert_eq!(schedule.timelines[0].version, 1);
+    assert_eq!(timeline(&db, "ASSEM-ORD-002").unwrap().assembly_status, AssemblyStatus::Planned);
+    assert_eq!(timeline(&db, "ASSEM-001").unwrap().assembly_status, AssemblyStatus::Cancelled);
+    assert!(db.schedule_assembly(today, true).unwrap().timelines.is_empty());
+
+    // The ID a proposal would get is taken by a timeline of another order.
+    open_order(&db, "ORD-003", 10, date(2024, 4, 1));
+    let mut squatter = timeline(&db, "ASSEM-ORD-002").unwrap();
+    squatter.assembly_id = "ASSEM-ORD-003".to_string();
+    db.create_assembly_timeline(squatter).unwrap();
+    db.transition_assembly("ASSEM-ORD-003", AssemblyStatus::Cancelled).unwrap();
+    let schedule = db.schedule_assembly(today, true).unwrap();
+    assert_eq!(schedule.timelines[0].assembly_id, "ASSEM-ORD-003-2");
+    assert_eq!(timeline(&db, "ASSEM-ORD-003").unwrap().order, "ORD-002");
+}
+
+#[test]
+fn orders_of_cancelled_timelines_are_planned_again() {
+    let (db, _dir) = seeded("schedule-cancelled");
+    let today = date(2024, 3, 4);
+    db.transition_assembly("ASSEM-001", AssemblyStatus::Cancelled).unwrap();
+    let schedule = db.schedule_assembly(today, true).unwrap();
+    assert_eq!(proposed(&schedule), ["ORD-001"]);
+    assert_eq!(timeline(&db, "ASSEM-ORD-001").unwrap().assembly_status, AssemblyStatus::Planned);
+}
+
+#[test]
+fn planned_timelines_hold_their_capacity() {
+    let (db, _dir) = seeded("schedule-capacity");
+    let today = date(2024, 3, 4);
+    db.transition_order("ORD-001", OrderStatus::Cancelled).unwrap();
+    open_order(&db, "ORD-002", 40, date(2024, 4, 1));
+    db.schedule_assembly(today, true).unwrap();
+
+    // The first order fills the day; the next one starts the day after.
+    open_order(&db, "ORD-003", 10, date(2024, 4, 1));
+    let schedule = db.schedule_assembly(today, false).unwrap();
+    assert_eq!(schedule.timelines[0].assembly_start_date, date(2024, 3, 5));
+}
+
+// ========== REORDER POINTS ==========
+
+#[test]
+fn leftovers_returned_from_the_line_are_not_usage() {
+    let (db, _dir) = seeded("usage");
+    small_assembly(&db, 10);
+    db.start_assembly("ASSEM-001", date(2024, 3, 4), None).unwrap();
+    db.complete_assembly("ASSEM-001", Some(6), BTreeMap::new(), date(2024, 3, 6)).unwrap();
+    assert_eq!(stock_at(&db, "COMP-001", "WURENLOS"), 74);
+
+    let usage = db.with_read_txn(|rtxn| db.daily_usage(rtxn, "COMP-001", date(2024, 3, 10), 30)).unwrap();
+    assert_eq!(usage.iter().sum::<u64>(), 6);
+    assert_eq!(usage[23], 6);
+    let usage = db.with_read_txn(|rtxn| db.daily_usage(rtxn, "COMP-002", date(2024, 3, 10), 30)).unwrap();
+    assert_eq!(usage.iter().sum::<u64>(), 12);
+}
+
+// ========== STATUS TRANSITIONS ==========
+
+#[test]
+fn orders_only_move_along_their_lifecycle() {
+    let (db, _dir) = seeded("order-status");
+    let back = db.transition_order("ORD-001", OrderStatus::Pending);
+    assert!(matches!(back, Err(InventoryError::InvalidTransition { .. })));
+    assert_eq!(db.get_order("ORD-001").unwrap().unwrap().order_status, OrderStatus::Processing);
+
+    db.transition_order("ORD-001", OrderStatus::Shipped).unwrap();
+    let cancelled = db.transition_order("ORD-001", OrderStatus::Cancelled);
+    assert!(matches!(cancelled, Err(InventoryError::InvalidTransition { .. })));
+    let order = db.transition_order("ORD-001", OrderStatus::Completed).unwrap();
+    assert_eq!(order.order_status, OrderStatus::Completed);
+    assert!(!OrderStatus::Completed.can_become(OrderStatus::Pending));
+}
+
+#[test]
+fn assemblies_cannot_skip_execution() {
+    let (db, _dir) = seeded("assembly-status");
+    // Execution states are only reached by starting and completing the work.
+    let skipped = db.transition_assembly("ASSEM-001", AssemblyStatus::Completed);
+    assert!(matches!(skipped, Err(InventoryError::Validation(_))));
+    let unstarted = db.complete_assembly("ASSEM-001", None, BTreeMap::new(), date(2024, 3, 6));
+    assert!(matches!(unstarted, Err(InventoryError::InvalidTransition { .. })));
+
+    let timeline = db.transition_assembly("ASSEM-001", AssemblyStatus::Planned).unwrap();
+    assert_eq!(timeline.assembly_status, AssemblyStatus::Planned);
+    assert!(db.transition_assembly("ASSEM-001", AssemblyStatus::Cancelled).is_ok());
+    let revived = db.transition_assembly("ASSEM-001", AssemblyStatus::Scheduled);
+    assert!(matches!(revived, Err(InventoryError::InvalidTransition { .. })));
+}
+
+#[test]
+fn status_patches_are_stored_once() {
+    let (db, _dir) = seeded("status-patch");
+    let before = db.get_order("ORD-001").unwrap().unwrap().version;
+    let patch = serde_json::json!({ "order_status": "Shipped", "quantity_required": 12 });
+    let order = db.patch_order("ORD-001", &patch, Some(&[before])).unwrap();
+    assert_eq!((order.order_status, order.version), (OrderStatus::Shipped, before + 1));
+    assert_eq!(db.get_order("ORD-001").unwrap().unwrap().version, before + 1);
+
+    let before = timeline(&db, "ASSEM-001").unwrap().version;
+    let patch = serde_json::json!({ "assembly_status": "Cancelled" });
+    let cancelled = db.patch_assembly_timeline("ASSEM-001", &patch, None).unwrap();
+    assert_eq!((cancelled.assembly_status, cancelled.version), (AssemblyStatus::Cancelled, before + 1));
+}
+
+#[test]
+fn new_records_start_at_their_initial_status() {
+    let (db, _dir) = seeded("initial-status");
+    let read = |rtxn: &RoTxn| {
+        Ok((
+            db.orders_db.get(rtxn, "ORD-001")?.unwrap(),
+            db.suppliers_orders_db.get(rtxn, "SUPP-ORD-001")?.unwrap(),
+            db.procurements_db.get(rtxn, "PROC-GROUP-001")?.unwrap(),
+        ))
+    };
+    let (mut order, mut supplier_order, mut group) = db.with_read_txn(read).unwrap();
+
+    order.order_id = String::new();
+    order.order_status = OrderStatus::Completed;
+    let created = db.create_order(order).unwrap();
+    assert_eq!(created.order_status, OrderStatus::Pending);
+    let mut put = db.get_order(&created.order_id).unwrap().unwrap();
+    put.order_id = "ORD-NEW".to_string();
+    put.order_status = OrderStatus::Cancelled;
+    assert_eq!(db.update_order(put, None).unwrap().order_status, OrderStatus::Pending);
+
+    supplier_order.order_id = "SUPP-ORD-NEW".to_string();
+    supplier_order.status = SupplierOrderStatus::Received;
+    let stored = db.update_supplier_order(supplier_order, None).unwrap();
+    assert_eq!(stored.status, SupplierOrderStatus::Draft);
+
+    group.procurement_id = String::new();
+    group.procurements[0].procurement_id = "PROC-NEW".to_string();
+    group.procurements[0].status = ProcurementStatus::Received;
+    let created = db.create_procurements(group).unwrap();
+    assert_eq!(created.procurements[0].status, ProcurementStatus::Pending);
+
+    let mut put = timeline(&db, "ASSEM-001").unwrap();
+    put.assembly_id = "ASSEM-NEW".to_string();
+    put.assembly_status = AssemblyStatus::InProgress;
+    assert_eq!(db.put_assembly_timeline(put, None).unwrap().assembly_status, AssemblyStatus::Planned);
+}
+
+// ========== PAGINATION ==========
+
+fn page_request(cursor: Option<String>, order: SortOrder) -> PageRequest {
+    PageRequest { cursor, limit: 2, order }
+}
+
+fn product_ids(page: &Page<Products>) -> Vec<&str> {
+    page.items.iter().map(|product| product.product_id.as_str()).collect()
+}
+
+#[test]
+fn cursors_walk_every_record_once() {
+    let (db, _dir) = seeded("pages");
+    for (id, name) in [("PROD-002", "Diver"), ("PROD-003", "Pilot"), ("PROD-004", "Dress")] {
+        db.create_product(product(id, name)).unwrap();
+    }
+    let filter = ProductFilter::default();
+
+    let first = db.list_products(&filter, &page_request(None, SortOrder::Asc)).unwrap();
+    assert_eq!(product_ids(&first), ["PROD-001", "PROD-002"]);
+    let second = db.list_products(&filter, &page_request(first.next_cursor, SortOrder::Asc)).unwrap();
+    assert_eq!(product_ids(&second), ["PROD-003", "PROD-004"]);
+    assert_eq!(second.next_cursor, None);
+
+    let first = db.list_products(&filter, &page_request(None, SortOrder::Desc)).unwrap();
+    assert_eq!(product_ids(&first), ["PROD-004", "PROD-003"]);
+    let second = db.list_products(&filter, &page_request(first.next_cursor, SortOrder::Desc)).unwrap();
+    assert_eq!(product_ids(&second), ["PROD-002", "PROD-001"]);
+}
+
+#[test]
+fn indexed_pages_follow_primary_key_order() {
+    let (db, _dir) = seeded("index-pages");
+    for id in ["ORD-004", "ORD-002", "ORD-003"] {
+        open_order(&db, id, 5, date(2024, 4, 1));
+    }
+    let filter = OrderFilter { status: Some(OrderStatus::Pending), ..OrderFilter::default() };
+
+    let first = db.list_orders(&filter, &page_request(None, SortOrder::Asc)).unwrap();
+    let ids: Vec<_> = first.items.iter().map(|order| order.order_id.as_str()).collect();
+    assert_eq!(ids, ["ORD-002", "ORD-003"]);
+    assert_eq!(first.next_cursor.as_deref(), Some("ORD-003"));
+    let second = db.list_orders(&filter, &page_request(first.next_cursor, SortOrder::Asc)).unwrap();
+    let ids: Vec<_> = second.items.iter().map(|order| order.order_id.as_str()).collect();
+    assert_eq!(ids, ["ORD-004"]);
+    assert_eq!(second.next_cursor, None);
+}
+
+// ========== SECONDARY INDEXES ==========
+
+fn orders_in(db: &InventoryDB, status: OrderStatus) -> Vec<String> {
+    db.with_read_txn(|rtxn| db.orders_by_status.keys(rtxn, &status.to_string())).unwrap()
+}
+
+#[test]
+fn indexes_follow_every_write() {
+    let (db, _dir) = seeded("index-writes");
+    assert_eq!(orders_in(&db, OrderStatus::Processing), ["ORD-001"]);
+
+    small_assembly(&db, 10);
+    db.start_assembly("ASSEM-001", date(2024, 3, 4), None).unwrap();
+    let moved = db.with_read_txn(|rtxn| db.movements_by_component.keys(rtxn, "COMP-002")).unwrap();
+    let on_day = db.with_read_txn(|rtxn| db.movements_by_date.keys(rtxn, "2024-03-04")).unwrap();
+    assert_eq!(on_day.len(), 2);
+    // One leg per component, each listed under its component.
+    assert_eq!(on_day.iter().filter(|key| moved.contains(key)).count(), 1);
+
+    db.transition_order("ORD-001", OrderStatus::Cancelled).unwrap();
+    assert!(orders_in(&db, OrderStatus::Processing).is_empty());
+    assert_eq!(orders_in(&db, OrderStatus::Cancelled), ["ORD-001"]);
+}
+
+#[test]
+fn rebuilt_indexes_match_the_maintained_ones() {
+    let (db, _dir) = seeded("index-rebuild");
+    open_order(&db, "ORD-002", 5, date(2024, 4, 1));
+    let entries = |db: &InventoryDB| {
+        db.with_read_txn(|rtxn| {
+            Ok(
+                db.orders_by_status.len(rtxn)? +
+                    db.orders_by_product.len(rtxn)? +
+                    db.movements_by_date.len(rtxn)? +
+                    db.movements_by_component.len(rtxn)? +
+                    db.supplier_orders_by_supplier.len(rtxn)? +
+                    db.timelines_by_order.len(rtxn)?
+            )
+        }).unwrap()
+    };
+    let maintained = entries(&db);
+
+    db.with_write_txn(|wtxn| db.clear_indexes(wtxn)).unwrap();
+    assert!(orders_in(&db, OrderStatus::Pending).is_empty());
+    assert_eq!(db.rebuild_indexes().unwrap(), maintained);
+    assert_eq!(orders_in(&db, OrderStatus::Pending), ["ORD-002"]);
+}
+
+// ========== MERGE PATCH ==========
+
+#[test]
+fn merge_patch_follows_rfc_7386() {
+    let mut target = serde_json::json!({ "a": { "b": 1, "c": 2 }, "d": [1], "e": "x" });
+    merge_patch(&mut target, &serde_json::json!({ "a": { "b": null, "f": 3 }, "d": [2], "e": null }));
+    assert_eq!(target, serde_json::json!({ "a": { "c": 2, "f": 3 }, "d": [2] }));
+}
+
+#[test]
+fn catalogue_items_are_patched_in_place() {
+    let (db, _dir) = seeded("catalogue-patch");
+    let stored = db.get_catalogue_item("SUPP-001", "COMP-002").unwrap().unwrap();
+    let patch = serde_json::json!({
+        "supplier_id": "SUPP-404",
+        "moq": 50,
+        "price_breaks": [{ "min_quantity": 250, "unit_price": 3.5 }, { "min_quantity": 1, "unit_price": 4.0 }],
+    });
+    let item = db.patch_catalogue_item("SUPP-001", "COMP-002", &patch, Some(&[stored.version])).unwrap();
+    assert_eq!((item.supplier_id.as_str(), item.moq, item.pack_size), ("SUPP-001", 50, 25));
+    assert_eq!(item.price_breaks[0].min_quantity, 1);
+    assert_eq!(item.version, stored.version + 1);
+
+    let stale = db.patch_catalogue_item("SUPP-001", "COMP-002", &patch, Some(&[stored.version]));
+    assert!(matches!(stale, Err(InventoryError::PreconditionFailed { .. })));
+    let invalid = serde_json::json!({ "pack_size": 0 });
+    let invalid = db.patch_catalogue_item("SUPP-001", "COMP-002", &invalid, None);
+    assert!(matches!(invalid, Err(InventoryError::InvalidFields(_))));
+    let missing = db.patch_catalogue_item("SUPP-001", "COMP-404", &patch, None);
+    assert!(matches!(missing, Err(InventoryError::NotFound(_))));
+}
+
+// ========== CONDITIONAL REQUESTS ==========
+
+async fn put_product(db: &Arc<InventoryDB>, if_match: &str, name: &str) -> actix_web::dev::ServiceResponse {
+    use actix_web::test;
+
+    let state = routes::AppState { db: db.clone(), reorder_policy: Config::default().reorder_policy() };
+    let app = test::init_service(
+        App::new().app_data(web::Data::new(state)).configure(routes::init_routes)
+    ).await;
+    let request = test::TestRequest::put()
+        .uri("/api/products/PROD-001")
+        .insert_header((actix_web::http::header::IF_MATCH, if_match))
+        .set_json(product("PROD-001", name))
+        .to_request();
+    test::call_service(&app, request).await
+}
+
+#[actix_web::test]
+async fn if_match_compares_strong_tags_only() {
+    let (db, _dir) = seeded("if-match");
+    let db = Arc::new(db);
+    let version = db.get_product("PROD-001").unwrap().unwrap().version;
+
+    let weak = put_product(&db, &format!("W/\"{}\"", version), "BP Watch").await;
+    assert_eq!(weak.status(), 412);
+    let stale = put_product(&db, &format!("\"{}\"", version + 5), "BP Watch").await;
+    assert_eq!(stale.status(), 412);
+    let malformed = put_product(&db, "1", "BP Watch").await;
+    assert_eq!(malformed.status(), 400);
+
+    let tags = format!("\"abc\", W/\"{}\", \"{}\"", version, version);
+    let listed = put_product(&db, &tags, "BP Watch II").await;
+    assert_eq!(listed.status(), 200);
+    let etag = listed.headers().get(actix_web::http::header::ETAG).unwrap().to_str().unwrap().to_string();
+    assert_eq!(etag, format!("\"{}\"", version + 1));
+    let body: Value = actix_web::test::read_body_json(listed).await;
+    assert_eq!(body["data"]["product_name"], "BP Watch II");
+    assert_eq!(body["data"]["version"], version + 1);
+
+    assert_eq!(put_product(&db, "*", "BP Watch").await.status(), 200);
+}
+
+#[actix_web::test]
+async fn actions_tag_the_record_they_return() {
+    use actix_web::test;
+
+    let (db, _dir) = seeded("action-etag");
+    let db = Arc::new(db);
+    let state = routes::AppState { db: db.clone(), reorder_policy: Config::default().reorder_policy() };
+    let app = test::init_service(
+        App::new().app_data(web::Data::new(state)).configure(routes::init_routes)
+    ).await;
+    let request = test::TestRequest::post()
+        .uri("/api/orders/ORD-001/status")
+        .set_json(serde_json::json!({ "status": "Shipped" }))
+        .to_request();
+    let response = test::call_service(&app, request).await;
+    assert_eq!(response.status(), 200);
+    let version = db.get_order("ORD-001").unwrap().unwrap().version;
+    let etag = response.headers().get(actix_web::http::header::ETAG).unwrap();
+    assert_eq!(etag.to_str().unwrap(), format!("\"{}\"", version));
+}